@@ -1,14 +1,34 @@
-use clap::{builder::ArgPredicate, value_parser, CommandFactory, Parser};
+use anyhow::bail;
+use clap::{builder::ArgPredicate, value_parser, CommandFactory, Parser, Subcommand};
 use clap_complete::Shell;
 use flexi_logger::Logger;
 use log::{debug, error, warn};
 use regex::Regex;
 use std::{
+    cell::RefCell,
     env::consts::{ARCH, OS},
-    path::Path,
+    io::Read,
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    rc::Rc,
+    time::Duration,
 };
 
-use crate::{constants, log_format, Evaluation, NixpkgsChannelVersion};
+use crate::{
+    binary_cache, constants,
+    formats::{BadgeStyle, OutputFormat, Sink, Template},
+    hydra_url::HydraUrl,
+    job_filter::JobFilter,
+    log_format,
+    pagination::{self, Pagination},
+    pins::PinFormat,
+    queries::{
+        normalize_store_path, print_json_schemas, ExportedJobset, ExporterQuery, LandedQuery,
+        SystemVersion,
+    },
+    reproduce::ReproduceFormat,
+    Evaluation, NixpkgsChannelVersion,
+};
 
 const DEFAULT_CHANNEL: &str = "unstable";
 
@@ -17,10 +37,21 @@ pub(crate) enum Queries {
     Jobset,
     Packages(Vec<String>),
     Evals(Vec<Evaluation>),
+    Builds(Vec<u64>),
+    StorePaths(Vec<String>),
+    Search(String),
+    Projects,
+    Jobsets(String),
+    JobsetConfig,
+    Queue,
+    Machines,
+    System(SystemVersion),
+    Landed(LandedQuery),
+    Exporter(ExporterQuery),
 }
 
 #[derive(Parser, Debug, Default)]
-#[command(author, version, verbatim_doc_comment)]
+#[command(author, version, verbatim_doc_comment, disable_help_subcommand = true)]
 #[allow(
     rustdoc::bare_urls,
     clippy::doc_markdown,
@@ -42,22 +73,73 @@ pub(crate) enum Queries {
 ///   nixpkgs/nixpkgs-24.05-darwin
 ///
 /// Jobset names can be constructed with the project name (e.g. `nixos/` or `nixpkgs/`)
-/// followed by a branch name. The available jobsets can be found at:
+/// followed by a branch name. The available jobsets can be listed with
+/// `hydra-check jobsets nixos` or `hydra-check jobsets nixpkgs`, or found at:
 ///   - https://hydra.nixos.org/project/nixos
 ///   - https://hydra.nixos.org/project/nixpkgs
 ///
+/// Hydra urls of builds, evals, jobs and jobsets can also be used as queries,
+/// which determines the jobset and job to check, for example:
+///
+///   https://hydra.nixos.org/job/nixos/release-24.05/nixpkgs.hello.x86_64-linux
+///
+/// Store paths and derivations (e.g. /nix/store/<hash>-hello-2.12.1) can be
+/// queried for the Hydra builds that produced them. Pass `-` as a query to
+/// read the queries from stdin, e.g. `nix-store -qR <path> | hydra-check -`.
+///
 pub struct HydraCheckCli {
     #[arg(id = "PACKAGES")]
     queries: Vec<String>,
 
+    #[command(subcommand)]
+    command: Option<Commands>,
+
     /// Only print the hydra build url, then exit
-    #[arg(long)]
+    #[arg(long, global = true)]
     url: bool,
 
-    /// Output json
-    #[arg(long)]
+    /// Output json, wrapped in a versioned envelope
+    /// `{ "schema_version": 1, "kind": "packages", "data": ... }`;
+    /// alias for `--format json`
+    #[arg(long, global = true, conflicts_with = "format")]
     json: bool,
 
+    /// Output format of the reports
+    #[arg(long, global = true, value_enum, default_value_t)]
+    format: OutputFormat,
+
+    /// Print each row of the reports with a template, e.g.
+    /// '{icon} {name} {arch} {build_url}', with the helpers `{field:<20}`
+    /// for padding, `{field:.7}` for truncating and `{field?then|else}`
+    /// for conditions; the fields are those of --json and --format csv
+    #[arg(
+        long,
+        global = true,
+        value_name = "TEMPLATE",
+        value_parser = Template::parse,
+        conflicts_with_all = ["format", "json"]
+    )]
+    template: Option<Template>,
+
+    /// Write the document of `--format html`, `--format junit`,
+    /// `--format openmetrics`, `--format atom` or `--format svg-badge`
+    /// to a file instead of stdout
+    #[arg(short, long, global = true, value_name = "PATH")]
+    output: Option<PathBuf>,
+
+    /// Label of the badge of `--format svg-badge`,
+    /// instead of the job, jobset or channel
+    #[arg(long, global = true, value_name = "TEXT")]
+    badge_label: Option<String>,
+
+    /// Style of the badge of `--format svg-badge`
+    #[arg(long, global = true, value_enum, default_value_t)]
+    badge_style: BadgeStyle,
+
+    /// Print the JSON Schemas of the --json output of every kind of query
+    #[arg(long, exclusive = true)]
+    json_schema: bool,
+
     /// Write only the latest build even if last build failed
     #[arg(short, long)]
     short: bool,
@@ -70,6 +152,57 @@ pub struct HydraCheckCli {
     )]
     long: bool,
 
+    /// Collect at most N entries, following Hydra's pagination if needed
+    #[arg(long, value_name = "N", global = true)]
+    limit: Option<usize>,
+
+    /// Only collect entries from this date on, e.g. 2025-06-01
+    #[arg(
+        long,
+        global = true,
+        value_name = "DATE",
+        value_parser = pagination::parse_since
+    )]
+    since: Option<i64>,
+
+    /// Only collect entries up to this date, e.g. 2025-06-30
+    #[arg(
+        long,
+        global = true,
+        value_name = "DATE",
+        value_parser = pagination::parse_until
+    )]
+    until: Option<i64>,
+
+    /// Check whether the build outputs are available in a binary cache,
+    /// https://cache.nixos.org by default, or any other substituter
+    /// such as a local `file://` cache given as `--check-cache=URL`
+    #[arg(
+        long,
+        value_name = "URL",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = constants::DEFAULT_BINARY_CACHE_URL,
+        value_parser = binary_cache::parse_cache_url
+    )]
+    check_cache: Option<String>,
+
+    /// Print commands that rebuild the (latest) build locally at the
+    /// revision of nixpkgs it was built from, or Hydra's reproduce script
+    #[arg(
+        long,
+        value_name = "FORMAT",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "nix"
+    )]
+    reproduce: Option<ReproduceFormat>,
+
+    /// Print a snippet that pins nixpkgs to the revision of the (latest
+    /// successful) build, evaluation or published release, e.g. for a `flake.nix`
+    #[arg(long, value_name = "FORMAT")]
+    pin_format: Option<PinFormat>,
+
     /// System architecture to check
     #[arg(short, long)]
     arch: Option<String>,
@@ -82,10 +215,32 @@ pub struct HydraCheckCli {
     #[arg(long, conflicts_with = "channel")]
     jobset: Option<String>,
 
-    /// Print details about specific evaluations instead of packages
-    #[arg(short, long)]
+    /// Print details about specific evaluations instead of packages;
+    /// evaluations are specified by their ID, by `rev:<commit>` for the
+    /// input revision (at least 7 hex digits), or by `@<date>` for the
+    /// newest one at that date, optionally followed by a package filter,
+    /// e.g. `rev:1e9e641a/hello`
+    #[arg(short, long, verbatim_doc_comment)]
     eval: bool,
 
+    /// Only show the builds of an evaluation whose job names match REGEX
+    #[arg(long = "match", value_name = "REGEX", conflicts_with = "glob")]
+    match_regex: Option<String>,
+
+    /// Only show the builds of an evaluation whose job names match PATTERN,
+    /// e.g. 'python312Packages.*'
+    #[arg(long, value_name = "PATTERN")]
+    glob: Option<String>,
+
+    /// Only show the builds of an evaluation for SYSTEM, e.g. aarch64-linux
+    #[arg(long, value_name = "SYSTEM")]
+    system: Option<String>,
+
+    /// Summarize the failing builds of an evaluation per status,
+    /// per system and per top-level attribute, instead of listing them
+    #[arg(long)]
+    summary: bool,
+
     /// Query the release tests of the given channel (jobset)
     #[arg(
         short, long, conflicts_with_all = ["PACKAGES", "eval"],
@@ -98,8 +253,12 @@ pub struct HydraCheckCli {
     #[arg(short, long, conflicts_with_all = ["PACKAGES", "eval"])]
     releases: bool,
 
+    /// Show the configuration and the declared inputs of the jobset
+    #[arg(long, conflicts_with_all = ["PACKAGES", "eval", "tests"])]
+    config: bool,
+
     /// Print more debugging information
-    #[arg(short, long)]
+    #[arg(short, long, global = true)]
     verbose: bool,
 
     /// Print generated completions for a given shell
@@ -107,19 +266,97 @@ pub struct HydraCheckCli {
     shell: Option<Shell>,
 }
 
+/// Other kinds of queries than the build status of packages
+#[derive(Subcommand, Debug, Clone)]
+enum Commands {
+    /// Search for projects, jobsets and jobs whose names contain TERM
+    Search {
+        /// Search term, e.g. `python312Packages.requests`
+        term: String,
+    },
+    /// List the projects of the Hydra instance
+    Projects,
+    /// List the jobsets of a project, with the status of their latest evals
+    Jobsets {
+        /// Project name, e.g. `nixpkgs`
+        project: String,
+    },
+    /// Summarize the build queue per system and per jobset
+    Queue,
+    /// List the build machines with their systems and current load
+    Machines,
+    /// Find the evaluation of the running NixOS system in its channel,
+    /// and report how far the system is behind
+    #[command(name = "self")]
+    System,
+    /// Check whether a nixpkgs commit has reached the given channels
+    /// (or --channel), using a local git checkout of nixpkgs
+    Landed {
+        /// Nixpkgs commit, e.g. the merge commit of a pull request
+        commit: String,
+        /// Channels to check, e.g. `nixos-unstable` or `24.11`
+        channels: Vec<String>,
+        /// Path to a local git checkout of nixpkgs
+        #[arg(long, value_name = "PATH", default_value = ".")]
+        nixpkgs_repo: PathBuf,
+    },
+    /// Serve Prometheus metrics of the evaluations and releases of the
+    /// given channels (or --channel), and of the latest builds of PACKAGES,
+    /// or print them once with `--format openmetrics`
+    Exporter {
+        /// Packages to report the latest build of, e.g. `hello`
+        packages: Vec<String>,
+        /// Channels to report, e.g. `nixos-unstable,24.11`
+        #[arg(long, value_name = "CHANNEL", value_delimiter = ',')]
+        channels: Vec<String>,
+        /// Address to serve `/metrics` on
+        #[arg(long, value_name = "ADDR", default_value = "127.0.0.1:9850")]
+        listen: SocketAddr,
+        /// Seconds to serve the same metrics for, before fetching them again
+        #[arg(long, value_name = "SECONDS", default_value_t = 300)]
+        cache_ttl: u64,
+    },
+}
+
 /// Resolved command line arguments, with all options normalized and unwrapped
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 #[allow(clippy::struct_excessive_bools)]
 pub(crate) struct ResolvedArgs {
     /// List of packages or evals to query
     pub(crate) queries: Queries,
     pub(crate) url: bool,
-    pub(crate) json: bool,
+    pub(crate) format: OutputFormat,
+    pub(crate) output: Option<PathBuf>,
     pub(crate) short: bool,
     pub(crate) long: bool,
+    pub(crate) pagination: Pagination,
+    pub(crate) job_filter: JobFilter,
+    pub(crate) summary: bool,
+    pub(crate) check_cache: Option<String>,
+    pub(crate) reproduce: Option<ReproduceFormat>,
+    pub(crate) pin_format: Option<PinFormat>,
     pub(crate) releases: bool,
     pub(crate) channel: Option<String>,
     pub(crate) jobset: String,
+    /// Shared by the clones of the arguments, e.g. for each channel
+    pub(crate) sink: Rc<RefCell<Sink>>,
+}
+
+/// Finds the aggregate job for the release tests of a channel jobset;
+/// see the `job` keys in:
+/// - <https://github.com/NixOS/infra/blob/main/channels.nix>, and
+/// - <https://status.nixos.org/>
+pub(crate) fn aggregate_job(jobset: &str) -> &'static str {
+    match jobset {
+        x if x.ends_with("darwin") => "darwin-tested",
+        x if x.starts_with("nixpkgs/") => "unstable",
+        x if x.starts_with("nixos/") => "tested",
+        _ => {
+            let default = "tested";
+            warn!("unknown --jobset '{jobset}', assuming job '{default}' for release tests");
+            default
+        }
+    }
 }
 
 impl HydraCheckCli {
@@ -292,56 +529,259 @@ impl HydraCheckCli {
                 error!("--jobset is not properly set up or deduced");
                 std::process::exit(1);
             };
-            // aggregate job for channel release tests; see the `job` keys in:
-            // - https://github.com/NixOS/infra/blob/main/channels.nix, and
-            // - https://status.nixos.org/
-            //
-            let aggregate_job = match jobset.as_str() {
-                x if x.ends_with("darwin") => "darwin-tested",
-                x if x.starts_with("nixpkgs/") => "unstable",
-                x if x.starts_with("nixos/") => "tested",
-                _ => {
-                    let default = "tested";
-                    warn!(
-                        "unknown --jobset '{jobset}', assuming job '{default}' for release tests"
-                    );
-                    default
-                }
-            };
-            return vec![aggregate_job.into()];
+            return vec![aggregate_job(jobset).into()];
         }
         self.queries
             .iter()
-            .filter_map(|package| {
-                if package.starts_with("python3Packages") || package.starts_with("python3.pkgs") {
-                    error!(
-                        "instead of '{package}', you want {}",
-                        "python3xPackages... (e.g. python311Packages)"
-                    );
-                    None
-                } else {
-                    Some(self.guess_package_name(package))
+            .map(|package| self.guess_package_name(package))
+            .collect()
+    }
+
+    /// Replaces the query `-` with the (whitespace separated) queries read
+    /// from stdin, e.g. a closure dumped by `nix-store -qR`.
+    fn read_stdin_queries(self) -> anyhow::Result<Self> {
+        if !self.queries.iter().any(|query| query == "-") {
+            return Ok(self);
+        }
+        let mut input = String::new();
+        std::io::stdin().read_to_string(&mut input)?;
+        let queries = self
+            .queries
+            .iter()
+            .flat_map(|query| match query.as_str() {
+                "-" => input.split_whitespace().map(str::to_string).collect(),
+                _ => vec![query.clone()],
+            })
+            .collect();
+        Ok(Self { queries, ..self })
+    }
+
+    /// Resolves the jobsets of the given channels, or else returns
+    /// the already resolved --channel and --jobset.
+    fn guess_channels(&self, channels: &[String]) -> Vec<(Option<String>, String)> {
+        let jobset = |args: Self| {
+            let jobset = args
+                .jobset
+                .expect("jobset should be resolved by `guess_jobset()`");
+            (args.channel, jobset)
+        };
+        if channels.is_empty() {
+            return vec![jobset(Self {
+                channel: self.channel.clone(),
+                jobset: self.jobset.clone(),
+                ..Default::default()
+            })];
+        }
+        channels
+            .iter()
+            .map(|channel| {
+                let args = Self {
+                    channel: Some(channel.clone()),
+                    arch: self.arch.clone(),
+                    ..Default::default()
+                };
+                jobset(args.guess_jobset())
+            })
+            .collect()
+    }
+
+    /// Resolves the jobsets of the given channels (or --channel) for
+    /// `hydra-check exporter`, with the job names of the packages in each.
+    fn guess_exported_jobsets(
+        &self,
+        packages: &[String],
+        channels: &[String],
+    ) -> Vec<ExportedJobset> {
+        self.guess_channels(channels)
+            .into_iter()
+            .map(|(channel, jobset)| {
+                let args = Self {
+                    jobset: Some(jobset.clone()),
+                    arch: self.arch.clone(),
+                    ..Default::default()
+                };
+                let jobs = packages
+                    .iter()
+                    .map(|x| args.guess_package_name(x))
+                    .collect();
+                ExportedJobset {
+                    channel,
+                    jobset,
+                    jobs,
                 }
             })
             .collect()
     }
 
-    fn guess_evals(&self) -> Vec<Evaluation> {
+    /// Reads the version of the running NixOS system for `hydra-check self`,
+    /// which implies the --channel unless --channel or --jobset is specified.
+    fn guess_system(self) -> anyhow::Result<(Self, Option<SystemVersion>)> {
+        if !matches!(self.command, Some(Commands::System)) {
+            return Ok((self, None));
+        }
+        let system = SystemVersion::detect()?;
+        if self.channel.is_some() || self.jobset.is_some() {
+            return Ok((self, Some(system)));
+        }
+        let channel = match NixpkgsChannelVersion::stable() {
+            Ok(stable) => system.channel(stable),
+            Err(err) => {
+                warn!("could not fetch the stable release version number: {err}");
+                "nixos-unstable".into()
+            }
+        };
+        debug!("the running system implies --channel '{channel}'");
+        let args = Self {
+            channel: Some(channel),
+            ..self
+        };
+        Ok((args, Some(system)))
+    }
+
+    /// Recognizes store paths passed as queries, which are looked up for
+    /// the Hydra builds that produced them.
+    fn guess_store_paths(&self) -> anyhow::Result<Option<Queries>> {
+        let store_dir = constants::NIX_STORE_DIR;
+        let is_store_path = |query: &&String| query.trim().starts_with(store_dir);
+        let count = self.queries.iter().filter(is_store_path).count();
+        if count == 0 {
+            return Ok(None);
+        }
+        if count != self.queries.len() {
+            bail!(
+                "store paths cannot be mixed with other queries: {:?}",
+                self.queries
+            );
+        }
+        let mut paths = Vec::new();
+        for query in &self.queries {
+            let path = normalize_store_path(query)?;
+            if !paths.contains(&path) {
+                paths.push(path);
+            }
+        }
+        Ok(Some(Queries::StorePaths(paths)))
+    }
+
+    /// Recognizes Hydra urls passed as queries, which determine the kind of
+    /// the queries. The jobset and job names are extracted from the urls,
+    /// overriding the guesses of [`Self::guess_jobset`] and
+    /// [`Self::guess_package_name`].
+    fn guess_from_urls(self) -> anyhow::Result<(Self, Option<Queries>)> {
+        let urls = self
+            .queries
+            .iter()
+            .filter_map(|query| HydraUrl::parse(query))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        if urls.is_empty() {
+            return Ok((self, None));
+        }
+        if urls.len() != self.queries.len() {
+            bail!(
+                "Hydra urls cannot be mixed with other queries: {:?}",
+                self.queries
+            );
+        }
+        let (mut builds, mut evals, mut jobs, mut jobsets) = (vec![], vec![], vec![], vec![]);
+        for url in urls {
+            match url {
+                HydraUrl::Build(id) => builds.push(id),
+                HydraUrl::Eval { id, filter, full } => {
+                    // an eval url without a filter shows the whole eval:
+                    let spec = format!("{id}/{}", filter.unwrap_or_default());
+                    let long = full || self.long || self.pagination().is_active();
+                    evals.push(Evaluation::guess_from_spec(&spec, long, None)?);
+                }
+                HydraUrl::Job { jobset, job } => jobs.push((jobset, job)),
+                HydraUrl::Jobset(jobset) => jobsets.push(jobset),
+            }
+        }
+        let kinds = [builds.len(), evals.len(), jobs.len(), jobsets.len()];
+        if kinds.iter().filter(|x| **x > 0).count() > 1 {
+            bail!(
+                "Hydra urls of builds, evals, jobs and jobsets cannot be mixed: {:?}",
+                self.queries
+            );
+        }
+        let jobset = match (jobs.first(), jobsets.as_slice()) {
+            (Some((jobset, _)), _) | (None, [jobset]) => jobset.clone(),
+            (None, []) => {
+                let queries = match builds.is_empty() {
+                    true => Queries::Evals(evals),
+                    false => Queries::Builds(builds),
+                };
+                return Ok((self, Some(queries)));
+            }
+            (None, _) => bail!("only a single jobset url can be queried: {jobsets:?}"),
+        };
+        if jobs.iter().any(|(x, _)| *x != jobset) {
+            bail!("only jobs from the same jobset can be queried: {jobs:?}");
+        }
+        debug!("--jobset '{jobset}' is extracted from the url");
+        let queries = match jobs.is_empty() {
+            // the jobset may still be queried for --eval or --tests
+            true => None,
+            false => Some(Queries::Packages(
+                jobs.into_iter().map(|(_, job)| job).collect(),
+            )),
+        };
+        let args = Self {
+            queries: vec![],
+            jobset: Some(jobset),
+            channel: None,
+            ..self
+        };
+        Ok((args, queries))
+    }
+
+    fn pagination(&self) -> Pagination {
+        Pagination {
+            limit: self.limit,
+            since: self.since,
+            until: self.until,
+        }
+    }
+
+    /// Resolves the output format, which `--json` and `--template` imply.
+    fn output_format(&self) -> OutputFormat {
+        match (self.json, &self.template) {
+            (true, _) => OutputFormat::Json,
+            (false, Some(_)) => OutputFormat::Template,
+            (false, None) => self.format,
+        }
+    }
+
+    fn job_filter(&self) -> anyhow::Result<JobFilter> {
+        JobFilter::new(
+            self.match_regex.as_deref(),
+            self.glob.as_deref(),
+            self.system.clone(),
+        )
+    }
+
+    fn guess_evals(&self, job_filter: &JobFilter) -> anyhow::Result<Vec<Evaluation>> {
+        // paginated, filtered and summarized builds of an eval are taken from the full listing
+        let long =
+            self.long || self.summary || self.pagination().is_active() || job_filter.is_active();
+        let hint = job_filter.hint.as_deref();
         if self.queries.is_empty() {
             // this would resolve to the latest eval of a jobset:
-            return vec![Evaluation::guess_from_spec("", self.long)];
+            return Ok(vec![Evaluation::guess_from_spec("", long, hint)?]);
         }
-        let mut evals = Vec::new();
-        for spec in &self.queries {
-            evals.push(Evaluation::guess_from_spec(spec, self.long));
-        }
-        evals
+        self.queries
+            .iter()
+            .map(|spec| Evaluation::guess_from_spec(spec, long, hint))
+            .collect()
     }
 
     /// Parses the command line flags and calls [`Self::guess_all_args()`].
-    /// Also prints shell completions if asked for.
+    /// Also prints shell completions or JSON Schemas if asked for.
     pub(crate) fn parse_and_guess() -> anyhow::Result<ResolvedArgs> {
         let args = Self::parse();
+        if args.json_schema {
+            print_json_schemas()?;
+            std::process::exit(0);
+        }
         if let Some(shell) = args.shell {
             // generate shell completions
             let mut cmd = Self::command();
@@ -387,24 +827,98 @@ impl HydraCheckCli {
             true => log::LevelFilter::Trace,
         };
         Logger::with(log_level).format(log_format).start()?;
-        let args = args.guess_arch();
+        let args = args.guess_arch().read_stdin_queries()?;
+        let job_filter = args.job_filter()?;
+        let (args, queries) = match args.guess_store_paths()? {
+            Some(queries) => (args, Some(queries)),
+            None => args.guess_from_urls()?,
+        };
+        let (args, system) = args.guess_system()?;
         let args = args.guess_jobset();
-        let queries = match (args.eval, !args.queries.is_empty() || args.tests) {
-            (true, _) => Queries::Evals(args.guess_evals()),
-            (_, true) => Queries::Packages(args.guess_packages()),
-            (_, false) => Queries::Jobset,
+        let queries = match args.command.clone() {
+            Some(Commands::Search { term }) => Some(Queries::Search(term)),
+            Some(Commands::Projects) => Some(Queries::Projects),
+            Some(Commands::Jobsets { project }) => Some(Queries::Jobsets(project)),
+            Some(Commands::Queue) => Some(Queries::Queue),
+            Some(Commands::Machines) => Some(Queries::Machines),
+            Some(Commands::System) => system.map(Queries::System),
+            Some(Commands::Landed {
+                commit,
+                channels,
+                nixpkgs_repo,
+            }) => Some(Queries::Landed(LandedQuery {
+                commit,
+                nixpkgs_repo,
+                channels: args.guess_channels(&channels),
+            })),
+            Some(Commands::Exporter {
+                packages,
+                channels,
+                listen,
+                cache_ttl,
+            }) => Some(Queries::Exporter(ExporterQuery {
+                jobsets: args.guess_exported_jobsets(&packages, &channels),
+                listen,
+                cache_ttl: Duration::from_secs(cache_ttl),
+            })),
+            None => queries,
+        };
+        let queries = match (queries, args.eval, !args.queries.is_empty() || args.tests) {
+            (Some(queries), _, _) => queries,
+            (None, _, _) if args.config => Queries::JobsetConfig,
+            (None, true, _) => Queries::Evals(args.guess_evals(&job_filter)?),
+            (None, _, true) => Queries::Packages(args.guess_packages()),
+            (None, _, false) => Queries::Jobset,
         };
+        if job_filter.is_active() && !matches!(queries, Queries::Evals(_)) {
+            warn!("--match, --glob and --system only apply to the builds of evals");
+        }
+        if args.summary && !matches!(queries, Queries::Evals(_)) {
+            warn!("--summary only applies to the builds of evals");
+        }
+        if args.check_cache.is_some() && matches!(queries, Queries::Evals(_) | Queries::Jobset) {
+            warn!("--check-cache is ignored when querying evals, as they contain no build outputs");
+        }
+        if args.reproduce.is_some() && !matches!(queries, Queries::Packages(_) | Queries::Builds(_))
+        {
+            warn!("--reproduce only applies to the builds of packages");
+        }
+        if args.pin_format.is_some()
+            && !matches!(
+                queries,
+                Queries::Packages(_) | Queries::Builds(_) | Queries::Evals(_)
+            )
+        {
+            warn!("--pin-format only applies to packages, builds and evals");
+        }
+        let format = args.output_format();
+        if args.output.is_some() && !format.has_document() {
+            warn!("--output only applies to --format html, junit, openmetrics, atom and svg-badge");
+        }
         Ok(ResolvedArgs {
             queries,
             url: args.url,
-            json: args.json,
+            format,
             short: args.short,
             long: args.long,
+            pagination: args.pagination(),
+            job_filter,
+            summary: args.summary,
+            check_cache: args.check_cache,
+            reproduce: args.reproduce,
+            pin_format: args.pin_format,
             releases: args.releases,
             channel: args.channel,
+            output: args.output,
             jobset: args
                 .jobset
                 .expect("jobset should be resolved by `guess_jobset()`"),
+            sink: Rc::new(RefCell::new(Sink::new(
+                format,
+                args.template,
+                args.badge_label,
+                args.badge_style,
+            ))),
         })
     }
 
@@ -425,6 +939,17 @@ impl ResolvedArgs {
             }
             Queries::Packages(packages) => self.fetch_and_print_packages(packages),
             Queries::Evals(evals) => self.fetch_and_print_evaluations(evals),
+            Queries::Builds(ids) => self.fetch_and_print_builds(ids),
+            Queries::StorePaths(paths) => self.fetch_and_print_store_paths(paths),
+            Queries::Search(term) => self.fetch_and_print_search(term),
+            Queries::Projects => self.fetch_and_print_projects(),
+            Queries::Jobsets(project) => self.fetch_and_print_jobsets(project),
+            Queries::JobsetConfig => self.fetch_and_print_jobset_config(),
+            Queries::Queue => self.fetch_and_print_queue(),
+            Queries::Machines => self.fetch_and_print_machines(),
+            Queries::System(system) => self.fetch_and_print_system(system),
+            Queries::Landed(query) => self.fetch_and_print_landed(query),
+            Queries::Exporter(query) => self.fetch_and_print_exporter(query),
         }
     }
 }
@@ -449,6 +974,21 @@ fn guess_jobset() {
     }
 }
 
+#[test]
+fn global_pagination() {
+    let args = HydraCheckCli::parse_from([
+        "hydra-check",
+        "jobsets",
+        "nixpkgs",
+        "--limit=5",
+        "--since=2025-06-01",
+        "--until=2025-06-30",
+    ]);
+    debug_assert_eq!(args.limit, Some(5));
+    debug_assert_eq!(args.since, Some(1_748_736_000));
+    debug_assert_eq!(args.until, Some(1_751_327_999));
+}
+
 #[test]
 fn guess_darwin() {
     let apple_silicon = "aarch64-darwin";
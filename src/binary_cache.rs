@@ -0,0 +1,172 @@
+//! Presence checks of build outputs in a binary cache (substituter),
+//! by querying the `narinfo` of each store path, e.g.
+//! <https://cache.nixos.org/1q8w6gl1ll0mwfkqc3c2yx005s6wwfrl.narinfo>.
+
+use colored::{ColoredString, Colorize};
+use log::debug;
+use reqwest::StatusCode;
+use schemars::JsonSchema;
+use serde::Serialize;
+use serde_with::skip_serializing_none;
+use std::path::Path;
+
+use crate::{constants, http_client, FetchHydraReport, ResolvedArgs, ShowHydraStatus, StatusIcon};
+
+/// Presence of a single output path in a binary cache
+#[skip_serializing_none]
+#[derive(Serialize, JsonSchema, Debug, Default, Clone)]
+pub(crate) struct CacheStatus {
+    pub(crate) icon: StatusIcon,
+    pub(crate) cached: Option<bool>,
+    pub(crate) status: String,
+    pub(crate) output: String,
+    pub(crate) path: String,
+    pub(crate) narinfo_url: Option<String>,
+}
+
+impl ShowHydraStatus for CacheStatus {
+    fn format_as_vec(&self) -> Vec<ColoredString> {
+        let icon = ColoredString::from(&self.icon);
+        vec![
+            format!("{icon} {}", self.status).into(),
+            self.output.as_str().into(),
+            self.path.as_str().into(),
+            self.narinfo_url.clone().unwrap_or_default().dimmed(),
+        ]
+    }
+}
+
+/// Parses the url of `--check-cache`, which is either a http(s) url or
+/// a local (`file://`) directory; other substituters, e.g. `s3://` or
+/// `ssh://`, cannot be queried for `narinfo` files directly.
+pub(crate) fn parse_cache_url(url: &str) -> anyhow::Result<String> {
+    match url.split_once("://") {
+        Some(("http" | "https" | "file", _)) => Ok(url.into()),
+        Some((scheme, _)) => anyhow::bail!(
+            "unsupported binary cache scheme '{scheme}://', use a http(s):// url or a file:// directory"
+        ),
+        None => anyhow::bail!("'{url}' is not a http(s):// or file:// url"),
+    }
+}
+
+/// Computes the location of the `narinfo` file for a store path,
+/// which is named after the hash part of the path.
+fn narinfo_url(cache_url: &str, path: &str) -> Option<String> {
+    let name = path.strip_prefix(constants::NIX_STORE_DIR)?;
+    let hash = name.trim_start_matches('/').split_once('-')?.0;
+    Some(format!(
+        "{}/{hash}.narinfo",
+        cache_url.trim_end_matches('/')
+    ))
+}
+
+impl CacheStatus {
+    fn from_result(output: &str, path: &str, cached: anyhow::Result<bool>) -> Self {
+        let (icon, cached, status) = match cached {
+            Ok(true) => (StatusIcon::Succeeded, Some(true), "cached".into()),
+            Ok(false) => (StatusIcon::Failed, Some(false), "not cached".into()),
+            Err(err) => (StatusIcon::Warning, None, format!("unknown: {err}")),
+        };
+        Self {
+            icon,
+            cached,
+            status,
+            output: output.into(),
+            path: path.into(),
+            narinfo_url: None,
+        }
+    }
+
+    /// Checks the presence of a store path in the binary cache at `cache_url`,
+    /// which is either a http(s) url or a local (`file://`) directory.
+    pub(crate) fn check(cache_url: &str, output: &str, path: &str) -> Self {
+        let Some(narinfo_url) = narinfo_url(cache_url, path) else {
+            let err = anyhow::anyhow!("not a valid store path");
+            return Self::from_result(output, path, Err(err));
+        };
+        debug!("checking {narinfo_url}");
+        let cached = match parse_cache_url(cache_url) {
+            Err(err) => Err(err),
+            Ok(_) if cache_url.starts_with("file://") => {
+                let file = narinfo_url.trim_start_matches("file://");
+                Ok(Path::new(file).is_file())
+            }
+            Ok(_) => http_client()
+                .and_then(|client| client.head(&narinfo_url).send())
+                .map_err(anyhow::Error::from)
+                .and_then(|response| match response.status() {
+                    x if x.is_success() => Ok(true),
+                    // S3 based caches may deny access to missing files:
+                    StatusCode::NOT_FOUND | StatusCode::FORBIDDEN => Ok(false),
+                    x => Err(anyhow::anyhow!("{x}")),
+                }),
+        };
+        Self {
+            narinfo_url: Some(narinfo_url),
+            ..Self::from_result(output, path, cached)
+        }
+    }
+}
+
+impl ResolvedArgs {
+    /// Checks the given `(output, path)` pairs in the binary cache of
+    /// `--check-cache`, or returns `None` if the flag is not set.
+    pub(crate) fn check_cache<'a>(
+        &self,
+        outputs: impl IntoIterator<Item = (&'a str, &'a str)>,
+    ) -> Option<Vec<CacheStatus>> {
+        let cache_url = self.check_cache.as_deref()?;
+        let statuses = outputs
+            .into_iter()
+            .map(|(output, path)| CacheStatus::check(cache_url, output, path))
+            .collect();
+        Some(statuses)
+    }
+
+    /// Prints the results of [`Self::check_cache`] below a report,
+    /// and returns whether all outputs are cached.
+    pub(crate) fn print_cache_status(
+        &self,
+        stat: &impl FetchHydraReport,
+        statuses: Option<&Vec<CacheStatus>>,
+    ) -> bool {
+        let Some(statuses) = statuses else {
+            return true;
+        };
+        if self.format.is_table() && !statuses.is_empty() {
+            let cache_url = self.check_cache.as_deref().unwrap_or_default();
+            println!(
+                "{} {}",
+                "Binary cache status".bold(),
+                format!("@ {cache_url}").dimmed()
+            );
+            println!("{}", stat.format_table(false, statuses));
+        }
+        statuses.iter().all(|x| x.cached == Some(true))
+    }
+}
+
+#[test]
+fn check_local_cache() {
+    let hash = "1q8w6gl1ll0mwfkqc3c2yx005s6wwfrl";
+    let path = format!("/nix/store/{hash}-hello-2.12.1");
+    assert_eq!(
+        narinfo_url("https://cache.nixos.org/", &path).unwrap(),
+        format!("https://cache.nixos.org/{hash}.narinfo")
+    );
+    let cache_dir = std::env::temp_dir().join(format!("hydra-check-cache-{}", std::process::id()));
+    std::fs::create_dir_all(&cache_dir).unwrap();
+    let cache_url = format!("file://{}", cache_dir.display());
+    let status = CacheStatus::check(&cache_url, "out", &path);
+    assert_eq!(status.cached, Some(false));
+    std::fs::write(cache_dir.join(format!("{hash}.narinfo")), "StorePath: ...").unwrap();
+    let status = CacheStatus::check(&cache_url, "out", &path);
+    assert_eq!(status.cached, Some(true));
+    std::fs::remove_dir_all(&cache_dir).unwrap();
+    let status = CacheStatus::check(&cache_url, "out", "/tmp/hello");
+    assert!(status.cached.is_none());
+    let status = CacheStatus::check("s3://nix-cache", "out", &path);
+    assert!(status.cached.is_none());
+    assert!(parse_cache_url("/var/cache/nix").is_err());
+    assert!(parse_cache_url("https://cache.nixos.org").is_ok());
+}
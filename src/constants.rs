@@ -47,6 +47,19 @@ pub const NIXOS_ARCHITECTURES: [&str; 2] = ["x86_64-linux", "aarch64-linux"];
 /// Default package filter for the details of a specific evaluation.
 pub const DEFAULT_EVALUATION_FILTER: &str = "nixVersions.stable";
 
+/// Location of the Nix store whose paths are built by Hydra, used to
+/// recognize queries for store paths such as `/nix/store/<hash>-hello-2.12.1`.
+pub const NIX_STORE_DIR: &str = "/nix/store";
+
+/// Default number of recent evaluations of a jobset to look through when
+/// searching for an evaluation by its revision, e.g. `--eval rev:1e9e641a`.
+/// This can be overridden by `--limit`.
+pub const DEFAULT_EVAL_SEARCH_LIMIT: usize = 500;
+
+/// Default binary cache to check the build outputs in, i.e. the
+/// substituter that Hydra uploads to, for `--check-cache`.
+pub const DEFAULT_BINARY_CACHE_URL: &str = "https://cache.nixos.org";
+
 /// User agent header that we send along to hydra for identifying this app.
 ///
 /// ```
@@ -0,0 +1,229 @@
+//! Atom feeds, see `--format atom`, with an entry per build of the packages
+//! or per evaluation of the jobsets, e.g. to follow the status of `hello`
+//! on `nixos-unstable` in a feed reader.
+//!
+//! The entries are identified by the Hydra URL of their build or evaluation,
+//! which never changes, so that feed readers only show the new ones, whether
+//! the feed is regenerated into a static file with `-o PATH`, e.g. by a cron
+//! job, or served by any web server.
+
+use std::fmt::Write;
+
+use chrono::{DateTime, SecondsFormat};
+
+use super::{html::escape, ReportKind};
+use crate::{constants::HYDRA_CHECK_HOST_URL, structs::ReleaseStatus, BuildStatus, EvalStatus};
+
+/// An entry of the feed, identified (and linked) by its Hydra URL
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Entry {
+    id: String,
+    link: String,
+    title: String,
+    summary: String,
+    /// Unix timestamp of the build or evaluation
+    updated: Option<i64>,
+}
+
+/// Rows of a report that are published as entries of the feed, where
+/// the `key` of the report is e.g. the package of the builds.
+pub(crate) trait FeedEntry {
+    fn entry(&self, key: &str) -> Option<Entry>;
+}
+
+impl FeedEntry for BuildStatus {
+    /// Builds without an ID, e.g. removed jobs, are not published.
+    fn entry(&self, key: &str) -> Option<Entry> {
+        self.build_id.as_ref()?;
+        let url = self.build_url.clone()?;
+        let job = self.job_name.as_deref().unwrap_or(key);
+        let name = self.name.as_deref().unwrap_or(job);
+        let summary = match &self.arch {
+            Some(arch) => format!("{name} on {arch}: {}", self.status),
+            None => format!("{name}: {}", self.status),
+        };
+        Some(Entry {
+            id: url.clone(),
+            link: url,
+            title: format!("{job}: {}", self.status),
+            summary,
+            updated: self.unix_timestamp(),
+        })
+    }
+}
+
+impl FeedEntry for EvalStatus {
+    fn entry(&self, key: &str) -> Option<Entry> {
+        let (id, url) = (self.id?, self.url.clone()?);
+        let changes = self.input_changes.as_ref().unwrap_or(&self.status);
+        let count = |x: Option<u64>| x.unwrap_or_default();
+        let summary = format!(
+            "{changes}: {} succeeded, {} failed, {} queued",
+            count(self.succeeded),
+            count(self.failed),
+            count(self.queued)
+        );
+        Some(Entry {
+            id: url.clone(),
+            link: url,
+            title: format!("{key}: evaluation {id}"),
+            summary,
+            updated: self.timestamp.and_then(|x| x.try_into().ok()),
+        })
+    }
+}
+
+impl FeedEntry for ReleaseStatus {
+    /// Evaluations with their release test, linked to the release if any.
+    fn entry(&self, key: &str) -> Option<Entry> {
+        let eval = self.eval.entry(key)?;
+        let test = &self.test;
+        let name = test.name.as_deref().unwrap_or(&test.status);
+        Some(Entry {
+            link: self.release_url.clone().unwrap_or(eval.link),
+            title: format!("{}, release test: {}", eval.title, test.status),
+            summary: format!("{name}, {}", eval.summary),
+            ..eval
+        })
+    }
+}
+
+impl ReportKind {
+    /// Hydra URL of the page of the `key` of the report, if there is one.
+    fn key_url(self, jobset: &str, key: &str) -> Option<String> {
+        let host = &*HYDRA_CHECK_HOST_URL;
+        match self {
+            Self::Jobset | Self::Releases => Some(format!("{host}/jobset/{key}")),
+            Self::Packages => Some(format!("{host}/job/{jobset}/{key}")),
+            Self::Evals => {
+                let id = key.split('/').next().unwrap_or(key);
+                Some(format!("{host}/eval/{id}"))
+            }
+            Self::Builds => Some(format!("{host}/build/{key}")),
+            _ => None,
+        }
+    }
+}
+
+/// Entries of the reports, collected until the whole feed is written
+#[derive(Debug, Default)]
+pub(crate) struct Feed {
+    kind: Option<ReportKind>,
+    /// Keys of the reports with their Hydra URL
+    keys: Vec<(String, Option<String>)>,
+    entries: Vec<Entry>,
+}
+
+impl Feed {
+    pub(super) fn collect_rows<T: FeedEntry>(
+        &mut self,
+        kind: ReportKind,
+        jobset: &str,
+        key: &str,
+        rows: &[T],
+    ) {
+        self.kind = Some(kind);
+        if !self.keys.iter().any(|(x, _)| x == key) {
+            self.keys.push((key.into(), kind.key_url(jobset, key)));
+        }
+        self.entries
+            .extend(rows.iter().filter_map(|row| row.entry(key)));
+    }
+
+    /// Formats the feed of the collected entries.
+    pub(super) fn format_document(&self, now: i64) -> Result<String, std::fmt::Error> {
+        format_document(self, now)
+    }
+}
+
+fn format_timestamp(timestamp: i64) -> String {
+    DateTime::from_timestamp(timestamp, 0)
+        .unwrap_or_default()
+        .to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+/// Formats the feed, with the entries sorted from the latest to the oldest;
+/// the entries without a timestamp, e.g. queued builds, are updated `now`.
+fn format_document(feed: &Feed, now: i64) -> Result<String, std::fmt::Error> {
+    let host = &*HYDRA_CHECK_HOST_URL;
+    let keys: Vec<_> = feed.keys.iter().map(|(key, _)| key.as_str()).collect();
+    let (id, link) = match feed.keys.as_slice() {
+        // the feed of a single key is identified by its page on Hydra
+        [(_, Some(url))] => (url.clone(), url.clone()),
+        _ => {
+            let kind = feed.kind.map(ReportKind::name).unwrap_or_default();
+            (format!("{host}/#{kind}:{}", keys.join(",")), host.clone())
+        }
+    };
+    let mut entries: Vec<_> = feed.entries.iter().collect();
+    entries.sort_by_key(|x| std::cmp::Reverse(x.updated.unwrap_or(now)));
+    let updated = entries.first().map_or(now, |x| x.updated.unwrap_or(now));
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n");
+    writeln!(xml, r#"<feed xmlns="http://www.w3.org/2005/Atom">"#)?;
+    writeln!(xml, "  <id>{}</id>", escape(&id))?;
+    writeln!(
+        xml,
+        "  <title>hydra-check: {}</title>",
+        escape(&keys.join(", "))
+    )?;
+    writeln!(xml, "  <updated>{}</updated>", format_timestamp(updated))?;
+    writeln!(xml, r#"  <link href="{}"/>"#, escape(&link))?;
+    writeln!(xml, "  <author><name>{}</name></author>", escape(host))?;
+    writeln!(
+        xml,
+        r#"  <generator version="{}">hydra-check</generator>"#,
+        env!("CARGO_PKG_VERSION")
+    )?;
+    for entry in entries {
+        writeln!(xml, "  <entry>")?;
+        writeln!(xml, "    <id>{}</id>", escape(&entry.id))?;
+        writeln!(xml, "    <title>{}</title>", escape(&entry.title))?;
+        let updated = format_timestamp(entry.updated.unwrap_or(now));
+        writeln!(xml, "    <updated>{updated}</updated>")?;
+        writeln!(xml, r#"    <link href="{}"/>"#, escape(&entry.link))?;
+        writeln!(xml, "    <summary>{}</summary>", escape(&entry.summary))?;
+        writeln!(xml, "  </entry>")?;
+    }
+    writeln!(xml, "</feed>")?;
+    Ok(xml)
+}
+
+#[test]
+fn atom_feed() {
+    let build = BuildStatus {
+        status: "Dependency failed".into(),
+        timestamp: Some("2024-10-12T09:45:00Z".into()),
+        build_id: Some("115099119".into()),
+        build_url: Some("https://hydra.nixos.org/build/115099119".into()),
+        name: Some("hello-2.12.1".into()),
+        arch: Some("x86_64-linux".into()),
+        ..Default::default()
+    };
+    let removed = BuildStatus {
+        status: "Removed".into(),
+        ..Default::default()
+    };
+    let feed = Feed {
+        kind: Some(ReportKind::Packages),
+        keys: vec![(
+            "hello.x86_64-linux".into(),
+            Some("https://hydra.nixos.org/job/nixpkgs/trunk/hello.x86_64-linux".into()),
+        )],
+        entries: [build, removed]
+            .iter()
+            .filter_map(|x| x.entry("hello.x86_64-linux"))
+            .collect(),
+    };
+    let document = format_document(&feed, 0).unwrap();
+    let entries = document.split_once("</generator>\n").unwrap().1;
+    insta::assert_snapshot!(entries, @r#"
+      <entry>
+        <id>https://hydra.nixos.org/build/115099119</id>
+        <title>hello.x86_64-linux: Dependency failed</title>
+        <updated>2024-10-12T09:45:00Z</updated>
+        <link href="https://hydra.nixos.org/build/115099119"/>
+        <summary>hello-2.12.1 on x86_64-linux: Dependency failed</summary>
+      </entry>
+    </feed>
+    "#);
+}
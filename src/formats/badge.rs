@@ -0,0 +1,294 @@
+//! Status badges, see `--format svg-badge`, rendered as shields-style SVG
+//! without any external badge service, e.g. for embedding in a README:
+//! the status of the latest build of a job (or of an aggregate job), the
+//! age of the latest evaluation of a jobset, or the age of the latest
+//! release of a channel, see `--releases`.
+//!
+//! An SVG file holds a single badge, so only the first job (or jobset)
+//! of the query is rendered.
+
+use std::fmt::Write;
+
+use anyhow::bail;
+use clap::ValueEnum;
+use log::warn;
+
+use super::{html::escape, ReportKind};
+use crate::{structs::ReleaseStatus, BuildStatus, EvalStatus, StatusIcon};
+
+/// Style of the badges, see `--badge-style`
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub(crate) enum BadgeStyle {
+    /// Rounded corners with a subtle gradient
+    #[default]
+    Flat,
+    /// Square corners without a gradient
+    FlatSquare,
+    /// Rounded corners with a glossy gradient
+    Plastic,
+}
+
+const LABEL_COLOR: &str = "#555";
+
+const FLAT_GRADIENT: &str = r##"<stop offset="0" stop-color="#bbb" stop-opacity=".1"/><stop offset="1" stop-opacity=".1"/>"##;
+
+const PLASTIC_GRADIENT: &str = r##"<stop offset="0" stop-color="#fff" stop-opacity=".7"/><stop offset=".1" stop-color="#aaa" stop-opacity=".1"/><stop offset=".9" stop-opacity=".3"/><stop offset="1" stop-opacity=".5"/>"##;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Badge {
+    label: String,
+    message: String,
+    color: &'static str,
+}
+
+/// Rows of a report that are summarized by a badge, labelled by the `key`
+/// of the report, e.g. the job of the builds.
+pub(crate) trait BadgeRows: Sized {
+    fn badge(key: &str, rows: &[Self], now: i64) -> Option<Badge>;
+}
+
+/// Color of the message of a badge, as on shields.io
+fn color(icon: &StatusIcon) -> &'static str {
+    match icon {
+        StatusIcon::Succeeded => "#4c1",
+        StatusIcon::Failed => "#e05d44",
+        StatusIcon::Cancelled => "#9f9f9f",
+        StatusIcon::Queued => "#dfb317",
+        StatusIcon::Warning => "#fe7d37",
+    }
+}
+
+/// Formats an age in seconds, e.g. `5 hours`.
+fn format_age(seconds: i64) -> String {
+    let (count, unit) = match seconds {
+        ..3_600 => (seconds / 60, "minute"),
+        3_600..172_800 => (seconds / 3_600, "hour"),
+        _ => (seconds / 86_400, "day"),
+    };
+    match count {
+        1 => format!("1 {unit}"),
+        _ => format!("{count} {unit}s"),
+    }
+}
+
+/// A badge with the age of an evaluation or release, which turns from
+/// green to red as it grows older than a day, up to two weeks.
+fn age_badge(label: &str, timestamp: Option<u64>, now: i64) -> Badge {
+    let Some(timestamp) = timestamp.and_then(|x| i64::try_from(x).ok()) else {
+        return Badge {
+            label: label.into(),
+            message: "unknown".into(),
+            color: color(&StatusIcon::Cancelled),
+        };
+    };
+    let age = now.saturating_sub(timestamp).max(0);
+    let color = match age / 86_400 {
+        0 => "#4c1",
+        1..3 => "#97ca00",
+        3..7 => "#dfb317",
+        7..14 => "#fe7d37",
+        _ => "#e05d44",
+    };
+    Badge {
+        label: label.into(),
+        message: format_age(age),
+        color,
+    }
+}
+
+impl BadgeRows for BuildStatus {
+    /// Status of the latest build, e.g. `hello.x86_64-linux | succeeded`.
+    fn badge(key: &str, rows: &[Self], _: i64) -> Option<Badge> {
+        let build = rows.first()?;
+        Some(Badge {
+            label: build.job_name.as_deref().unwrap_or(key).into(),
+            message: build.status.to_lowercase(),
+            color: color(&build.icon),
+        })
+    }
+}
+
+impl BadgeRows for EvalStatus {
+    /// Age of the latest evaluation of the jobset.
+    fn badge(key: &str, rows: &[Self], now: i64) -> Option<Badge> {
+        let eval = rows.first()?;
+        Some(age_badge(key, eval.timestamp, now))
+    }
+}
+
+impl BadgeRows for ReleaseStatus {
+    /// Age of the latest evaluation with a successful release test.
+    fn badge(key: &str, rows: &[Self], now: i64) -> Option<Badge> {
+        match rows.iter().find(|x| x.test.success) {
+            Some(release) => Some(age_badge(key, release.eval.timestamp, now)),
+            None => Some(Badge {
+                label: key.into(),
+                message: "unreleased".into(),
+                color: color(&StatusIcon::Failed),
+            }),
+        }
+    }
+}
+
+/// Approximate width of the text in Verdana 11px, as on shields.io
+fn text_width(text: &str) -> usize {
+    let width: usize = text
+        .chars()
+        .map(|char| match char {
+            'i' | 'j' | 'l' | '.' | ',' | ':' | ';' | '\'' | '!' | '|' => 3,
+            'f' | 'r' | 't' | 'I' | ' ' | '(' | ')' | '[' | ']' | '/' | '-' => 4,
+            'm' | 'w' | 'M' | 'W' => 10,
+            char if char.is_ascii_uppercase() => 8,
+            _ => 7,
+        })
+        .sum();
+    width + 10
+}
+
+impl Badge {
+    fn render(&self, style: BadgeStyle) -> Result<String, std::fmt::Error> {
+        let (label, message) = (escape(&self.label), escape(&self.message));
+        let (label_width, message_width) = (text_width(&self.label), text_width(&self.message));
+        let width = label_width + message_width;
+        let (radius, gradient) = match style {
+            BadgeStyle::Flat => (3, FLAT_GRADIENT),
+            BadgeStyle::FlatSquare => (0, ""),
+            BadgeStyle::Plastic => (4, PLASTIC_GRADIENT),
+        };
+        let mut svg = String::new();
+        writeln!(
+            svg,
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{width}" height="20" role="img" aria-label="{label}: {message}">"#
+        )?;
+        writeln!(svg, "  <title>{label}: {message}</title>")?;
+        if !gradient.is_empty() {
+            writeln!(
+                svg,
+                r#"  <linearGradient id="s" x2="0" y2="100%">{gradient}</linearGradient>"#
+            )?;
+        }
+        writeln!(
+            svg,
+            r##"  <clipPath id="r"><rect width="{width}" height="20" rx="{radius}" fill="#fff"/></clipPath>"##
+        )?;
+        writeln!(svg, r#"  <g clip-path="url(#r)">"#)?;
+        writeln!(
+            svg,
+            r#"    <rect width="{label_width}" height="20" fill="{LABEL_COLOR}"/>"#
+        )?;
+        writeln!(
+            svg,
+            r#"    <rect x="{label_width}" width="{message_width}" height="20" fill="{}"/>"#,
+            self.color
+        )?;
+        if !gradient.is_empty() {
+            writeln!(
+                svg,
+                r#"    <rect width="{width}" height="20" fill="url(#s)"/>"#
+            )?;
+        }
+        writeln!(svg, "  </g>")?;
+        writeln!(
+            svg,
+            r##"  <g fill="#fff" text-anchor="middle" font-family="Verdana,Geneva,DejaVu Sans,sans-serif" font-size="11">"##
+        )?;
+        let centers = [
+            (label_width / 2, &label),
+            (label_width + message_width / 2, &message),
+        ];
+        for (x, text) in centers {
+            if style != BadgeStyle::FlatSquare {
+                writeln!(
+                    svg,
+                    r##"    <text x="{x}" y="15" fill="#010101" fill-opacity=".3">{text}</text>"##
+                )?;
+            }
+            writeln!(svg, r#"    <text x="{x}" y="14">{text}</text>"#)?;
+        }
+        writeln!(svg, "  </g>")?;
+        writeln!(svg, "</svg>")?;
+        Ok(svg)
+    }
+}
+
+/// Badges of the reports, of which only the first one is written
+#[derive(Debug)]
+pub(crate) struct Badges {
+    /// Label instead of the job, jobset or channel, see `--badge-label`
+    label: Option<String>,
+    style: BadgeStyle,
+    collected: Vec<Badge>,
+}
+
+impl Badges {
+    pub(super) fn new(label: Option<String>, style: BadgeStyle) -> Self {
+        Self {
+            label,
+            style,
+            collected: vec![],
+        }
+    }
+
+    /// Collects the badge of the rows, except for the builds of evaluations,
+    /// which are not summarized by their first build.
+    pub(super) fn collect_rows<T: BadgeRows>(&mut self, kind: ReportKind, key: &str, rows: &[T]) {
+        if kind == ReportKind::Evals {
+            return;
+        }
+        let now = chrono::Utc::now().timestamp();
+        self.collected.extend(T::badge(key, rows, now));
+    }
+
+    /// Formats the first collected badge, with the label if set,
+    /// and starts collecting the badges of the next report.
+    pub(super) fn format_document(&mut self) -> anyhow::Result<String> {
+        let badges = std::mem::take(&mut self.collected);
+        let Some(badge) = badges.first() else {
+            bail!("--format svg-badge only applies to jobs, builds, jobsets and --releases");
+        };
+        if badges.len() > 1 {
+            warn!(
+                "only the badge of '{}' is written, query a single job for each badge",
+                badge.label
+            );
+        }
+        let badge = Badge {
+            label: self.label.as_ref().unwrap_or(&badge.label).clone(),
+            ..badge.clone()
+        };
+        Ok(badge.render(self.style)?)
+    }
+}
+
+#[test]
+fn render_badge() {
+    let build = BuildStatus {
+        icon: StatusIcon::Failed,
+        status: "Dependency failed".into(),
+        ..Default::default()
+    };
+    let badge = BuildStatus::badge("hello.x86_64-linux", &[build], 0).unwrap();
+    insta::assert_snapshot!(badge.render(BadgeStyle::FlatSquare).unwrap(), @r##"
+    <svg xmlns="http://www.w3.org/2000/svg" width="228" height="20" role="img" aria-label="hello.x86_64-linux: dependency failed">
+      <title>hello.x86_64-linux: dependency failed</title>
+      <clipPath id="r"><rect width="228" height="20" rx="0" fill="#fff"/></clipPath>
+      <g clip-path="url(#r)">
+        <rect width="113" height="20" fill="#555"/>
+        <rect x="113" width="115" height="20" fill="#e05d44"/>
+      </g>
+      <g fill="#fff" text-anchor="middle" font-family="Verdana,Geneva,DejaVu Sans,sans-serif" font-size="11">
+        <text x="56" y="14">hello.x86_64-linux</text>
+        <text x="170" y="14">dependency failed</text>
+      </g>
+    </svg>
+    "##);
+    let eval = EvalStatus {
+        timestamp: Some(1_700_000_000),
+        ..Default::default()
+    };
+    let badge = EvalStatus::badge("nixos/trunk-combined", &[eval], 1_700_000_000 + 4 * 86_400);
+    assert_eq!(
+        badge.map(|x| (x.message, x.color)),
+        Some(("4 days".into(), "#dfb317"))
+    );
+}
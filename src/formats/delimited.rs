@@ -0,0 +1,132 @@
+//! Comma- and tab-separated values, see `--format csv` and `--format tsv`,
+//! which flatten the rows of a report into plain columns for spreadsheets.
+
+use std::fmt::Display;
+
+use super::ReportKind;
+use crate::StatusIcon;
+
+/// Rows of a report with a fixed set of plain (uncolored) columns
+pub(crate) trait FlatRow {
+    const COLUMNS: &'static [&'static str];
+    fn values(&self) -> Vec<String>;
+
+    /// Status of the row, shown as an emoji by `--format markdown`
+    fn icon(&self) -> Option<&StatusIcon> {
+        None
+    }
+}
+
+/// Formats an optional value as a cell, which is empty if missing.
+pub(crate) fn cell(value: Option<impl Display>) -> String {
+    value.map(|x| x.to_string()).unwrap_or_default()
+}
+
+impl ReportKind {
+    /// Name of the column that holds the key of the report, if any,
+    /// e.g. the package of the builds.
+    pub(super) fn key_column(self) -> Option<&'static str> {
+        match self {
+            Self::Jobset | Self::Releases | Self::JobsetConfig => Some("jobset"),
+            Self::Packages => Some("package"),
+            Self::Evals => Some("eval"),
+            Self::Builds => Some("build"),
+            Self::StorePaths => Some("store_path"),
+            Self::Search => Some("term"),
+            Self::Landed => Some("commit"),
+            Self::Projects | Self::Jobsets | Self::Queue | Self::Machines | Self::System => None,
+        }
+    }
+}
+
+fn format_rows<T: FlatRow>(
+    delimiter: u8,
+    header: bool,
+    kind: ReportKind,
+    key: Option<&str>,
+    group: Option<&str>,
+    rows: &[T],
+) -> anyhow::Result<String> {
+    let mut writer = csv::WriterBuilder::new()
+        .delimiter(delimiter)
+        .from_writer(vec![]);
+    // the key and the group are prepended to the columns of each row
+    let key = kind.key_column().and(key);
+    if header {
+        let key_column = kind.key_column().filter(|_| key.is_some());
+        let group_column = group.map(|_| "group");
+        let columns = key_column.into_iter().chain(group_column);
+        writer.write_record(columns.chain(T::COLUMNS.iter().copied()))?;
+    }
+    for row in rows {
+        let prefix = key.into_iter().chain(group).map(str::to_string);
+        writer.write_record(prefix.chain(row.values()))?;
+    }
+    Ok(String::from_utf8(writer.into_inner()?)?)
+}
+
+/// Writer of the rows of a report, of which the header is printed
+/// once, right before its first row
+#[derive(Debug)]
+pub(crate) struct Writer {
+    delimiter: u8,
+    header_printed: bool,
+}
+
+impl Writer {
+    pub(super) fn new(delimiter: u8) -> Self {
+        Self {
+            delimiter,
+            header_printed: false,
+        }
+    }
+
+    /// Formats the rows, preceded by the header if nothing
+    /// has been printed yet.
+    pub(super) fn format_rows<T: FlatRow>(
+        &mut self,
+        kind: ReportKind,
+        key: Option<&str>,
+        group: Option<&str>,
+        rows: &[T],
+    ) -> anyhow::Result<String> {
+        if rows.is_empty() {
+            return Ok(String::new());
+        }
+        let header = !std::mem::replace(&mut self.header_printed, true);
+        format_rows(self.delimiter, header, kind, key, group, rows)
+    }
+
+    /// Starts the next report, which is preceded by a header of its own.
+    pub(super) fn reset(&mut self) {
+        self.header_printed = false;
+    }
+}
+
+#[test]
+fn flatten_builds() {
+    use crate::{BuildStatus, StatusIcon};
+    let build = BuildStatus {
+        icon: StatusIcon::Failed,
+        status: "Dependency failed".into(),
+        timestamp: Some("2020-03-18T22:02:59Z".into()),
+        build_id: Some("115099119".into()),
+        build_url: Some("https://hydra.nixos.org/build/115099119".into()),
+        name: Some("tarball, \"i686\"".into()),
+        arch: Some("i686-linux".into()),
+        ..Default::default()
+    };
+    let csv = format_rows(
+        b',',
+        true,
+        ReportKind::Packages,
+        Some("hello"),
+        None,
+        &[build],
+    );
+    assert_eq!(
+        csv.unwrap(),
+        "package,job,name,arch,status,build_id,timestamp,url\n\
+        hello,,\"tarball, \"\"i686\"\"\",i686-linux,Dependency failed,115099119,2020-03-18T22:02:59Z,https://hydra.nixos.org/build/115099119\n"
+    );
+}
@@ -0,0 +1,260 @@
+//! A self-contained static HTML page, see `--format html`, with sortable
+//! tables colored by status and links back to Hydra, e.g. for publishing
+//! the reports of a cron job on a web server.
+//!
+//! The rows are collected as they are fetched, like the other flat formats,
+//! and the page is written once the whole report is fetched, together with
+//! the inputs and changes of the evaluations from the report itself.
+
+use std::fmt::Write;
+
+use serde::Serialize;
+use serde_json::Value;
+
+use super::{FlatRow, ReportKind};
+use crate::StatusIcon;
+
+/// Sections of the (keyed) reports with extra details,
+/// that are not streamed as rows, e.g. of the evaluations
+const DETAILS: [&str; 2] = ["inputs", "changes"];
+
+const STYLE: &str = r"
+body { font-family: system-ui, sans-serif; margin: 2em; color: #222; }
+table { border-collapse: collapse; margin-bottom: 1.5em; font-size: 0.9em; }
+th, td { border: 1px solid #ddd; padding: 0.3em 0.6em; text-align: left; }
+th { background: #f4f4f4; cursor: pointer; user-select: none; }
+th[data-order=asc]::after { content: ' ▲'; }
+th[data-order=desc]::after { content: ' ▼'; }
+tr.succeeded td:first-child { background: #dafbe1; }
+tr.failed td:first-child { background: #ffebe9; }
+tr.cancelled td:first-child { background: #eee; }
+tr.queued td:first-child, tr.warning td:first-child { background: #fff8c5; }
+.generated { color: #666; }
+";
+
+/// Sorts the rows of a table by the clicked column, numerically if possible.
+const SCRIPT: &str = r#"
+document.querySelectorAll("th").forEach((th) => th.addEventListener("click", () => {
+  const body = th.closest("table").tBodies[0];
+  const ascending = th.dataset.order !== "asc";
+  th.parentNode.querySelectorAll("th").forEach((other) => delete other.dataset.order);
+  th.dataset.order = ascending ? "asc" : "desc";
+  const text = (row) => row.cells[th.cellIndex].textContent.trim();
+  const rows = [...body.rows].sort((a, b) => {
+    const [x, y] = [text(a), text(b)];
+    const numeric = x !== "" && y !== "" && !isNaN(x) && !isNaN(y);
+    const order = numeric ? x - y : x.localeCompare(y);
+    return ascending ? order : -order;
+  });
+  body.append(...rows);
+}));
+"#;
+
+/// Rows of a report, collected until the whole page is written
+#[derive(Debug)]
+struct Table {
+    key: Option<String>,
+    group: Option<String>,
+    columns: Vec<String>,
+    rows: Vec<(Option<StatusIcon>, Vec<String>)>,
+}
+
+/// Tables of the reports, collected until the whole page is written
+#[derive(Debug, Default)]
+pub(crate) struct Page {
+    tables: Vec<Table>,
+}
+
+/// Escapes text and attribute values, of HTML as well as XML.
+pub(super) fn escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn format_cell(column: &str, value: &str) -> String {
+    let value = escape(value);
+    match column {
+        _ if column == "url" || column.ends_with("_url") => {
+            format!(r#"<a href="{value}">{value}</a>"#)
+        }
+        _ => value,
+    }
+}
+
+fn format_table(table: &Table) -> Result<String, std::fmt::Error> {
+    let mut html = String::from("<table>\n<thead><tr>");
+    for column in &table.columns {
+        write!(html, "<th>{}</th>", escape(column))?;
+    }
+    html += "</tr></thead>\n<tbody>\n";
+    for (icon, values) in &table.rows {
+        match icon
+            .as_ref()
+            .and_then(|icon| serde_json::to_value(icon).ok())
+        {
+            Some(Value::String(status)) => write!(html, r#"<tr class="{status}">"#)?,
+            _ => html += "<tr>",
+        }
+        for (column, value) in table.columns.iter().zip(values) {
+            write!(html, "<td>{}</td>", format_cell(column, value))?;
+        }
+        html += "</tr>\n";
+    }
+    html += "</tbody>\n</table>\n";
+    Ok(html)
+}
+
+/// Converts an array of JSON objects, e.g. the inputs of an evaluation,
+/// into a table with the fields of its first object as columns.
+fn table_from_json(group: &str, values: &[Value]) -> Option<Table> {
+    let columns: Vec<String> = values.first()?.as_object()?.keys().cloned().collect();
+    let rows = values
+        .iter()
+        .map(|value| {
+            let cells = columns
+                .iter()
+                .map(|column| json_cell(&value[column]))
+                .collect();
+            (None, cells)
+        })
+        .collect();
+    Some(Table {
+        key: None,
+        group: Some(group.into()),
+        columns,
+        rows,
+    })
+}
+
+/// Formats a JSON value as a cell, e.g. the pair of old and new revisions
+/// of a changed input as `old → new`.
+fn json_cell(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(x) => x.clone(),
+        Value::Array(xs) => xs.iter().map(json_cell).collect::<Vec<_>>().join(" → "),
+        x => x.to_string(),
+    }
+}
+
+fn format_document(
+    kind: ReportKind,
+    data: &Value,
+    tables: &[Table],
+    generated: &str,
+) -> anyhow::Result<String> {
+    let title = kind.name();
+    let mut body = String::new();
+    let mut last_key = None;
+    for table in tables {
+        if table.key != last_key {
+            last_key.clone_from(&table.key);
+            if let (Some(column), Some(key)) = (kind.key_column(), &table.key) {
+                writeln!(body, "<h2>{column} <code>{}</code></h2>", escape(key))?;
+                let report = &data[key];
+                for (group, values) in DETAILS.map(|x| (x, report[x].as_array())) {
+                    if let Some(details) = values.and_then(|x| table_from_json(group, x)) {
+                        write!(body, "<h3>{group}</h3>\n{}", format_table(&details)?)?;
+                    }
+                }
+            }
+        }
+        if table.rows.is_empty() {
+            continue;
+        }
+        if let Some(group) = &table.group {
+            let count = table.rows.len();
+            writeln!(body, "<h3>{} ({count})</h3>", escape(group))?;
+        }
+        body += &format_table(table)?;
+    }
+    Ok(format!(
+        "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n\
+        <title>hydra-check: {title}</title>\n<style>{STYLE}</style>\n</head>\n<body>\n\
+        <h1>hydra-check: {title}</h1>\n<p class=\"generated\">{generated}</p>\n\
+        {body}<script>{SCRIPT}</script>\n</body>\n</html>\n"
+    ))
+}
+
+impl Page {
+    /// Collects the rows for the page, which is formatted by [`Page::format_page`].
+    pub(super) fn collect_rows<T: FlatRow>(
+        &mut self,
+        key: Option<&str>,
+        group: Option<&str>,
+        rows: &[T],
+    ) {
+        self.tables.push(Table {
+            key: key.map(str::to_string),
+            group: group.map(str::to_string),
+            columns: T::COLUMNS.iter().map(|&x| x.to_string()).collect(),
+            rows: rows
+                .iter()
+                .map(|row| (row.icon().cloned(), row.values()))
+                .collect(),
+        });
+    }
+
+    /// Formats the page with the collected tables of the report.
+    pub(super) fn format_page(
+        self,
+        kind: ReportKind,
+        data: impl Serialize,
+    ) -> anyhow::Result<String> {
+        let data = serde_json::to_value(data)?;
+        let generated = format!(
+            "Generated by hydra-check {} at {}",
+            env!("CARGO_PKG_VERSION"),
+            chrono::Utc::now().format("%Y-%m-%d %H:%M:%S UTC")
+        );
+        format_document(kind, &data, &self.tables, &generated)
+    }
+}
+
+#[test]
+fn html_eval_report() {
+    use crate::BuildStatus;
+    let build = BuildStatus {
+        icon: StatusIcon::Failed,
+        status: "Failed".into(),
+        build_id: Some("115099119".into()),
+        build_url: Some("https://hydra.nixos.org/build/115099119".into()),
+        job_name: Some("<hello>".into()),
+        ..Default::default()
+    };
+    let table = Table {
+        key: Some("1809585".into()),
+        group: Some("now_fail".into()),
+        columns: BuildStatus::COLUMNS.iter().map(|&x| x.into()).collect(),
+        rows: vec![(Some(build.icon.clone()), build.values())],
+    };
+    let data = serde_json::json!({
+        "1809585": { "inputs": [{ "name": "nixpkgs", "revision": "e0f7f35" }] }
+    });
+    let document = format_document(ReportKind::Evals, &data, &[table], "").unwrap();
+    let body = document
+        .split("</p>\n")
+        .nth(1)
+        .and_then(|x| x.split("<script>").next())
+        .unwrap();
+    insta::assert_snapshot!(body, @r#"
+    <h2>eval <code>1809585</code></h2>
+    <h3>inputs</h3>
+    <table>
+    <thead><tr><th>name</th><th>revision</th></tr></thead>
+    <tbody>
+    <tr><td>nixpkgs</td><td>e0f7f35</td></tr>
+    </tbody>
+    </table>
+    <h3>now_fail (1)</h3>
+    <table>
+    <thead><tr><th>job</th><th>name</th><th>arch</th><th>status</th><th>build_id</th><th>timestamp</th><th>url</th></tr></thead>
+    <tbody>
+    <tr class="failed"><td>&lt;hello&gt;</td><td></td><td></td><td>Failed</td><td>115099119</td><td></td><td><a href="https://hydra.nixos.org/build/115099119">https://hydra.nixos.org/build/115099119</a></td></tr>
+    </tbody>
+    </table>
+    "#);
+}
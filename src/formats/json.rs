@@ -0,0 +1,30 @@
+//! The `--json` output of every query, wrapped in a versioned envelope
+//! `{ "schema_version": 1, "kind": "packages", "data": ... }`.
+
+use schemars::JsonSchema;
+use serde::Serialize;
+
+use super::ReportKind;
+
+/// Version of the `--json` output, which is bumped whenever fields
+/// are removed, renamed or change their type, but not when added.
+pub(crate) const JSON_SCHEMA_VERSION: u32 = 1;
+
+/// Versioned envelope of the `--json` output
+#[derive(Serialize, JsonSchema)]
+pub(crate) struct JsonOutput<T> {
+    /// Version of the schema, bumped on breaking changes
+    schema_version: u32,
+    kind: ReportKind,
+    data: T,
+}
+
+/// Formats the `data` of a report as `--json`, wrapped in the versioned envelope.
+pub(super) fn format_json(kind: ReportKind, data: impl Serialize) -> anyhow::Result<String> {
+    let output = JsonOutput {
+        schema_version: JSON_SCHEMA_VERSION,
+        kind,
+        data,
+    };
+    Ok(serde_json::to_string_pretty(&output)? + "\n")
+}
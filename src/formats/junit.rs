@@ -0,0 +1,219 @@
+//! JUnit XML, see `--format junit`, which reports the builds as test cases
+//! for CI dashboards, e.g. each package, or each job of an evaluation.
+//!
+//! Successful builds pass, failed builds are failures, queued or cancelled
+//! builds are skipped, and missing (or removed) jobs are errors; the status
+//! of the build and its Hydra URL make up the message.
+
+use std::fmt::Write;
+
+use super::{html::escape, FlatRow, ReportKind};
+use crate::StatusIcon;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Outcome {
+    Passed,
+    Failure,
+    Skipped,
+    Error,
+}
+
+impl From<&StatusIcon> for Outcome {
+    fn from(icon: &StatusIcon) -> Self {
+        match icon {
+            StatusIcon::Succeeded => Self::Passed,
+            StatusIcon::Failed => Self::Failure,
+            StatusIcon::Queued | StatusIcon::Cancelled => Self::Skipped,
+            StatusIcon::Warning => Self::Error,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct TestCase {
+    classname: String,
+    name: String,
+    outcome: Outcome,
+    /// Status of the build, e.g. `Dependency failed`
+    status: String,
+    url: Option<String>,
+}
+
+impl TestCase {
+    fn message(&self) -> String {
+        match &self.url {
+            Some(url) => format!("{}: {url}", self.status),
+            None => self.status.clone(),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct TestSuite {
+    name: String,
+    cases: Vec<TestCase>,
+}
+
+impl TestSuite {
+    fn count(&self, outcome: Outcome) -> usize {
+        self.cases.iter().filter(|x| x.outcome == outcome).count()
+    }
+}
+
+/// Test suites of the reports, collected until the whole document is written
+#[derive(Debug, Default)]
+pub(crate) struct Report {
+    suites: Vec<TestSuite>,
+}
+
+impl ReportKind {
+    /// Checks if the key of the report is a test case on its own, with the
+    /// latest build as its outcome, instead of a test suite of its rows.
+    fn is_test_case(self) -> bool {
+        matches!(self, Self::Packages | Self::Builds | Self::StorePaths)
+    }
+}
+
+impl Report {
+    /// Collects the rows with a status as test cases, e.g. the builds of a
+    /// package or of an evaluation, but not the search results.
+    pub(super) fn collect_rows<T: FlatRow>(
+        &mut self,
+        kind: ReportKind,
+        key: Option<&str>,
+        group: Option<&str>,
+        rows: &[T],
+    ) {
+        let column = |values: &[String], names: &[&str]| {
+            T::COLUMNS
+                .iter()
+                .zip(values)
+                .find(|(column, value)| names.contains(column) && !value.is_empty())
+                .map(|(_, value)| value.clone())
+        };
+        let (suite_name, rows) = match (kind.is_test_case(), key) {
+            (true, _) => (kind.name(), rows.get(..1).unwrap_or_default()),
+            (false, Some(key)) => (format!("{} {key}", kind.name()), rows),
+            (false, None) => (kind.name(), rows),
+        };
+        let classname = match group {
+            Some(group) => format!("{}.{group}", kind.name()),
+            None => kind.name(),
+        };
+        let cases = rows.iter().filter_map(|row| {
+            let values = row.values();
+            let name = match kind.is_test_case() {
+                true => key.map(str::to_string),
+                false => column(&values, &["job"]).or_else(|| values.first().cloned()),
+            };
+            Some(TestCase {
+                classname: classname.clone(),
+                name: name.unwrap_or_default(),
+                outcome: row.icon()?.into(),
+                status: column(&values, &["status", "test_status"]).unwrap_or_default(),
+                url: column(&values, &["url", "release_url"]),
+            })
+        });
+        let suites = &mut self.suites;
+        let index = match suites.iter().position(|x| x.name == suite_name) {
+            Some(index) => index,
+            None => {
+                suites.push(TestSuite {
+                    name: suite_name,
+                    cases: vec![],
+                });
+                suites.len() - 1
+            }
+        };
+        suites[index].cases.extend(cases);
+    }
+
+    /// Formats the document with the collected test suites of the report.
+    pub(super) fn format_document(self) -> Result<String, std::fmt::Error> {
+        format_document(&self.suites)
+    }
+}
+
+fn format_counts(cases: usize, failures: usize, errors: usize, skipped: usize) -> String {
+    format!(r#"tests="{cases}" failures="{failures}" errors="{errors}" skipped="{skipped}""#)
+}
+
+fn format_document(suites: &[TestSuite]) -> Result<String, std::fmt::Error> {
+    let total = |outcome| suites.iter().map(|x| x.count(outcome)).sum::<usize>();
+    let cases = suites.iter().map(|x| x.cases.len()).sum();
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    let counts = format_counts(
+        cases,
+        total(Outcome::Failure),
+        total(Outcome::Error),
+        total(Outcome::Skipped),
+    );
+    writeln!(xml, r#"<testsuites name="hydra-check" {counts}>"#)?;
+    for suite in suites.iter().filter(|x| !x.cases.is_empty()) {
+        let counts = format_counts(
+            suite.cases.len(),
+            suite.count(Outcome::Failure),
+            suite.count(Outcome::Error),
+            suite.count(Outcome::Skipped),
+        );
+        writeln!(
+            xml,
+            r#"  <testsuite name="{}" {counts}>"#,
+            escape(&suite.name)
+        )?;
+        for case in &suite.cases {
+            let (classname, name) = (escape(&case.classname), escape(&case.name));
+            writeln!(
+                xml,
+                r#"    <testcase classname="{classname}" name="{name}">"#
+            )?;
+            let (message, status) = (escape(&case.message()), escape(&case.status));
+            let element = match case.outcome {
+                Outcome::Passed => format!("<system-out>{message}</system-out>"),
+                Outcome::Skipped => format!(r#"<skipped message="{message}"/>"#),
+                Outcome::Failure => format!(r#"<failure message="{message}" type="{status}"/>"#),
+                Outcome::Error => format!(r#"<error message="{message}" type="{status}"/>"#),
+            };
+            writeln!(xml, "      {element}")?;
+            writeln!(xml, "    </testcase>")?;
+        }
+        writeln!(xml, "  </testsuite>")?;
+    }
+    writeln!(xml, "</testsuites>")?;
+    Ok(xml)
+}
+
+#[test]
+fn junit_test_cases() {
+    let case = |name: &str, outcome, status: &str| TestCase {
+        classname: "evals.now_fail".into(),
+        name: name.into(),
+        outcome,
+        status: status.into(),
+        url: Some("https://hydra.nixos.org/build/1".into()),
+    };
+    let suite = TestSuite {
+        name: "evals 1809585".into(),
+        cases: vec![
+            case("hello.x86_64-linux", Outcome::Passed, "Succeeded"),
+            case("nix.i686-linux", Outcome::Failure, "Dependency failed"),
+            case("<removed>", Outcome::Error, "Removed"),
+        ],
+    };
+    insta::assert_snapshot!(format_document(&[suite]).unwrap(), @r#"
+    <?xml version="1.0" encoding="UTF-8"?>
+    <testsuites name="hydra-check" tests="3" failures="1" errors="1" skipped="0">
+      <testsuite name="evals 1809585" tests="3" failures="1" errors="1" skipped="0">
+        <testcase classname="evals.now_fail" name="hello.x86_64-linux">
+          <system-out>Succeeded: https://hydra.nixos.org/build/1</system-out>
+        </testcase>
+        <testcase classname="evals.now_fail" name="nix.i686-linux">
+          <failure message="Dependency failed: https://hydra.nixos.org/build/1" type="Dependency failed"/>
+        </testcase>
+        <testcase classname="evals.now_fail" name="&lt;removed&gt;">
+          <error message="Removed: https://hydra.nixos.org/build/1" type="Removed"/>
+        </testcase>
+      </testsuite>
+    </testsuites>
+    "#);
+}
@@ -0,0 +1,178 @@
+//! Markdown tables, see `--format markdown`, for pasting the reports
+//! into GitHub issues and pull request comments.
+//!
+//! Each report gets a heading, and each group within it, e.g. the
+//! `now_fail` builds of an evaluation, a collapsible `<details>` section.
+//! Rows that would not fit into a single GitHub comment are left out.
+
+use super::{FlatRow, ReportKind};
+use crate::StatusIcon;
+
+/// Maximum length of a GitHub comment, in characters
+const MAX_COMMENT_LENGTH: usize = 65_536;
+
+/// Room kept for the closing tags and the note about omitted rows
+const RESERVED_LENGTH: usize = 256;
+
+/// What is printed so far of the report
+#[derive(Debug, Default)]
+pub(crate) struct Progress {
+    /// Length of everything printed so far in characters,
+    /// counted against [`MAX_COMMENT_LENGTH`]
+    printed_length: usize,
+    /// Key of the last printed report, so that its heading is printed
+    /// only once for all of its groups
+    last_key: Option<String>,
+}
+
+impl StatusIcon {
+    fn emoji(&self) -> &'static str {
+        match self {
+            Self::Succeeded => "✅",
+            Self::Failed => "❌",
+            Self::Cancelled => "⏹️",
+            Self::Queued => "⏳",
+            Self::Warning => "⚠️",
+        }
+    }
+}
+
+/// Escapes a cell so that it stays within its column.
+fn escape(value: &str) -> String {
+    value.replace('|', "\\|").replace('\n', "<br>")
+}
+
+fn format_cell(column: &str, value: &str) -> String {
+    match column {
+        _ if value.is_empty() => String::new(),
+        "url" => format!("[🔗]({value})"),
+        _ if column.ends_with("_url") => format!("[🔗]({value})"),
+        _ => escape(value),
+    }
+}
+
+fn format_line(cells: impl IntoIterator<Item = String>) -> String {
+    let cells: Vec<_> = cells.into_iter().collect();
+    format!("| {} |\n", cells.join(" | "))
+}
+
+/// Formats the rows as a table, preceded by a heading for a new `key`
+/// and wrapped in a `<details>` section for a `group`, in at most
+/// `budget` characters.
+fn format_section<T: FlatRow>(
+    kind: ReportKind,
+    key: Option<&str>,
+    group: Option<&str>,
+    rows: &[T],
+    heading: bool,
+    budget: usize,
+) -> String {
+    let mut section = String::new();
+    if let (true, Some(column), Some(key)) = (heading, kind.key_column(), key) {
+        section.push_str(&["### ", column, " `", key, "`\n\n"].concat());
+    }
+    if let Some(group) = group {
+        let summary = [group, " (", &rows.len().to_string(), ")"].concat();
+        section.push_str(&["<details><summary>", &summary, "</summary>\n\n"].concat());
+    }
+    let icon = rows.first().and_then(FlatRow::icon).map(|_| String::new());
+    let columns = T::COLUMNS.iter().map(|&column| column.to_string());
+    section += &format_line(icon.clone().into_iter().chain(columns));
+    let separator = icon.map(|_| ":-:".to_string()).into_iter();
+    section += &format_line(separator.chain(T::COLUMNS.iter().map(|_| "---".to_string())));
+    let mut length = section.chars().count();
+    let mut shown = 0;
+    for row in rows {
+        let emoji = row.icon().map(|icon| icon.emoji().to_string());
+        let values = row.values();
+        let cells = T::COLUMNS
+            .iter()
+            .zip(&values)
+            .map(|(column, value)| format_cell(column, value));
+        let line = format_line(emoji.into_iter().chain(cells));
+        length += line.chars().count();
+        if length + RESERVED_LENGTH > budget {
+            break;
+        }
+        section += &line;
+        shown += 1;
+    }
+    if shown < rows.len() {
+        let omitted = (rows.len() - shown).to_string();
+        section.push_str(
+            &[
+                "\n_... and ",
+                &omitted,
+                " more, omitted to fit in a comment_\n",
+            ]
+            .concat(),
+        );
+    }
+    if group.is_some() {
+        section += "\n</details>\n";
+    }
+    section + "\n"
+}
+
+impl Progress {
+    /// Formats the rows as a table, until the output of the whole report
+    /// would not fit into a single GitHub comment.
+    pub(super) fn format_rows<T: FlatRow>(
+        &mut self,
+        kind: ReportKind,
+        key: Option<&str>,
+        group: Option<&str>,
+        rows: &[T],
+    ) -> String {
+        if rows.is_empty() {
+            return String::new();
+        }
+        let heading = self.last_key.as_deref() != key;
+        self.last_key = key.map(str::to_string);
+        let budget = MAX_COMMENT_LENGTH.saturating_sub(self.printed_length);
+        let section = format_section(kind, key, group, rows, heading, budget);
+        self.printed_length += section.chars().count();
+        section
+    }
+}
+
+#[test]
+fn markdown_details() {
+    use crate::BuildStatus;
+    let build = BuildStatus {
+        icon: StatusIcon::Failed,
+        status: "Failed | timed out".into(),
+        build_id: Some("115099119".into()),
+        build_url: Some("https://hydra.nixos.org/build/115099119".into()),
+        job_name: Some("hello.x86_64-linux".into()),
+        ..Default::default()
+    };
+    let rows = vec![build; 3];
+    let section = format_section(
+        ReportKind::Evals,
+        Some("1809585"),
+        Some("now_fail"),
+        &rows,
+        true,
+        700,
+    );
+    insta::assert_snapshot!(section, @r"
+    ### eval `1809585`
+
+    <details><summary>now_fail (3)</summary>
+
+    |  | job | name | arch | status | build_id | timestamp | url |
+    | :-: | --- | --- | --- | --- | --- | --- | --- |
+    | ❌ | hello.x86_64-linux |  |  | Failed \| timed out | 115099119 |  | [🔗](https://hydra.nixos.org/build/115099119) |
+    | ❌ | hello.x86_64-linux |  |  | Failed \| timed out | 115099119 |  | [🔗](https://hydra.nixos.org/build/115099119) |
+
+    _... and 1 more, omitted to fit in a comment_
+
+    </details>
+    ");
+    // the length is counted in characters, not in bytes of the emoji
+    let mut progress = Progress::default();
+    let section = progress.format_rows(ReportKind::Evals, None, None, &rows);
+    assert_eq!(progress.printed_length, section.chars().count());
+    assert!(section.len() > progress.printed_length);
+}
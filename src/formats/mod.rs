@@ -0,0 +1,659 @@
+//! Machine-readable output formats of the reports, see `--format`.
+//!
+//! Reports are either printed as a whole once all of them are fetched,
+//! e.g. as a single JSON document, or streamed row by row as soon as
+//! each of them is fetched, e.g. as NDJSON records or CSV rows.
+
+mod atom;
+mod badge;
+mod delimited;
+mod html;
+mod json;
+mod junit;
+mod markdown;
+mod ndjson;
+mod openmetrics;
+mod template;
+
+use std::path::Path;
+
+use clap::ValueEnum;
+use log::info;
+use schemars::JsonSchema;
+use serde::Serialize;
+
+pub(crate) use atom::FeedEntry;
+pub(crate) use badge::{BadgeRows, BadgeStyle};
+pub(crate) use delimited::{cell, FlatRow};
+pub(crate) use json::{JsonOutput, JSON_SCHEMA_VERSION};
+pub(crate) use ndjson::ReportRow;
+pub(crate) use openmetrics::{MetricRows, Metrics, CONTENT_TYPE as OPENMETRICS_CONTENT_TYPE};
+pub(crate) use template::Template;
+
+use crate::ResolvedArgs;
+
+/// Output format of the reports, see `--format`
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub(crate) enum OutputFormat {
+    /// Colored tables and details, for humans
+    #[default]
+    Table,
+    /// A single JSON document, see `--json-schema`
+    Json,
+    /// One JSON record per build, eval or release, printed as soon as fetched
+    Ndjson,
+    /// Comma-separated values with flattened columns, for spreadsheets
+    Csv,
+    /// Tab-separated values with flattened columns, for spreadsheets
+    Tsv,
+    /// Tables with status emoji and links, for GitHub comments
+    Markdown,
+    /// A self-contained page with sortable tables, see `--output`
+    Html,
+    /// JUnit XML with the builds as test cases, for CI dashboards
+    Junit,
+    /// Prometheus metrics of the evals, builds and releases, see `hydra-check exporter`
+    Openmetrics,
+    /// An Atom feed of the builds, evals or releases, for feed readers
+    Atom,
+    /// A shields-style SVG badge of a job, jobset or channel, see `--badge-style`
+    SvgBadge,
+    /// A line per row, see `--template`
+    #[value(skip)]
+    Template,
+}
+
+impl OutputFormat {
+    /// Checks if the reports are printed for humans, as opposed to
+    /// the machine-readable formats that suppress all other output.
+    pub(crate) fn is_table(self) -> bool {
+        self == Self::Table
+    }
+
+    /// Checks if the reports are printed as a single document,
+    /// which can be written to a file with `--output`.
+    pub(crate) fn has_document(self) -> bool {
+        self.document_name().is_some()
+    }
+
+    /// Name of the document in the log, once it is written to a file.
+    fn document_name(self) -> Option<&'static str> {
+        match self {
+            Self::Html => Some("report"),
+            Self::Junit => Some("test report"),
+            Self::Openmetrics => Some("metrics"),
+            Self::Atom => Some("feed"),
+            Self::SvgBadge => Some("badge"),
+            Self::Table
+            | Self::Json
+            | Self::Ndjson
+            | Self::Csv
+            | Self::Tsv
+            | Self::Markdown
+            | Self::Template => None,
+        }
+    }
+}
+
+/// Kind of report in the `data` of the `--json` output
+#[derive(Serialize, JsonSchema, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum ReportKind {
+    /// Recent evaluations of a jobset, keyed by jobset
+    Jobset,
+    /// Recent builds of packages, keyed by package
+    Packages,
+    /// Releases of a channel, see `--releases`, keyed by jobset
+    Releases,
+    /// Details of evaluations, see `--eval`, keyed by evaluation
+    Evals,
+    /// Details of builds, keyed by build ID
+    Builds,
+    /// Builds that produced store paths, keyed by store path
+    StorePaths,
+    /// Search results, keyed by search term
+    Search,
+    /// Projects of the Hydra instance
+    Projects,
+    /// Jobsets of a project, keyed by project
+    Jobsets,
+    /// Configuration of a jobset, see `--config`, keyed by jobset
+    JobsetConfig,
+    /// Summary of the build queue
+    Queue,
+    /// Status of the queue runner and its build machines
+    Machines,
+    /// Position of the running system in its channel, see `hydra-check self`
+    System,
+    /// First evaluations and releases that contain a commit,
+    /// see `hydra-check landed`, keyed by commit and jobset
+    Landed,
+}
+
+impl ReportKind {
+    pub(crate) const ALL: [Self; 14] = [
+        Self::Jobset,
+        Self::Packages,
+        Self::Releases,
+        Self::Evals,
+        Self::Builds,
+        Self::StorePaths,
+        Self::Search,
+        Self::Projects,
+        Self::Jobsets,
+        Self::JobsetConfig,
+        Self::Queue,
+        Self::Machines,
+        Self::System,
+        Self::Landed,
+    ];
+
+    /// Name of the kind, as in the `kind` of the `--json` output
+    fn name(self) -> String {
+        serde_json::to_value(self)
+            .ok()
+            .and_then(|kind| kind.as_str().map(str::to_string))
+            .unwrap_or_default()
+    }
+
+    /// Checks if the report consists of builds, evals or releases,
+    /// which are streamed row by row with [`ResolvedArgs::print_rows`].
+    fn has_rows(self) -> bool {
+        matches!(
+            self,
+            Self::Jobset
+                | Self::Packages
+                | Self::Releases
+                | Self::Evals
+                | Self::Builds
+                | Self::StorePaths
+        )
+    }
+}
+
+/// Output of the reports in a format, which streams their rows right away
+/// or collects them for the whole document, and is reset once the whole
+/// report is printed by [`ResolvedArgs::print_report`]
+#[derive(Debug)]
+pub(crate) enum Sink {
+    /// The tables, which are printed by each query itself
+    Table,
+    Json,
+    Ndjson,
+    /// `--format csv` and `--format tsv`
+    Delimited(delimited::Writer),
+    Markdown(markdown::Progress),
+    Html(html::Page),
+    Junit(junit::Report),
+    Openmetrics(Metrics),
+    Atom(atom::Feed),
+    SvgBadge(badge::Badges),
+    Template(template::Printer),
+}
+
+impl Sink {
+    /// Creates the sink of the `format`, of which `--template` is
+    /// implied by the `template` itself.
+    pub(crate) fn new(
+        format: OutputFormat,
+        template: Option<Template>,
+        badge_label: Option<String>,
+        badge_style: BadgeStyle,
+    ) -> Self {
+        match format {
+            OutputFormat::Table => Self::Table,
+            OutputFormat::Json => Self::Json,
+            OutputFormat::Ndjson => Self::Ndjson,
+            OutputFormat::Csv => Self::Delimited(delimited::Writer::new(b',')),
+            OutputFormat::Tsv => Self::Delimited(delimited::Writer::new(b'\t')),
+            OutputFormat::Markdown => Self::Markdown(markdown::Progress::default()),
+            OutputFormat::Html => Self::Html(html::Page::default()),
+            OutputFormat::Junit => Self::Junit(junit::Report::default()),
+            OutputFormat::Openmetrics => Self::Openmetrics(Metrics::default()),
+            OutputFormat::Atom => Self::Atom(atom::Feed::default()),
+            OutputFormat::SvgBadge => Self::SvgBadge(badge::Badges::new(badge_label, badge_style)),
+            OutputFormat::Template => match template {
+                Some(template) => Self::Template(template::Printer::new(template)),
+                None => Self::Table,
+            },
+        }
+    }
+
+    /// Formats the rows of a report of the `jobset` that are streamed,
+    /// or collects them for the whole document.
+    fn add_rows<T: ReportRow>(
+        &mut self,
+        kind: ReportKind,
+        jobset: &str,
+        key: &str,
+        group: Option<&str>,
+        rows: &[T],
+    ) -> anyhow::Result<String> {
+        match self {
+            Self::Ndjson => ndjson::format_records(kind, key, group, rows),
+            Self::Openmetrics(metrics) => {
+                T::add_metrics(metrics, jobset, key, rows);
+                Ok(String::new())
+            }
+            Self::Atom(feed) => {
+                feed.collect_rows(kind, jobset, key, rows);
+                Ok(String::new())
+            }
+            Self::SvgBadge(badges) => {
+                badges.collect_rows(kind, key, rows);
+                Ok(String::new())
+            }
+            _ => self.add_flat_rows(kind, Some(key), group, rows),
+        }
+    }
+
+    /// Formats the rows of a report that is not streamed, e.g. the jobsets
+    /// of a project, for the formats that flatten every report into rows.
+    fn add_flat_rows<T: FlatRow + Serialize>(
+        &mut self,
+        kind: ReportKind,
+        key: Option<&str>,
+        group: Option<&str>,
+        rows: &[T],
+    ) -> anyhow::Result<String> {
+        match self {
+            Self::Delimited(writer) => writer.format_rows(kind, key, group, rows),
+            Self::Markdown(progress) => Ok(progress.format_rows(kind, key, group, rows)),
+            Self::Html(page) => {
+                page.collect_rows(key, group, rows);
+                Ok(String::new())
+            }
+            Self::Junit(report) => {
+                report.collect_rows(kind, key, group, rows);
+                Ok(String::new())
+            }
+            Self::Template(printer) => Ok(printer.format_rows(kind, key, group, rows)),
+            Self::Table
+            | Self::Json
+            | Self::Ndjson
+            | Self::Openmetrics(_)
+            | Self::Atom(_)
+            | Self::SvgBadge(_) => Ok(String::new()),
+        }
+    }
+
+    /// Formats the whole report once all of it is fetched,
+    /// and resets the sink for the next report.
+    fn finish(&mut self, kind: ReportKind, data: impl Serialize) -> anyhow::Result<String> {
+        match self {
+            Self::Json => json::format_json(kind, data),
+            Self::Ndjson if !kind.has_rows() => ndjson::format_report(kind, data),
+            Self::Delimited(writer) => {
+                writer.reset();
+                Ok(String::new())
+            }
+            Self::Markdown(progress) => {
+                *progress = markdown::Progress::default();
+                Ok(String::new())
+            }
+            Self::Html(page) => std::mem::take(page).format_page(kind, data),
+            Self::Junit(report) => Ok(std::mem::take(report).format_document()?),
+            Self::Openmetrics(metrics) => Ok(std::mem::take(metrics).render()?),
+            Self::Atom(feed) => {
+                let now = chrono::Utc::now().timestamp();
+                Ok(std::mem::take(feed).format_document(now)?)
+            }
+            Self::SvgBadge(badges) => badges.format_document(),
+            Self::Template(printer) => {
+                printer.reset();
+                Ok(String::new())
+            }
+            Self::Ndjson | Self::Table => Ok(String::new()),
+        }
+    }
+}
+
+impl ResolvedArgs {
+    /// Prints the rows of a report as soon as they are fetched,
+    /// for the formats that stream them, e.g. `--format ndjson`.
+    /// The `key` is the package, jobset, evaluation, build or store path
+    /// of the report, and the `group` distinguishes the rows within it.
+    pub(crate) fn print_rows<T: ReportRow>(
+        &self,
+        kind: ReportKind,
+        key: &str,
+        group: Option<&str>,
+        rows: &[T],
+    ) -> anyhow::Result<()> {
+        let text = self
+            .sink
+            .borrow_mut()
+            .add_rows(kind, &self.jobset, key, group, rows)?;
+        // stdout is line buffered, so each record is flushed right away
+        print!("{text}");
+        Ok(())
+    }
+
+    /// Prints the rows of a report that is not streamed, e.g. the jobsets
+    /// of a project, for the formats that flatten every report into rows,
+    /// i.e. `--format csv`, `--format tsv`, `--format markdown`
+    /// `--format html` and `--format junit`, which collect them for the
+    /// whole document.
+    pub(crate) fn print_flat_rows<T: FlatRow + Serialize>(
+        &self,
+        kind: ReportKind,
+        key: Option<&str>,
+        group: Option<&str>,
+        rows: &[T],
+    ) -> anyhow::Result<()> {
+        let text = self
+            .sink
+            .borrow_mut()
+            .add_flat_rows(kind, key, group, rows)?;
+        print!("{text}");
+        Ok(())
+    }
+
+    /// Prints the metrics of `hydra-check exporter` once, like the other
+    /// queries with `--format openmetrics`.
+    pub(crate) fn print_metrics(&self, metrics: &Metrics) -> anyhow::Result<()> {
+        write_output(self.output.as_deref(), &metrics.render()?, "metrics")
+    }
+
+    /// Prints the collected reports once all of them are fetched,
+    /// for the formats that do not stream them row by row.
+    pub(crate) fn print_report(
+        &self,
+        kind: ReportKind,
+        data: impl Serialize,
+    ) -> anyhow::Result<()> {
+        let text = self.sink.borrow_mut().finish(kind, data)?;
+        match self.format.document_name() {
+            Some(name) => write_output(self.output.as_deref(), &text, name),
+            None => {
+                print!("{text}");
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Writes a document to the `output` file, or else to stdout; the file is
+/// renamed into place, so that it is never read while partially written.
+fn write_output(output: Option<&Path>, document: &str, name: &str) -> anyhow::Result<()> {
+    match output {
+        Some(path) => {
+            // next to the file, e.g. `report.html.1234.partial`, so that
+            // neither its siblings nor concurrent runs are overwritten
+            let mut partial = path.as_os_str().to_owned();
+            partial.push(format!(".{}.partial", std::process::id()));
+            std::fs::write(&partial, document)?;
+            std::fs::rename(&partial, path)?;
+            info!("wrote the {name} to {}", path.display());
+        }
+        None => print!("{document}"),
+    }
+    Ok(())
+}
+
+#[test]
+fn write_output_file() {
+    let dir = std::env::temp_dir().join(format!("hydra-check-output-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let (path, sibling) = (dir.join("report.html"), dir.join("report.partial"));
+    std::fs::write(&sibling, "sibling").unwrap();
+    write_output(Some(&path), "first", "report").unwrap();
+    write_output(Some(&path), "second", "report").unwrap();
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), "second");
+    assert_eq!(std::fs::read_to_string(&sibling).unwrap(), "sibling");
+    assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 2);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+/// Adds the builds of two packages to the sink, and returns the output
+/// of the rows and of the whole report.
+#[cfg(test)]
+fn print_packages(sink: &mut Sink) -> String {
+    let (hello, nix) = (fixture_builds(), fixture_builds()[1..].to_vec());
+    let mut output = sink
+        .add_rows(
+            ReportKind::Packages,
+            "nixpkgs/unstable",
+            "hello",
+            None,
+            &hello,
+        )
+        .unwrap();
+    output += &sink
+        .add_rows(ReportKind::Packages, "nixpkgs/unstable", "nix", None, &nix)
+        .unwrap();
+    let data = indexmap::IndexMap::from([("hello", hello), ("nix", nix)]);
+    output + &sink.finish(ReportKind::Packages, data).unwrap()
+}
+
+/// The latest builds of a job, of which the latest one failed
+#[cfg(test)]
+fn fixture_builds() -> Vec<crate::BuildStatus> {
+    use crate::{BuildStatus, StatusIcon};
+    let build = |id: &str, success: bool, status: &str, date: &str| BuildStatus {
+        icon: match success {
+            true => StatusIcon::Succeeded,
+            false => StatusIcon::Failed,
+        },
+        success,
+        status: status.into(),
+        timestamp: Some(format!("{date}T09:45:00Z")),
+        build_id: Some(id.into()),
+        build_url: Some(format!("https://hydra.nixos.org/build/{id}")),
+        name: Some("hello-2.12.1".into()),
+        arch: Some("x86_64-linux".into()),
+        evals: true,
+        ..Default::default()
+    };
+    vec![
+        build("277560645", false, "Failed", "2024-11-07"),
+        build("277403713", true, "Succeeded", "2024-11-05"),
+    ]
+}
+
+#[test]
+fn print_json_reports() {
+    let mut sink = Sink::new(OutputFormat::Json, None, None, BadgeStyle::default());
+    let output = print_packages(&mut sink);
+    // nothing is left over from the first report
+    assert_eq!(print_packages(&mut sink), output);
+    let json: serde_json::Value = serde_json::from_str(&output).unwrap();
+    assert_eq!(json["kind"], "packages");
+    assert_eq!(json["data"]["hello"][0]["icon"], "failed");
+    assert_eq!(json["data"]["nix"][0]["build_id"], "277403713");
+    // the rows are streamed, so the whole report is not repeated
+    let mut sink = Sink::new(OutputFormat::Ndjson, None, None, BadgeStyle::default());
+    let keys: Vec<_> = print_packages(&mut sink)
+        .lines()
+        .map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap())
+        .map(|record| format!("{} {}", record["key"], record["data"]["build_id"]))
+        .collect();
+    assert_eq!(
+        keys,
+        [
+            r#""hello" "277560645""#,
+            r#""hello" "277403713""#,
+            r#""nix" "277403713""#
+        ]
+    );
+    let output = sink.finish(ReportKind::Projects, ["nixpkgs"]).unwrap();
+    assert_eq!(
+        output,
+        r#"{"schema_version":1,"kind":"projects","record":"report","data":["nixpkgs"]}"#.to_owned()
+            + "\n"
+    );
+}
+
+#[test]
+fn print_delimited_reports() {
+    let mut sink = Sink::new(OutputFormat::Csv, None, None, BadgeStyle::default());
+    let output = print_packages(&mut sink);
+    // the header is printed again for the next report
+    assert_eq!(print_packages(&mut sink), output);
+    insta::assert_snapshot!(output, @r"
+    package,job,name,arch,status,build_id,timestamp,url
+    hello,,hello-2.12.1,x86_64-linux,Failed,277560645,2024-11-07T09:45:00Z,https://hydra.nixos.org/build/277560645
+    hello,,hello-2.12.1,x86_64-linux,Succeeded,277403713,2024-11-05T09:45:00Z,https://hydra.nixos.org/build/277403713
+    nix,,hello-2.12.1,x86_64-linux,Succeeded,277403713,2024-11-05T09:45:00Z,https://hydra.nixos.org/build/277403713
+    ");
+}
+
+#[test]
+fn print_markdown_reports() {
+    let mut sink = Sink::new(OutputFormat::Markdown, None, None, BadgeStyle::default());
+    let output = print_packages(&mut sink);
+    // the headings and the length are counted again for the next report
+    assert_eq!(print_packages(&mut sink), output);
+    insta::assert_snapshot!(output, @r"
+    ### package `hello`
+
+    |  | job | name | arch | status | build_id | timestamp | url |
+    | :-: | --- | --- | --- | --- | --- | --- | --- |
+    | ❌ |  | hello-2.12.1 | x86_64-linux | Failed | 277560645 | 2024-11-07T09:45:00Z | [🔗](https://hydra.nixos.org/build/277560645) |
+    | ✅ |  | hello-2.12.1 | x86_64-linux | Succeeded | 277403713 | 2024-11-05T09:45:00Z | [🔗](https://hydra.nixos.org/build/277403713) |
+
+    ### package `nix`
+
+    |  | job | name | arch | status | build_id | timestamp | url |
+    | :-: | --- | --- | --- | --- | --- | --- | --- |
+    | ✅ |  | hello-2.12.1 | x86_64-linux | Succeeded | 277403713 | 2024-11-05T09:45:00Z | [🔗](https://hydra.nixos.org/build/277403713) |
+    ");
+}
+
+#[test]
+fn print_html_reports() {
+    let mut sink = Sink::new(OutputFormat::Html, None, None, BadgeStyle::default());
+    let body = |output: String| {
+        let body = output.split("</p>\n").nth(1).unwrap_or_default();
+        body.split("<script>")
+            .next()
+            .unwrap_or_default()
+            .to_string()
+    };
+    let output = body(print_packages(&mut sink));
+    // the tables are not collected again for the next report
+    assert_eq!(body(print_packages(&mut sink)), output);
+    insta::assert_snapshot!(output, @r#"
+    <h2>package <code>hello</code></h2>
+    <table>
+    <thead><tr><th>job</th><th>name</th><th>arch</th><th>status</th><th>build_id</th><th>timestamp</th><th>url</th></tr></thead>
+    <tbody>
+    <tr class="failed"><td></td><td>hello-2.12.1</td><td>x86_64-linux</td><td>Failed</td><td>277560645</td><td>2024-11-07T09:45:00Z</td><td><a href="https://hydra.nixos.org/build/277560645">https://hydra.nixos.org/build/277560645</a></td></tr>
+    <tr class="succeeded"><td></td><td>hello-2.12.1</td><td>x86_64-linux</td><td>Succeeded</td><td>277403713</td><td>2024-11-05T09:45:00Z</td><td><a href="https://hydra.nixos.org/build/277403713">https://hydra.nixos.org/build/277403713</a></td></tr>
+    </tbody>
+    </table>
+    <h2>package <code>nix</code></h2>
+    <table>
+    <thead><tr><th>job</th><th>name</th><th>arch</th><th>status</th><th>build_id</th><th>timestamp</th><th>url</th></tr></thead>
+    <tbody>
+    <tr class="succeeded"><td></td><td>hello-2.12.1</td><td>x86_64-linux</td><td>Succeeded</td><td>277403713</td><td>2024-11-05T09:45:00Z</td><td><a href="https://hydra.nixos.org/build/277403713">https://hydra.nixos.org/build/277403713</a></td></tr>
+    </tbody>
+    </table>
+    "#);
+}
+
+#[test]
+fn print_junit_reports() {
+    let mut sink = Sink::new(OutputFormat::Junit, None, None, BadgeStyle::default());
+    let output = print_packages(&mut sink);
+    // the test cases are not collected again for the next report
+    assert_eq!(print_packages(&mut sink), output);
+    insta::assert_snapshot!(output, @r#"
+    <?xml version="1.0" encoding="UTF-8"?>
+    <testsuites name="hydra-check" tests="2" failures="1" errors="0" skipped="0">
+      <testsuite name="packages" tests="2" failures="1" errors="0" skipped="0">
+        <testcase classname="packages" name="hello">
+          <failure message="Failed: https://hydra.nixos.org/build/277560645" type="Failed"/>
+        </testcase>
+        <testcase classname="packages" name="nix">
+          <system-out>Succeeded: https://hydra.nixos.org/build/277403713</system-out>
+        </testcase>
+      </testsuite>
+    </testsuites>
+    "#);
+}
+
+#[test]
+fn print_openmetrics_reports() {
+    let mut sink = Sink::new(OutputFormat::Openmetrics, None, None, BadgeStyle::default());
+    // the ages depend on the current time
+    let samples = |output: String| {
+        let lines = output.lines().filter(|x| !x.contains("age_seconds"));
+        lines.collect::<Vec<_>>().join("\n")
+    };
+    let output = samples(print_packages(&mut sink));
+    // the metrics are not collected again for the next report
+    assert_eq!(samples(print_packages(&mut sink)), output);
+    insta::assert_snapshot!(output, @r#"
+    # TYPE hydra_build_succeeded gauge
+    # HELP hydra_build_succeeded Whether the latest build of the job succeeded.
+    hydra_build_succeeded{jobset="nixpkgs/unstable",job="hello"} 0
+    hydra_build_succeeded{jobset="nixpkgs/unstable",job="nix"} 1
+    # TYPE hydra_build_status gauge
+    # HELP hydra_build_status Status code of the latest finished build of the job, e.g. 2 if a dependency failed.
+    hydra_build_status{jobset="nixpkgs/unstable",job="hello"} 1
+    hydra_build_status{jobset="nixpkgs/unstable",job="nix"} 0
+    # EOF
+    "#);
+}
+
+#[test]
+fn print_template_reports() {
+    let template = Template::parse("{icon} {key:<6}{missing}{build_id}").unwrap();
+    let mut sink = Sink::new(
+        OutputFormat::Template,
+        Some(template),
+        None,
+        BadgeStyle::default(),
+    );
+    let output = print_packages(&mut sink);
+    assert_eq!(print_packages(&mut sink), output);
+    insta::assert_snapshot!(output, @r#"
+    ✖ hello 277560645
+    ✔ hello 277403713
+    ✔ nix   277403713
+    "#);
+}
+
+#[test]
+fn print_atom_reports() {
+    let mut sink = Sink::new(OutputFormat::Atom, None, None, BadgeStyle::default());
+    let output = print_packages(&mut sink);
+    // the entries are not collected again for the next report
+    assert_eq!(print_packages(&mut sink), output);
+    let entries = output.split_once("</generator>\n").unwrap_or_default().1;
+    insta::assert_snapshot!(entries, @r#"
+      <entry>
+        <id>https://hydra.nixos.org/build/277560645</id>
+        <title>hello: Failed</title>
+        <updated>2024-11-07T09:45:00Z</updated>
+        <link href="https://hydra.nixos.org/build/277560645"/>
+        <summary>hello-2.12.1 on x86_64-linux: Failed</summary>
+      </entry>
+      <entry>
+        <id>https://hydra.nixos.org/build/277403713</id>
+        <title>hello: Succeeded</title>
+        <updated>2024-11-05T09:45:00Z</updated>
+        <link href="https://hydra.nixos.org/build/277403713"/>
+        <summary>hello-2.12.1 on x86_64-linux: Succeeded</summary>
+      </entry>
+      <entry>
+        <id>https://hydra.nixos.org/build/277403713</id>
+        <title>nix: Succeeded</title>
+        <updated>2024-11-05T09:45:00Z</updated>
+        <link href="https://hydra.nixos.org/build/277403713"/>
+        <summary>hello-2.12.1 on x86_64-linux: Succeeded</summary>
+      </entry>
+    </feed>
+    "#);
+}
+
+#[test]
+fn print_badge_reports() {
+    let mut sink = Sink::new(OutputFormat::SvgBadge, None, None, BadgeStyle::FlatSquare);
+    let output = print_packages(&mut sink);
+    // only the badge of the first report is written, again for the next one
+    assert_eq!(print_packages(&mut sink), output);
+    assert!(output.contains(r#"aria-label="hello: failed""#));
+    sink.finish(ReportKind::Projects, ()).unwrap_err();
+}
@@ -0,0 +1,116 @@
+//! Newline-delimited JSON, see `--format ndjson`, which prints one
+//! self-describing record per build, eval or release as soon as it
+//! is fetched, for `jq` pipelines and log shippers.
+
+use serde::Serialize;
+use serde_with::skip_serializing_none;
+
+use super::{BadgeRows, FeedEntry, FlatRow, MetricRows, ReportKind, JSON_SCHEMA_VERSION};
+use crate::{structs::ReleaseStatus, BuildStatus, EvalStatus};
+
+/// Type of the `data` of a record
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum RecordType {
+    Build,
+    Eval,
+    Release,
+    /// A whole report, for the kinds that are not made of rows
+    Report,
+}
+
+/// Rows of a report that are streamed as individual records
+pub(crate) trait ReportRow:
+    Serialize + FlatRow + MetricRows + FeedEntry + BadgeRows
+{
+    const RECORD: RecordType;
+}
+
+impl ReportRow for BuildStatus {
+    const RECORD: RecordType = RecordType::Build;
+}
+
+impl ReportRow for EvalStatus {
+    const RECORD: RecordType = RecordType::Eval;
+}
+
+impl ReportRow for ReleaseStatus {
+    const RECORD: RecordType = RecordType::Release;
+}
+
+/// A single line of the `--format ndjson` output
+#[skip_serializing_none]
+#[derive(Serialize)]
+struct NdjsonRecord<'a, T> {
+    schema_version: u32,
+    kind: ReportKind,
+    record: RecordType,
+    /// e.g. the package, jobset, evaluation, build or store path
+    key: Option<&'a str>,
+    /// e.g. `now_fail` for the builds of an evaluation
+    group: Option<&'a str>,
+    data: T,
+}
+
+impl<T: Serialize> NdjsonRecord<'_, T> {
+    fn to_line(&self) -> serde_json::Result<String> {
+        serde_json::to_string(self)
+    }
+}
+
+/// Formats each row as a record on a line of its own.
+pub(super) fn format_records<T: ReportRow>(
+    kind: ReportKind,
+    key: &str,
+    group: Option<&str>,
+    rows: &[T],
+) -> anyhow::Result<String> {
+    let mut lines = String::new();
+    for row in rows {
+        let record = NdjsonRecord {
+            schema_version: JSON_SCHEMA_VERSION,
+            kind,
+            record: T::RECORD,
+            key: Some(key),
+            group,
+            data: row,
+        };
+        lines += &(record.to_line()? + "\n");
+    }
+    Ok(lines)
+}
+
+pub(super) fn format_report(kind: ReportKind, data: impl Serialize) -> anyhow::Result<String> {
+    let record = NdjsonRecord {
+        schema_version: JSON_SCHEMA_VERSION,
+        kind,
+        record: RecordType::Report,
+        key: None,
+        group: None,
+        data,
+    };
+    Ok(record.to_line()? + "\n")
+}
+
+#[test]
+fn ndjson_record() {
+    let build = BuildStatus {
+        icon: crate::StatusIcon::Succeeded,
+        success: true,
+        status: "Succeeded".into(),
+        build_id: Some("57619684".into()),
+        ..Default::default()
+    };
+    let record = NdjsonRecord {
+        schema_version: JSON_SCHEMA_VERSION,
+        kind: ReportKind::Packages,
+        record: BuildStatus::RECORD,
+        key: Some("hello"),
+        group: None,
+        data: &build,
+    };
+    assert_eq!(
+        record.to_line().unwrap(),
+        r#"{"schema_version":1,"kind":"packages","record":"build","key":"hello","data":{"icon":"succeeded","success":true,"status":"Succeeded","build_id":"57619684","evals":false}}"#
+    );
+}
@@ -0,0 +1,279 @@
+//! OpenMetrics (Prometheus) text exposition, see `--format openmetrics`
+//! and `hydra-check exporter`, e.g. for the textfile collector of
+//! `node_exporter`, with the build counts of evaluations, the status
+//! and age of the latest builds, and the lag of channel releases.
+
+use std::{collections::HashSet, fmt::Write};
+
+use crate::{
+    structs::{buildstatus_code, ReleaseStatus},
+    BuildStatus, EvalStatus,
+};
+
+/// Content type of `/metrics`, see `hydra-check exporter`
+pub(crate) const CONTENT_TYPE: &str = "application/openmetrics-text; version=1.0.0; charset=utf-8";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Family {
+    EvalBuilds,
+    BuildSucceeded,
+    BuildStatus,
+    BuildAge,
+    ChannelLag,
+    CheckUp,
+}
+
+impl Family {
+    const ALL: [Self; 6] = [
+        Self::EvalBuilds,
+        Self::BuildSucceeded,
+        Self::BuildStatus,
+        Self::BuildAge,
+        Self::ChannelLag,
+        Self::CheckUp,
+    ];
+
+    /// Name, unit and description of the metric family
+    fn describe(self) -> (&'static str, Option<&'static str>, &'static str) {
+        match self {
+            Self::EvalBuilds => (
+                "hydra_eval_builds",
+                None,
+                "Number of builds of an evaluation, per status.",
+            ),
+            Self::BuildSucceeded => (
+                "hydra_build_succeeded",
+                None,
+                "Whether the latest build of the job succeeded.",
+            ),
+            Self::BuildStatus => (
+                "hydra_build_status",
+                None,
+                "Status code of the latest finished build of the job, e.g. 2 if a dependency failed.",
+            ),
+            Self::BuildAge => (
+                "hydra_build_age_seconds",
+                Some("seconds"),
+                "Time since the latest build of the job finished.",
+            ),
+            Self::ChannelLag => (
+                "hydra_channel_lag_seconds",
+                Some("seconds"),
+                "Time since the evaluation of the latest release of the jobset.",
+            ),
+            Self::CheckUp => (
+                "hydra_check_up",
+                None,
+                "Whether the metrics of the jobset could be collected.",
+            ),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Sample {
+    family: Family,
+    labels: Vec<(&'static str, String)>,
+    value: u64,
+}
+
+/// Samples of the metrics, rendered in the text format by [`Metrics::render`]
+#[derive(Debug, Default)]
+pub(crate) struct Metrics {
+    samples: Vec<Sample>,
+}
+
+/// Rows of a report that are exported as metrics, labelled by the jobset
+/// and the `key` of the report, e.g. the package of the builds.
+pub(crate) trait MetricRows: Sized {
+    fn add_metrics(metrics: &mut Metrics, jobset: &str, key: &str, rows: &[Self]);
+}
+
+impl MetricRows for EvalStatus {
+    fn add_metrics(metrics: &mut Metrics, jobset: &str, _: &str, rows: &[Self]) {
+        for eval in rows {
+            let Some(id) = eval.id else {
+                continue;
+            };
+            let counts = [
+                ("succeeded", eval.succeeded),
+                ("failed", eval.failed),
+                ("queued", eval.queued),
+            ];
+            for (status, count) in counts {
+                let labels = [
+                    ("jobset", jobset),
+                    ("eval", &id.to_string()),
+                    ("status", status),
+                ];
+                metrics.add(Family::EvalBuilds, &labels, count.unwrap_or_default());
+            }
+        }
+    }
+}
+
+impl MetricRows for BuildStatus {
+    /// Adds the latest build of each job, i.e. the first build of a package,
+    /// or every build of an evaluation, which are named by their job.
+    fn add_metrics(metrics: &mut Metrics, jobset: &str, key: &str, rows: &[Self]) {
+        let mut jobs = HashSet::new();
+        let now = chrono::Utc::now().timestamp();
+        for build in rows {
+            let job = build.job_name.as_deref().unwrap_or(key);
+            if !jobs.insert(job) {
+                continue;
+            }
+            let labels = [("jobset", jobset), ("job", job)];
+            metrics.add(Family::BuildSucceeded, &labels, u64::from(build.success));
+            if let Some(code) = buildstatus_code(&build.status) {
+                metrics.add(Family::BuildStatus, &labels, code);
+            }
+            if let Some(timestamp) = build.unix_timestamp() {
+                let age = now.saturating_sub(timestamp).try_into().unwrap_or_default();
+                metrics.add(Family::BuildAge, &labels, age);
+            }
+        }
+    }
+}
+
+impl MetricRows for ReleaseStatus {
+    /// Adds the time since the latest release, i.e. the newest evaluation
+    /// that passed its release tests, which grows until the next release.
+    fn add_metrics(metrics: &mut Metrics, jobset: &str, _: &str, rows: &[Self]) {
+        let now = chrono::Utc::now().timestamp();
+        let released = rows
+            .iter()
+            .find(|x| x.test.success)
+            .and_then(|x| x.eval.timestamp)
+            .and_then(|x| i64::try_from(x).ok());
+        if let Some(released) = released {
+            let lag = now.saturating_sub(released).try_into().unwrap_or_default();
+            metrics.add(Family::ChannelLag, &[("jobset", jobset)], lag);
+        }
+    }
+}
+
+/// Escapes a label value, see the OpenMetrics specification.
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', r"\\")
+        .replace('"', r#"\""#)
+        .replace('\n', r"\n")
+}
+
+impl Metrics {
+    fn add(&mut self, family: Family, labels: &[(&'static str, &str)], value: u64) {
+        let labels = labels.iter().map(|&(x, y)| (x, y.to_string())).collect();
+        self.samples.push(Sample {
+            family,
+            labels,
+            value,
+        });
+    }
+
+    /// Adds whether the metrics of the `jobset` could be collected.
+    pub(crate) fn add_up(&mut self, jobset: &str, up: bool) {
+        self.add(Family::CheckUp, &[("jobset", jobset)], u64::from(up));
+    }
+
+    /// Moves the samples of `other`, e.g. of a single jobset, into these.
+    pub(crate) fn append(&mut self, mut other: Self) {
+        self.samples.append(&mut other.samples);
+    }
+
+    /// Renders the samples in the text format, grouped by metric family.
+    pub(crate) fn render(&self) -> Result<String, std::fmt::Error> {
+        let mut text = String::new();
+        for family in Family::ALL {
+            let samples: Vec<_> = self.samples.iter().filter(|x| x.family == family).collect();
+            if samples.is_empty() {
+                continue;
+            }
+            let (name, unit, help) = family.describe();
+            writeln!(text, "# TYPE {name} gauge")?;
+            if let Some(unit) = unit {
+                writeln!(text, "# UNIT {name} {unit}")?;
+            }
+            writeln!(text, "# HELP {name} {help}")?;
+            for sample in samples {
+                let labels: Vec<_> = sample
+                    .labels
+                    .iter()
+                    .map(|(label, value)| format!(r#"{label}="{}""#, escape(value)))
+                    .collect();
+                writeln!(text, "{name}{{{}}} {}", labels.join(","), sample.value)?;
+            }
+        }
+        writeln!(text, "# EOF")?;
+        Ok(text)
+    }
+}
+
+#[test]
+fn render_metrics() {
+    let eval = EvalStatus {
+        id: Some(1_809_585),
+        succeeded: Some(170_000),
+        failed: Some(12),
+        queued: None,
+        ..Default::default()
+    };
+    let build = BuildStatus {
+        status: "Dependency failed".into(),
+        ..Default::default()
+    };
+    let mut metrics = Metrics::default();
+    EvalStatus::add_metrics(&mut metrics, "nixos/trunk-combined", "", &[eval]);
+    let mut jobset_metrics = Metrics::default();
+    BuildStatus::add_metrics(
+        &mut jobset_metrics,
+        "nixos/trunk-combined",
+        "hello",
+        &[build],
+    );
+    metrics.append(jobset_metrics);
+    metrics.add_up("nixos/trunk-combined", true);
+    metrics.add_up("nixos/release-25.05", false);
+    insta::assert_snapshot!(metrics.render().unwrap(), @r#"
+    # TYPE hydra_eval_builds gauge
+    # HELP hydra_eval_builds Number of builds of an evaluation, per status.
+    hydra_eval_builds{jobset="nixos/trunk-combined",eval="1809585",status="succeeded"} 170000
+    hydra_eval_builds{jobset="nixos/trunk-combined",eval="1809585",status="failed"} 12
+    hydra_eval_builds{jobset="nixos/trunk-combined",eval="1809585",status="queued"} 0
+    # TYPE hydra_build_succeeded gauge
+    # HELP hydra_build_succeeded Whether the latest build of the job succeeded.
+    hydra_build_succeeded{jobset="nixos/trunk-combined",job="hello"} 0
+    # TYPE hydra_build_status gauge
+    # HELP hydra_build_status Status code of the latest finished build of the job, e.g. 2 if a dependency failed.
+    hydra_build_status{jobset="nixos/trunk-combined",job="hello"} 2
+    # TYPE hydra_check_up gauge
+    # HELP hydra_check_up Whether the metrics of the jobset could be collected.
+    hydra_check_up{jobset="nixos/trunk-combined"} 1
+    hydra_check_up{jobset="nixos/release-25.05"} 0
+    # EOF
+    "#);
+    assert_eq!(escape("a \"b\"\\\n"), r#"a \"b\"\\\n"#);
+}
+
+#[test]
+fn channel_lag() {
+    let release = |success: bool, age: i64| ReleaseStatus {
+        eval: EvalStatus {
+            timestamp: u64::try_from(chrono::Utc::now().timestamp() - age).ok(),
+            ..Default::default()
+        },
+        test: BuildStatus {
+            success,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let mut metrics = Metrics::default();
+    // the lag grows since the latest release, even without newer evaluations
+    let releases = [release(false, 600), release(true, 7200)];
+    ReleaseStatus::add_metrics(&mut metrics, "nixos/trunk-combined", "", &releases);
+    let [sample] = metrics.samples.as_slice() else {
+        panic!("expected a single sample: {metrics:?}");
+    };
+    assert!((7200..7260).contains(&sample.value));
+}
@@ -0,0 +1,344 @@
+//! Custom output templates, see `--template`, which print each row of
+//! a report as a single line with exactly the fields needed, e.g. for
+//! shell prompts, status bars and scripts:
+//!
+//! - `{name}` is replaced by the field, or nothing if it is missing;
+//! - `{name:<20}`, `{name:>8}` and `{name:^10}` pad the field to a width,
+//!   and `{name:.7}` truncates it, e.g. a revision;
+//! - `{name?then|else}` prints `then` if the field is set (i.e. neither
+//!   empty, `false` nor `0`), and `else` otherwise, which may contain
+//!   fields themselves, e.g. `{failed?{failed} failed|ok}`;
+//! - `{{` and `}}` are literal braces.
+//!
+//! The fields are the columns of `--format csv` and the fields of
+//! `--json` (nested with dots, e.g. `eval.id`), as well as the `icon`,
+//! the `key` of the report (also named after its kind, e.g. `package`)
+//! and the `group` within it (e.g. `now_fail`).
+
+use anyhow::{anyhow, bail};
+use indexmap::IndexMap;
+use log::warn;
+use serde::Serialize;
+use serde_json::Value;
+
+use super::{FlatRow, ReportKind};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+enum Align {
+    #[default]
+    Left,
+    Center,
+    Right,
+}
+
+/// Padding and truncation of a field, e.g. `<20` or `.7`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+struct Spec {
+    align: Align,
+    width: usize,
+    precision: Option<usize>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Segment {
+    Text(String),
+    Field(String, Spec),
+    Conditional(String, Vec<Segment>, Vec<Segment>),
+}
+
+/// A parsed `--template`, e.g. `{icon} {name:<30} {arch}`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Template(Vec<Segment>);
+
+impl Spec {
+    fn parse(spec: &str) -> anyhow::Result<Self> {
+        let (align, rest) = match spec.chars().next() {
+            Some('<') => (Align::Left, &spec[1..]),
+            Some('^') => (Align::Center, &spec[1..]),
+            Some('>') => (Align::Right, &spec[1..]),
+            _ => (Align::default(), spec),
+        };
+        let (width, precision) = match rest.split_once('.') {
+            Some((width, precision)) => (width, Some(precision.parse()?)),
+            None => (rest, None),
+        };
+        let width = match width {
+            "" => 0,
+            width => width.parse()?,
+        };
+        Ok(Self {
+            align,
+            width,
+            precision,
+        })
+    }
+
+    fn apply(self, value: &str) -> String {
+        let value: String = match self.precision {
+            Some(precision) => value.chars().take(precision).collect(),
+            None => value.into(),
+        };
+        let padding = self.width.saturating_sub(value.chars().count());
+        let (left, right) = match self.align {
+            Align::Left => (0, padding),
+            Align::Center => (padding / 2, padding - padding / 2),
+            Align::Right => (padding, 0),
+        };
+        format!("{}{value}{}", " ".repeat(left), " ".repeat(right))
+    }
+}
+
+/// Parses the segments up to (and including) one of the `stops`,
+/// which is returned if found, or else up to the end.
+fn parse_segments(
+    chars: &mut std::iter::Peekable<std::str::Chars<'_>>,
+    stops: &[char],
+) -> anyhow::Result<(Vec<Segment>, Option<char>)> {
+    let mut segments = Vec::new();
+    let mut text = String::new();
+    while let Some(char) = chars.next() {
+        match char {
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+                text.push('{');
+            }
+            '}' if chars.peek() == Some(&'}') && !stops.contains(&'}') => {
+                chars.next();
+                text.push('}');
+            }
+            char if stops.contains(&char) => {
+                if !text.is_empty() {
+                    segments.push(Segment::Text(text));
+                }
+                return Ok((segments, Some(char)));
+            }
+            '{' => {
+                if !text.is_empty() {
+                    segments.push(Segment::Text(std::mem::take(&mut text)));
+                }
+                segments.push(parse_field(chars)?);
+            }
+            '}' => bail!("unmatched '}}' in --template, use '}}}}' for a literal brace"),
+            char => text.push(char),
+        }
+    }
+    if !text.is_empty() {
+        segments.push(Segment::Text(text));
+    }
+    Ok((segments, None))
+}
+
+/// Parses a field after its opening brace, e.g. `name:<20}`.
+fn parse_field(chars: &mut std::iter::Peekable<std::str::Chars<'_>>) -> anyhow::Result<Segment> {
+    let mut name = String::new();
+    while let Some(&char) = chars.peek() {
+        if !(char.is_ascii_alphanumeric() || char == '_' || char == '.') {
+            break;
+        }
+        name.push(char);
+        chars.next();
+    }
+    if name.is_empty() {
+        bail!("missing field name after '{{' in --template");
+    }
+    match chars.next() {
+        Some('}') => Ok(Segment::Field(name, Spec::default())),
+        Some(':') => {
+            let spec: String = chars.by_ref().take_while(|&x| x != '}').collect();
+            let spec = Spec::parse(&spec).map_err(|err| {
+                anyhow!("invalid format '{spec}' of field '{name}' in --template: {err}")
+            })?;
+            Ok(Segment::Field(name, spec))
+        }
+        Some('?') => {
+            let (then, stop) = parse_segments(chars, &['|', '}'])?;
+            let otherwise = match stop {
+                Some('|') => match parse_segments(chars, &['}'])? {
+                    (otherwise, Some('}')) => otherwise,
+                    _ => bail!("unclosed condition on field '{name}' in --template"),
+                },
+                Some(_) => vec![],
+                None => bail!("unclosed condition on field '{name}' in --template"),
+            };
+            Ok(Segment::Conditional(name, then, otherwise))
+        }
+        _ => bail!("unclosed field '{name}' in --template"),
+    }
+}
+
+impl Template {
+    /// Parses the `--template` argument.
+    pub(crate) fn parse(template: &str) -> anyhow::Result<Self> {
+        let (segments, _) = parse_segments(&mut template.chars().peekable(), &[])?;
+        Ok(Self(segments))
+    }
+
+    fn render(segments: &[Segment], fields: &IndexMap<String, String>) -> String {
+        let value = |name: &str| fields.get(name).map(String::as_str).unwrap_or_default();
+        segments
+            .iter()
+            .map(|segment| match segment {
+                Segment::Text(text) => text.clone(),
+                Segment::Field(name, spec) => spec.apply(value(name)),
+                Segment::Conditional(name, then, otherwise) => match value(name) {
+                    "" | "false" | "0" => Self::render(otherwise, fields),
+                    _ => Self::render(then, fields),
+                },
+            })
+            .collect()
+    }
+
+    /// Names of the fields used by the template, including the conditions.
+    fn field_names(segments: &[Segment]) -> Vec<&str> {
+        segments
+            .iter()
+            .flat_map(|segment| match segment {
+                Segment::Text(_) => vec![],
+                Segment::Field(name, _) => vec![name.as_str()],
+                Segment::Conditional(name, then, otherwise) => [name.as_str()]
+                    .into_iter()
+                    .chain(Self::field_names(then))
+                    .chain(Self::field_names(otherwise))
+                    .collect(),
+            })
+            .collect()
+    }
+}
+
+/// Flattens the (JSON) fields of a row, with the fields of nested objects
+/// prefixed by their parent, e.g. `eval.id`, and arrays joined by spaces.
+fn flatten(prefix: &str, value: &Value, fields: &mut IndexMap<String, String>) {
+    match value {
+        Value::Object(object) => {
+            for (name, value) in object {
+                let name = match prefix {
+                    "" => name.clone(),
+                    prefix => format!("{prefix}.{name}"),
+                };
+                flatten(&name, value, fields);
+            }
+        }
+        _ if prefix.is_empty() => {}
+        Value::Null => {
+            fields.insert(prefix.into(), String::new());
+        }
+        Value::String(x) => {
+            fields.insert(prefix.into(), x.clone());
+        }
+        Value::Array(xs) => {
+            let xs: Vec<_> = xs.iter().filter(|x| !x.is_object()).map(cell).collect();
+            fields.insert(prefix.into(), xs.join(" "));
+        }
+        x => {
+            fields.insert(prefix.into(), x.to_string());
+        }
+    }
+}
+
+fn cell(value: &Value) -> String {
+    match value {
+        Value::String(x) => x.clone(),
+        x => x.to_string(),
+    }
+}
+
+fn fields<T: FlatRow + Serialize>(
+    kind: ReportKind,
+    key: Option<&str>,
+    group: Option<&str>,
+    row: &T,
+) -> IndexMap<String, String> {
+    let mut fields = IndexMap::new();
+    if let Ok(value) = serde_json::to_value(row) {
+        flatten("", &value, &mut fields);
+    }
+    for (column, value) in T::COLUMNS.iter().zip(row.values()) {
+        fields.insert((*column).into(), value);
+    }
+    if let Some(icon) = row.icon() {
+        fields.insert("icon".into(), icon.to_string());
+    }
+    if let Some(key) = key {
+        fields.insert("key".into(), key.into());
+        if let Some(column) = kind.key_column() {
+            fields.entry(column.into()).or_insert_with(|| key.into());
+        }
+    }
+    fields.insert("group".into(), group.unwrap_or_default().into());
+    fields
+}
+
+/// Printer of the rows of a report with a template
+#[derive(Debug)]
+pub(crate) struct Printer {
+    template: Template,
+    /// Whether the fields of the template are checked, which is only
+    /// reported once, for the first row
+    checked: bool,
+}
+
+impl Printer {
+    pub(super) fn new(template: Template) -> Self {
+        Self {
+            template,
+            checked: false,
+        }
+    }
+
+    /// Formats each row with the template, on a line of its own.
+    pub(super) fn format_rows<T: FlatRow + Serialize>(
+        &mut self,
+        kind: ReportKind,
+        key: Option<&str>,
+        group: Option<&str>,
+        rows: &[T],
+    ) -> String {
+        let mut lines = String::new();
+        for row in rows {
+            let fields = fields(kind, key, group, row);
+            if !std::mem::replace(&mut self.checked, true) {
+                for name in Template::field_names(&self.template.0) {
+                    if !fields.contains_key(name) {
+                        let known: Vec<_> = fields.keys().map(String::as_str).collect();
+                        warn!(
+                            "unknown field '{name}' in --template, try: {}",
+                            known.join(", ")
+                        );
+                    }
+                }
+            }
+            lines += &(Template::render(&self.template.0, &fields) + "\n");
+        }
+        lines
+    }
+
+    /// Starts the next report, of which the fields are checked again.
+    pub(super) fn reset(&mut self) {
+        self.checked = false;
+    }
+}
+
+#[test]
+fn render_template() {
+    use crate::{BuildStatus, StatusIcon};
+    let build = BuildStatus {
+        icon: StatusIcon::Failed,
+        status: "Dependency failed".into(),
+        build_id: Some("115099119".into()),
+        build_url: Some("https://hydra.nixos.org/build/115099119".into()),
+        name: Some("hello-2.12.1".into()),
+        arch: Some("x86_64-linux".into()),
+        ..Default::default()
+    };
+    let template = Template::parse(
+        "{{{icon}}} {package:<8}|{name:^14}|{arch:.6} {success?ok|{status} #{build_id}}",
+    )
+    .unwrap();
+    let fields = fields(ReportKind::Packages, Some("hello"), None, &build);
+    assert_eq!(
+        Template::render(&template.0, &fields),
+        "{✖} hello   | hello-2.12.1 |x86_64 Dependency failed #115099119"
+    );
+    assert!(Template::parse("{name").is_err());
+    assert!(Template::parse("{name?then").is_err());
+}
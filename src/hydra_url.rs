@@ -0,0 +1,127 @@
+//! Parsing of Hydra urls that are passed as queries, such as
+//! <https://hydra.nixos.org/build/290062156> or
+//! <https://hydra.nixos.org/job/nixpkgs/unstable/hello.x86_64-linux>.
+
+use anyhow::{anyhow, bail};
+use reqwest::Url;
+
+use crate::constants::HYDRA_CHECK_HOST_URL;
+
+/// A Hydra url pointing to a page that we know how to report on.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum HydraUrl {
+    /// `/build/<id>`
+    Build(u64),
+    /// `/eval/<id>?filter=<filter>&full=1`
+    Eval {
+        id: u64,
+        filter: Option<String>,
+        full: bool,
+    },
+    /// `/job/<project>/<jobset>/<job>`
+    Job { jobset: String, job: String },
+    /// `/jobset/<project>/<jobset>`
+    Jobset(String),
+}
+
+impl HydraUrl {
+    /// Parses a query as a url of the configured Hydra instance.
+    /// Returns `None` if the query does not look like a url at all.
+    pub(crate) fn parse(query: &str) -> Option<anyhow::Result<Self>> {
+        Self::parse_with_host(query, &HYDRA_CHECK_HOST_URL)
+    }
+
+    fn parse_with_host(query: &str, host: &str) -> Option<anyhow::Result<Self>> {
+        let query = query.trim();
+        if !(query.starts_with("https://") || query.starts_with("http://")) {
+            return None;
+        }
+        Some(Self::parse_url(query, host))
+    }
+
+    fn parse_url(query: &str, host: &str) -> anyhow::Result<Self> {
+        let url = Url::parse(query)?;
+        let host_url = Url::parse(host)?;
+        let host_path = host_url.path().trim_end_matches('/');
+        let path = match url.path().strip_prefix(host_path) {
+            Some(path) if url.origin() == host_url.origin() => path,
+            _ => bail!(
+                "'{query}' does not belong to the Hydra instance at {host}, {}",
+                "consider setting $HYDRA_CHECK_HOST_URL"
+            ),
+        };
+        let segments: Vec<_> = path.split('/').filter(|x| !x.is_empty()).collect();
+        let err = || anyhow!("could not parse '{query}' as a build, eval, job or jobset url");
+        let query_pair = |key: &str| {
+            url.query_pairs()
+                .find(|(k, _)| k == key)
+                .map(|(_, value)| value.to_string())
+        };
+        let parsed = match segments.as_slice() {
+            ["build", id, ..] => Self::Build(id.parse().map_err(|_| err())?),
+            ["eval", id, ..] => Self::Eval {
+                id: id.parse().map_err(|_| err())?,
+                filter: query_pair("filter").filter(|x| !x.is_empty()),
+                full: query_pair("full").is_some_and(|x| x == "1"),
+            },
+            ["job", project, jobset, job, ..] => Self::Job {
+                jobset: format!("{project}/{jobset}"),
+                job: (*job).to_string(),
+            },
+            ["jobset", project, jobset, ..] => Self::Jobset(format!("{project}/{jobset}")),
+            _ => bail!(err()),
+        };
+        Ok(parsed)
+    }
+}
+
+#[test]
+fn parse_hydra_urls() {
+    let host = "https://hydra.nixos.org";
+    for (url, parsed) in [
+        ("https://hydra.nixos.org/build/123", HydraUrl::Build(123)),
+        (
+            "https://hydra.nixos.org/eval/456?filter=foo&full=1",
+            HydraUrl::Eval {
+                id: 456,
+                filter: Some("foo".into()),
+                full: true,
+            },
+        ),
+        (
+            "https://hydra.nixos.org/eval/456#tabs-still-fail",
+            HydraUrl::Eval {
+                id: 456,
+                filter: None,
+                full: false,
+            },
+        ),
+        (
+            "https://hydra.nixos.org/job/nixos/release-24.05/nixpkgs.hello.x86_64-linux/latest",
+            HydraUrl::Job {
+                jobset: "nixos/release-24.05".into(),
+                job: "nixpkgs.hello.x86_64-linux".into(),
+            },
+        ),
+        (
+            "https://hydra.nixos.org/jobset/nixpkgs/staging-next/evals",
+            HydraUrl::Jobset("nixpkgs/staging-next".into()),
+        ),
+    ] {
+        let result = HydraUrl::parse_with_host(url, host).unwrap().unwrap();
+        assert_eq!(result, parsed);
+    }
+    assert!(HydraUrl::parse_with_host("hello", host).is_none());
+    for url in [
+        "https://hydra.example.com/build/123",
+        "https://hydra.nixos.org/project/nixos",
+        "https://hydra.nixos.org/build/latest",
+    ] {
+        assert!(HydraUrl::parse_with_host(url, host).unwrap().is_err());
+    }
+    let result = HydraUrl::parse_with_host(
+        "https://example.com/hydra/build/1",
+        "https://example.com/hydra/",
+    );
+    assert_eq!(result.unwrap().unwrap(), HydraUrl::Build(1));
+}
@@ -0,0 +1,171 @@
+//! Client-side filtering of the builds in evaluation reports, by job name
+//! (`--match REGEX` or `--glob PATTERN`) and by system (`--system`).
+//!
+//! Hydra itself only supports a substring filter (`?filter=`), so the
+//! fetched builds are narrowed down by a literal part of the pattern
+//! where possible, see [`JobFilter::hint`].
+
+use anyhow::bail;
+use regex::Regex;
+
+use crate::{constants, BuildStatus};
+
+/// Criteria for the builds to keep in an evaluation report
+#[derive(Debug, Clone, Default)]
+pub(crate) struct JobFilter {
+    pub(crate) regex: Option<Regex>,
+    pub(crate) system: Option<String>,
+    /// Literal text that the names of all matching jobs contain,
+    /// which can be passed on to Hydra's `?filter=`
+    pub(crate) hint: Option<String>,
+}
+
+/// Escapes a glob pattern into an (anchored) regular expression.
+/// Supports `*` (any text), `?` (any character) and `[...]` (character class).
+fn glob_to_regex(glob: &str) -> anyhow::Result<Regex> {
+    let mut regex = String::from("^");
+    let mut chars = glob.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '*' => regex.push_str(".*"),
+            '?' => regex.push('.'),
+            '[' => {
+                let class: String = chars.by_ref().take_while(|&c| c != ']').collect();
+                let class = match class.strip_prefix('!') {
+                    Some(negated) => format!("^{negated}"),
+                    None => class,
+                };
+                regex.push('[');
+                regex.push_str(&class.replace('\\', "\\\\"));
+                regex.push(']');
+            }
+            c => regex.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    regex.push('$');
+    match Regex::new(&regex) {
+        Ok(regex) => Ok(regex),
+        Err(err) => bail!("invalid --glob '{glob}': {err}"),
+    }
+}
+
+/// Finds the literal prefix of a glob pattern.
+fn glob_literal_prefix(glob: &str) -> &str {
+    let end = glob.find(['*', '?', '[']).unwrap_or(glob.len());
+    &glob[..end]
+}
+
+/// Finds the literal prefix of a regular expression, if it is simple enough.
+fn regex_literal_prefix(regex: &str) -> String {
+    let mut prefix = String::new();
+    if regex.contains('|') {
+        // alternatives do not share a prefix
+        return prefix;
+    }
+    let mut chars = regex.strip_prefix('^').unwrap_or(regex).chars().peekable();
+    while let Some(c) = chars.next() {
+        let literal = match c {
+            '\\' => match chars.next() {
+                Some(c) if !c.is_alphanumeric() => c,
+                _ => break,
+            },
+            c if c.is_alphanumeric() || c == '-' || c == '_' => c,
+            _ => break,
+        };
+        // the last literal might be optional or repeated
+        if matches!(chars.peek(), Some('?' | '*' | '{')) {
+            break;
+        }
+        prefix.push(literal);
+    }
+    prefix
+}
+
+impl JobFilter {
+    pub(crate) fn new(
+        regex: Option<&str>,
+        glob: Option<&str>,
+        system: Option<String>,
+    ) -> anyhow::Result<Self> {
+        let (regex, prefix) = match (regex, glob) {
+            (Some(_), Some(_)) => bail!("--match and --glob cannot be used together"),
+            (Some(regex), None) => match Regex::new(regex) {
+                Ok(compiled) => (Some(compiled), regex_literal_prefix(regex)),
+                Err(err) => bail!("invalid --match '{regex}': {err}"),
+            },
+            (None, Some(glob)) => (Some(glob_to_regex(glob)?), glob_literal_prefix(glob).into()),
+            (None, None) => (None, String::new()),
+        };
+        let hint = match (prefix.as_str(), &system) {
+            ("", Some(system)) => Some(format!(".{system}")),
+            ("", None) => None,
+            (prefix, _) => Some(prefix.into()),
+        };
+        Ok(Self {
+            regex,
+            system,
+            hint,
+        })
+    }
+
+    pub(crate) fn is_active(&self) -> bool {
+        self.regex.is_some() || self.system.is_some()
+    }
+
+    fn matches(&self, build: &BuildStatus) -> bool {
+        let system = build.arch.as_deref().unwrap_or_default();
+        if self.system.as_ref().is_some_and(|x| x != system) {
+            return false;
+        }
+        let Some(regex) = &self.regex else {
+            return true;
+        };
+        let job = build
+            .job_name
+            .as_deref()
+            .or(build.name.as_deref())
+            .unwrap_or_default();
+        // also match the attribute path without the system suffix
+        let attr = constants::KNOWN_ARCHITECTURES
+            .iter()
+            .chain([&system])
+            .find_map(|arch| job.strip_suffix(&format!(".{arch}")))
+            .unwrap_or(job);
+        regex.is_match(job) || regex.is_match(attr)
+    }
+
+    /// Keeps the builds that match the filter.
+    pub(crate) fn apply(&self, builds: Vec<BuildStatus>) -> Vec<BuildStatus> {
+        if !self.is_active() {
+            return builds;
+        }
+        builds.into_iter().filter(|x| self.matches(x)).collect()
+    }
+}
+
+#[test]
+fn filter_jobs() {
+    let build = |job: &str, arch: &str| BuildStatus {
+        job_name: Some(job.into()),
+        arch: Some(arch.into()),
+        ..Default::default()
+    };
+    let builds = vec![
+        build("python312Packages.requests.aarch64-linux", "aarch64-linux"),
+        build("python312Packages.requests.x86_64-linux", "x86_64-linux"),
+        build("python313Packages.requests.aarch64-linux", "aarch64-linux"),
+        build("hello.aarch64-linux", "aarch64-linux"),
+    ];
+    let system = Some("aarch64-linux".to_string());
+    let filter = JobFilter::new(None, Some("python312Packages.*"), system.clone()).unwrap();
+    assert_eq!(filter.hint.as_deref(), Some("python312Packages."));
+    assert_eq!(filter.apply(builds.clone()).len(), 1);
+    let filter = JobFilter::new(Some(r"^python31[23]Packages\.req"), None, None).unwrap();
+    assert_eq!(filter.hint.as_deref(), Some("python31"));
+    assert_eq!(filter.apply(builds.clone()).len(), 3);
+    let filter = JobFilter::new(None, Some("hello"), system).unwrap();
+    assert_eq!(filter.apply(builds).len(), 1);
+    let filter = JobFilter::new(Some("hello|world"), None, None).unwrap();
+    assert!(filter.hint.is_none());
+    assert!(JobFilter::new(Some("("), None, None).is_err());
+}
@@ -12,8 +12,15 @@
 #![doc = include_str!("../README.md")]
 
 mod args;
+mod binary_cache;
 mod fetch_stable;
+mod formats;
+mod hydra_url;
+mod job_filter;
+mod pagination;
+mod pins;
 mod queries;
+mod reproduce;
 mod structs;
 
 pub mod constants;
@@ -23,12 +30,14 @@ pub use args::HydraCheckCli;
 pub use fetch_stable::NixpkgsChannelVersion;
 
 use args::ResolvedArgs;
+use pagination::{next_page_url, Pagination};
 use soup::{SoupFind, TryAttr};
 use structs::{BuildStatus, EvalInput, EvalStatus, Evaluation, StatusIcon};
 
 use colored::{ColoredString, Colorize};
 use comfy_table::Table;
 use scraper::{ElementRef, Html};
+use serde::de::DeserializeOwned;
 use std::time::Duration;
 
 /// Trait for a single `Status` entry from a Hydra report.
@@ -44,15 +53,7 @@ trait ShowHydraStatus {
 trait FetchHydraReport: Clone {
     fn get_url(&self) -> &str;
     fn fetch_document(&self) -> anyhow::Result<Html> {
-        let document = reqwest::blocking::Client::builder()
-            .timeout(Duration::from_secs(30))
-            .user_agent(constants::APP_USER_AGENT)
-            .build()?
-            .get(self.get_url())
-            .send()?
-            .error_for_status()?
-            .text()?;
-        Ok(Html::parse_document(&document))
+        fetch_html(self.get_url())
     }
 
     fn finish_with_error(self, status: String) -> Self;
@@ -101,6 +102,87 @@ trait FetchHydraReport: Clone {
         }
         table.trim_fmt()
     }
+
+    /// Fetches the table body found by [`Self::find_tbody`] page by page,
+    /// following Hydra's `?page=` links until the [`Pagination`] limits
+    /// are satisfied. Without any limits, only the first page is read.
+    /// Returns the error report if the first page does not contain a table,
+    /// and fails if any later page does not.
+    fn fetch_pages<T>(
+        &self,
+        selector: &str,
+        pagination: &Pagination,
+        read_tbody: impl Fn(ElementRef<'_>) -> anyhow::Result<Vec<T>>,
+        timestamp: impl Fn(&T) -> Option<i64>,
+    ) -> anyhow::Result<Result<Vec<T>, Self>> {
+        self.fetch_pages_until(selector, pagination, read_tbody, timestamp, |_| false)
+    }
+
+    /// Same as [`Self::fetch_pages`], but also stops fetching further pages
+    /// as soon as an entry is `found`.
+    fn fetch_pages_until<T>(
+        &self,
+        selector: &str,
+        pagination: &Pagination,
+        read_tbody: impl Fn(ElementRef<'_>) -> anyhow::Result<Vec<T>>,
+        timestamp: impl Fn(&T) -> Option<i64>,
+        found: impl Fn(&T) -> bool,
+    ) -> anyhow::Result<Result<Vec<T>, Self>> {
+        let mut entries = Vec::new();
+        let mut url = self.get_url().to_string();
+        loop {
+            let doc = fetch_html(&url)?;
+            let tbody = match self.find_tbody(&doc, selector) {
+                Err(stat) if url == self.get_url() => return Ok(Err(stat)),
+                // don't pass off the entries of the previous pages as complete
+                Err(_) => anyhow::bail!("could not find the table of the next page at {url}"),
+                Ok(tbody) => tbody,
+            };
+            let page = read_tbody(tbody)?;
+            let first = entries.len();
+            let done = pagination.collect(&mut entries, page, &timestamp);
+            // only the entries of this page are new, the others were already checked
+            if done || entries[first..].iter().any(&found) {
+                break;
+            }
+            match next_page_url(&doc, &url) {
+                Some(next_url) => url = next_url,
+                None => break,
+            }
+        }
+        Ok(Ok(entries))
+    }
+}
+
+fn http_client() -> reqwest::Result<reqwest::blocking::Client> {
+    reqwest::blocking::Client::builder()
+        .timeout(Duration::from_secs(30))
+        .user_agent(constants::APP_USER_AGENT)
+        .build()
+}
+
+/// Fetches and parses a html document from the given url.
+fn fetch_html(url: &str) -> anyhow::Result<Html> {
+    let document = http_client()?.get(url).send()?.error_for_status()?.text()?;
+    Ok(Html::parse_document(&document))
+}
+
+/// Fetches and deserializes a response from Hydra's JSON API,
+/// which is served from the same urls as the html pages.
+fn fetch_json<T: DeserializeOwned>(url: &str) -> anyhow::Result<T> {
+    let response = http_client()?
+        .get(url)
+        .header(reqwest::header::ACCEPT, "application/json")
+        .send()?
+        .error_for_status()?
+        .text()?;
+    Ok(serde_json::from_str(&response)?)
+}
+
+/// Checks whether the url exists, e.g. whether a channel release is published.
+fn url_exists(url: &str) -> anyhow::Result<bool> {
+    let response = http_client()?.head(url).send()?;
+    Ok(response.status().is_success())
 }
 
 fn is_skipable_row(row: ElementRef<'_>) -> anyhow::Result<bool> {
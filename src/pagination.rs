@@ -0,0 +1,175 @@
+//! Pagination of Hydra listings, following the `?page=` links of pages
+//! such as <https://hydra.nixos.org/jobset/nixpkgs/unstable/evals?page=2>.
+
+use anyhow::anyhow;
+use chrono::{DateTime, NaiveDate, NaiveTime};
+use log::debug;
+use scraper::Html;
+
+use crate::SoupFind;
+
+/// Limits on the entries collected from a (paginated) Hydra listing,
+/// as specified by `--limit`, `--since` and `--until`.
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct Pagination {
+    /// Maximum number of entries to collect
+    pub(crate) limit: Option<usize>,
+    /// Unix timestamp of the oldest entry to collect
+    pub(crate) since: Option<i64>,
+    /// Unix timestamp of the newest entry to collect
+    pub(crate) until: Option<i64>,
+}
+
+impl Pagination {
+    /// Whether any limit is specified. If not, only the first page of
+    /// a listing is read, as it has always been.
+    pub(crate) fn is_active(&self) -> bool {
+        self.limit.is_some() || self.since.is_some() || self.until.is_some()
+    }
+
+    /// Whether `count` entries already satisfy the `--limit`.
+    pub(crate) fn is_full(&self, count: usize) -> bool {
+        self.limit.is_some_and(|limit| count >= limit)
+    }
+
+    /// Whether an entry with the given timestamp lies within `--since`
+    /// and `--until`. Entries without a timestamp are always accepted.
+    pub(crate) fn accepts(&self, timestamp: Option<i64>) -> bool {
+        match timestamp {
+            Some(x) => {
+                self.until.map_or(true, |until| x <= until)
+                    && self.since.map_or(true, |since| x >= since)
+            }
+            None => true,
+        }
+    }
+
+    /// Appends the entries of a single page to `entries`, assuming that
+    /// the listing is sorted from the newest to the oldest entry.
+    /// Entries without a timestamp (e.g. queued builds) are always kept.
+    /// Returns `true` if no more pages need to be fetched.
+    pub(crate) fn collect<T>(
+        &self,
+        entries: &mut Vec<T>,
+        page: Vec<T>,
+        timestamp: impl Fn(&T) -> Option<i64>,
+    ) -> bool {
+        if page.is_empty() || !self.is_active() {
+            entries.extend(page);
+            return true;
+        }
+        for entry in page {
+            if self.is_full(entries.len()) {
+                return true;
+            }
+            match timestamp(&entry) {
+                // too new, skip to the older entries
+                Some(x) if self.until.is_some_and(|until| x > until) => {}
+                Some(x) if self.since.is_some_and(|since| x < since) => return true,
+                _ => entries.push(entry),
+            }
+        }
+        self.is_full(entries.len())
+    }
+
+    /// Filters an unsorted list of entries, e.g. the builds of an evaluation.
+    pub(crate) fn apply<T>(
+        &self,
+        entries: Vec<T>,
+        timestamp: impl Fn(&T) -> Option<i64>,
+    ) -> Vec<T> {
+        let entries = entries
+            .into_iter()
+            .filter(|entry| self.accepts(timestamp(entry)));
+        match self.limit {
+            Some(limit) => entries.take(limit).collect(),
+            None => entries.collect(),
+        }
+    }
+}
+
+/// Finds the link to the next page of a paginated Hydra listing, if any.
+pub(crate) fn next_page_url(doc: &Html, current_url: &str) -> Option<String> {
+    let next = doc
+        .find_all("ul.pagination li, ul.pager li")
+        .into_iter()
+        .find(|li| li.text().collect::<String>().contains("Next"))?;
+    if next.value().classes().any(|class| class == "disabled") {
+        return None;
+    }
+    let href = next.find("a").ok()?.attr("href")?;
+    let url = reqwest::Url::parse(current_url).ok()?.join(href).ok()?;
+    let url = url.to_string();
+    debug!("found the next page at: {url}");
+    (url != current_url).then_some(url)
+}
+
+fn parse_date(date: &str, time: NaiveTime) -> anyhow::Result<i64> {
+    let date = date.trim();
+    if let Ok(datetime) = DateTime::parse_from_rfc3339(date) {
+        return Ok(datetime.timestamp());
+    }
+    let date = NaiveDate::parse_from_str(date, "%Y-%m-%d").map_err(|_| {
+        anyhow!("could not parse '{date}', expecting a date such as '2025-06-01' or '2025-06-01T12:00:00Z'")
+    })?;
+    Ok(date.and_time(time).and_utc().timestamp())
+}
+
+/// Parses the `--since` argument; a plain date starts at 00:00:00 UTC.
+pub(crate) fn parse_since(date: &str) -> anyhow::Result<i64> {
+    parse_date(date, NaiveTime::MIN)
+}
+
+/// Parses the `--until` argument; a plain date ends at 23:59:59 UTC.
+pub(crate) fn parse_until(date: &str) -> anyhow::Result<i64> {
+    let end_of_day = NaiveTime::from_hms_opt(23, 59, 59).expect("the time should be valid");
+    parse_date(date, end_of_day)
+}
+
+#[test]
+fn paginate_sorted_entries() {
+    let pagination = Pagination {
+        limit: Some(4),
+        since: Some(10),
+        until: Some(50),
+    };
+    let mut entries = Vec::new();
+    let page = vec![Some(70), Some(50), None, Some(40)];
+    let done = pagination.collect(&mut entries, page, |x| *x);
+    assert!(!done);
+    assert_eq!(entries, [Some(50), None, Some(40)]);
+    let done = pagination.collect(&mut entries, vec![Some(30), Some(20)], |x| *x);
+    assert!(done);
+    assert_eq!(entries, [Some(50), None, Some(40), Some(30)]);
+
+    let pagination = Pagination {
+        since: Some(25),
+        ..Default::default()
+    };
+    let mut entries = Vec::new();
+    let done = pagination.collect(&mut entries, vec![Some(30), Some(20), Some(10)], |x| *x);
+    assert!(done);
+    assert_eq!(entries, [Some(30)]);
+}
+
+#[test]
+fn filter_unsorted_entries() {
+    let pagination = Pagination {
+        limit: Some(2),
+        since: Some(10),
+        until: Some(50),
+    };
+    assert!(pagination.accepts(None) && pagination.accepts(Some(10)));
+    assert!(!pagination.accepts(Some(5)) && !pagination.accepts(Some(60)));
+    assert!(!pagination.is_full(1) && pagination.is_full(2));
+    let entries = vec![Some(5), Some(60), Some(20), None, Some(30)];
+    assert_eq!(pagination.apply(entries, |x| *x), [Some(20), None]);
+}
+
+#[test]
+fn parse_date_bounds() {
+    assert_eq!(parse_since("2025-06-01").unwrap(), 1_748_736_000);
+    assert_eq!(parse_until("2025-06-01").unwrap(), 1_748_822_399);
+    assert_eq!(parse_until("2025-06-01T00:00:00Z").unwrap(), 1_748_736_000);
+    assert!(parse_since("June 1st").is_err());
+}
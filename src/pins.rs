@@ -0,0 +1,122 @@
+//! Snippets that pin nixpkgs to a known-good revision, for copying into
+//! a `flake.nix` or the sources of `npins` or `niv`, see `--pin-format`.
+
+use clap::ValueEnum;
+use colored::Colorize;
+
+use crate::{structs::PinnedInput, ResolvedArgs};
+
+/// Kind of snippet printed by `--pin-format`
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum PinFormat {
+    /// `inputs.nixpkgs.url` of a `flake.nix`
+    Flake,
+    /// `fetchTarball` (or `fetchGit`) in a Nix expression
+    #[value(name = "fetchTarball")]
+    FetchTarball,
+    /// `npins add` command
+    Npins,
+    /// `niv add` command
+    Niv,
+}
+
+/// Generates the snippet that pins the input in the given format.
+/// The `branch` is only used by `npins` and `niv` to track updates.
+fn pin_snippet(pinned: &PinnedInput, format: PinFormat, branch: &str) -> String {
+    let rev = &pinned.revision;
+    let url = &pinned.url;
+    let release = pinned.release_url.as_ref().and(pinned.tarball_url());
+    match (format, release, pinned.github()) {
+        (PinFormat::Flake, _, _) => {
+            format!("inputs.nixpkgs.url = \"{}\";", pinned.flake_ref())
+        }
+        (PinFormat::FetchTarball, _, _) => match pinned.tarball_url() {
+            Some(tarball) => format!("import (fetchTarball \"{tarball}\") {{ }}"),
+            None => {
+                format!("import (builtins.fetchGit {{ url = \"{url}\"; rev = \"{rev}\"; }}) {{ }}")
+            }
+        },
+        (PinFormat::Npins, Some(tarball), _) => {
+            format!("npins add tarball --name nixpkgs {tarball}")
+        }
+        (PinFormat::Npins, None, Some((owner, repo))) => {
+            format!("npins add github --name nixpkgs {owner} {repo} --branch {branch} --at {rev}")
+        }
+        (PinFormat::Npins, None, None) => {
+            format!("npins add git --name nixpkgs {url} --branch {branch} --at {rev}")
+        }
+        (PinFormat::Niv, Some(tarball), _) => {
+            format!("niv add nixpkgs --type tarball --template {tarball}")
+        }
+        (PinFormat::Niv, None, Some((owner, repo))) => {
+            format!("niv add {owner}/{repo} --name nixpkgs --branch {branch} --rev {rev}")
+        }
+        (PinFormat::Niv, None, None) => {
+            format!("niv add git --name nixpkgs --repo {url} --branch {branch} --rev {rev}")
+        }
+    }
+}
+
+impl ResolvedArgs {
+    /// Generates the snippet of `--pin-format` for the pinned input,
+    /// or returns `None` if the flag is not set.
+    pub(crate) fn pin(&self, pinned: Option<&PinnedInput>) -> Option<String> {
+        let format = self.pin_format?;
+        // the branch of a channel, e.g. `nixos-unstable`, or else `master`
+        let branch = match self.channel.as_deref() {
+            Some(channel) if channel.starts_with("nixos-") || channel.starts_with("nixpkgs-") => {
+                channel
+            }
+            _ => "master",
+        };
+        Some(pin_snippet(pinned?, format, branch))
+    }
+
+    /// Prints the result of [`Self::pin`] below a report.
+    pub(crate) fn print_pin(&self, snippet: Option<&String>) {
+        let Some(snippet) = snippet else {
+            return;
+        };
+        if !self.format.is_table() {
+            return;
+        }
+        println!("{}", "Pin nixpkgs with:".bold());
+        println!("{snippet}");
+    }
+}
+
+#[test]
+fn pin_snippets() {
+    let pinned = PinnedInput {
+        url: "https://github.com/NixOS/nixpkgs.git".into(),
+        revision: "1e9e641a3fc1b22fbdb823a99d8ff96692cc4fba".into(),
+        release_url: None,
+    };
+    let snippets = [
+        PinFormat::Flake,
+        PinFormat::FetchTarball,
+        PinFormat::Npins,
+        PinFormat::Niv,
+    ]
+    .map(|format| pin_snippet(&pinned, format, "nixos-unstable"));
+    assert_eq!(
+        snippets,
+        [
+            r#"inputs.nixpkgs.url = "github:NixOS/nixpkgs/1e9e641a3fc1b22fbdb823a99d8ff96692cc4fba";"#,
+            r#"import (fetchTarball "https://github.com/NixOS/nixpkgs/archive/1e9e641a3fc1b22fbdb823a99d8ff96692cc4fba.tar.gz") { }"#,
+            "npins add github --name nixpkgs NixOS nixpkgs --branch nixos-unstable --at 1e9e641a3fc1b22fbdb823a99d8ff96692cc4fba",
+            "niv add NixOS/nixpkgs --name nixpkgs --branch nixos-unstable --rev 1e9e641a3fc1b22fbdb823a99d8ff96692cc4fba",
+        ]
+    );
+    let release = PinnedInput {
+        revision: "5d8f4beac036".into(),
+        release_url: Some(
+            "https://releases.nixos.org/nixos/unstable/nixos-25.11pre854150.5d8f4beac036".into(),
+        ),
+        ..pinned
+    };
+    assert_eq!(
+        pin_snippet(&release, PinFormat::Flake, "nixos-unstable"),
+        r#"inputs.nixpkgs.url = "https://releases.nixos.org/nixos/unstable/nixos-25.11pre854150.5d8f4beac036/nixexprs.tar.xz";"#
+    );
+}
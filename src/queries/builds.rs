@@ -2,18 +2,36 @@
 //! from urls such as <https://hydra.nixos.org/build/290062156>.
 //!
 //! This module is adapted from the `evals` module as the two are similar
-//! in structure. The module is currently only used by the `builds` module,
-//! hence the relevant interfaces are marked as `pub(super)`.
+//! in structure. The [`BuildReport`] is also used by the `packages` module
+//! to show the inputs of a build, hence the relevant interfaces are marked
+//! as `pub(super)`.
 
+use colored::Colorize;
+use indexmap::IndexMap;
+use schemars::JsonSchema;
 use serde::Serialize;
+use serde_with::skip_serializing_none;
 
-use crate::{EvalInput, FetchHydraReport, StatusIcon};
+use crate::{
+    binary_cache::CacheStatus,
+    constants, fetch_json,
+    formats::ReportKind,
+    structs::{BuildDetails, PinnedInput},
+    EvalInput, FetchHydraReport, ResolvedArgs, StatusIcon,
+};
 
 #[non_exhaustive]
-#[derive(Serialize, Clone)]
+#[skip_serializing_none]
+#[derive(Serialize, JsonSchema, Clone)]
 pub(super) struct BuildReport {
     url: String,
+    pub(super) details: Option<BuildDetails>,
     pub(super) inputs: Vec<EvalInput>,
+    cache: Option<Vec<CacheStatus>>,
+    /// Commands to rebuild the job locally, see `--reproduce`
+    reproduce: Option<Vec<String>>,
+    /// Snippet that pins nixpkgs to the inputs, see `--pin-format`
+    pin: Option<String>,
 }
 
 impl FetchHydraReport for BuildReport {
@@ -37,10 +55,19 @@ impl BuildReport {
     pub(super) fn from_url(url: &str) -> Self {
         Self {
             url: url.to_string(),
+            details: None,
             inputs: vec![],
+            cache: None,
+            reproduce: None,
+            pin: None,
         }
     }
 
+    pub(super) fn from_id(id: u64) -> Self {
+        let url = format!("{}/build/{id}", &*constants::HYDRA_CHECK_HOST_URL);
+        Self::from_url(&url)
+    }
+
     pub(super) fn fetch_and_read(self) -> anyhow::Result<Self> {
         let doc = self.fetch_document()?;
         let tbody = match self.find_tbody(&doc, "div#tabs-buildinputs") {
@@ -51,4 +78,96 @@ impl BuildReport {
         let inputs = EvalInput::from_tbody(tbody, &self.url)?;
         Ok(Self { inputs, ..self })
     }
+
+    /// Fetches the build details from Hydra's JSON API.
+    pub(super) fn fetch_details(self) -> anyhow::Result<Self> {
+        let details = fetch_json(&self.url)?;
+        Ok(Self {
+            details: Some(details),
+            ..self
+        })
+    }
+}
+
+impl ResolvedArgs {
+    pub(crate) fn fetch_and_print_builds(&self, ids: &[u64]) -> anyhow::Result<bool> {
+        let mut status = true;
+        let mut indexmap = IndexMap::new();
+        for (idx, id) in ids.iter().enumerate() {
+            let stat = BuildReport::from_id(*id);
+            if self.url {
+                println!("{}", stat.get_url());
+                continue;
+            }
+            let stat = stat.fetch_details()?;
+            let details = stat.details.clone().unwrap_or_default();
+            let build_status = details.to_build_status();
+            if !build_status.success {
+                status = false;
+            }
+            let key = id.to_string();
+            self.print_rows(
+                ReportKind::Builds,
+                &key,
+                None,
+                std::slice::from_ref(&build_status),
+            )?;
+            if self.format.is_table() {
+                if idx > 0 && !self.short {
+                    println!(); // vertical whitespace
+                }
+                println!(
+                    "Build {} of job {} {}",
+                    id.to_string().bold(),
+                    details.full_job_name().bold(),
+                    format!("@ {}", stat.get_url()).dimmed(),
+                );
+                println!("{}", stat.format_table(false, &vec![build_status]));
+            }
+            let outputs = details.buildoutputs.iter();
+            let cache = self.check_cache(outputs.map(|(name, x)| (name.as_str(), x.path.as_str())));
+            if !self.print_cache_status(&stat, cache.as_ref()) {
+                status = false;
+            }
+            let stat = BuildReport { cache, ..stat };
+            // the inputs are needed to reproduce or pin the build, and for --format json
+            let stat = match self.short
+                && self.format.is_table()
+                && self.reproduce.is_none()
+                && self.pin_format.is_none()
+            {
+                true => stat,
+                false => stat.fetch_and_read()?,
+            };
+            let reproduce = self.reproduce(&details, &stat.inputs);
+            self.print_reproduce(reproduce.as_ref());
+            let pin = self.pin(PinnedInput::from_inputs(&stat.inputs).as_ref());
+            self.print_pin(pin.as_ref());
+            let stat = BuildReport {
+                reproduce,
+                pin,
+                ..stat
+            };
+            if !self.format.is_table() {
+                indexmap.insert(id, stat);
+                continue;
+            }
+            if self.short {
+                continue;
+            }
+            for (name, output) in &details.buildoutputs {
+                println!("{}: {}", format!("output {name}").bold(), output.path);
+            }
+            if !details.jobsetevals.is_empty() {
+                let evals: Vec<_> = details.jobsetevals.iter().map(u64::to_string).collect();
+                println!("{}: {}", "evals".bold(), evals.join(", "));
+            }
+            for entry in &stat.inputs {
+                println!(); // vertical separation
+                println!("{entry}");
+            }
+        }
+        self.print_report(ReportKind::Builds, &indexmap)?;
+        Ok(status)
+    }
 }
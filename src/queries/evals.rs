@@ -10,6 +10,7 @@ use colored::Colorize;
 use indexmap::IndexMap;
 use log::{info, warn};
 use regex::Regex;
+use schemars::JsonSchema;
 use scraper::Html;
 use serde::Serialize;
 use serde_json::Value;
@@ -19,13 +20,15 @@ use std::fmt::Display;
 #[cfg(test)]
 use insta::assert_snapshot;
 
+use super::summary::EvalSummary;
 use crate::{
-    constants::HYDRA_CHECK_HOST_URL, BuildStatus, EvalInput, Evaluation, FetchHydraReport,
+    constants::HYDRA_CHECK_HOST_URL, formats::ReportKind, job_filter::JobFilter,
+    structs::PinnedInput, BuildStatus, EvalInput, Evaluation, FetchHydraReport, Pagination,
     ResolvedArgs, SoupFind, StatusIcon,
 };
 
 #[skip_serializing_none]
-#[derive(Serialize, Clone)]
+#[derive(Serialize, JsonSchema, Clone)]
 struct EvalInputChanges {
     input: String,
     description: String,
@@ -176,8 +179,9 @@ impl EvalInputChanges {
     }
 }
 
-#[derive(Serialize, Clone)]
-struct EvalReport<'a> {
+#[skip_serializing_none]
+#[derive(Serialize, JsonSchema, Clone)]
+pub(super) struct EvalReport<'a> {
     #[serde(flatten)]
     eval: &'a Evaluation,
     url: String,
@@ -191,6 +195,10 @@ struct EvalReport<'a> {
     still_fail: Vec<BuildStatus>,
     still_succeed: Vec<BuildStatus>,
     unfinished: Vec<BuildStatus>,
+    /// Aggregated failures, see `--summary`
+    summary: Option<EvalSummary>,
+    /// Snippet that pins nixpkgs to the inputs, see `--pin-format`
+    pin: Option<String>,
 }
 
 impl FetchHydraReport for EvalReport<'_> {
@@ -234,6 +242,8 @@ impl<'a> From<&'a Evaluation> for EvalReport<'a> {
             still_fail: vec![],
             still_succeed: vec![],
             unfinished: vec![],
+            summary: None,
+            pin: None,
         }
     }
 }
@@ -302,6 +312,67 @@ impl EvalReport<'_> {
         })
     }
 
+    /// Applies `--match`, `--glob` and `--system` to each table of builds.
+    fn filter_jobs(self, job_filter: &JobFilter) -> Self {
+        if !job_filter.is_active() {
+            return self;
+        }
+        Self {
+            aborted: job_filter.apply(self.aborted),
+            now_fail: job_filter.apply(self.now_fail),
+            now_succeed: job_filter.apply(self.now_succeed),
+            new: job_filter.apply(self.new),
+            removed: job_filter.apply(self.removed),
+            still_fail: job_filter.apply(self.still_fail),
+            still_succeed: job_filter.apply(self.still_succeed),
+            unfinished: job_filter.apply(self.unfinished),
+            ..self
+        }
+    }
+
+    /// Applies `--limit`, `--since` and `--until` to each table of builds.
+    fn paginate(self, pagination: &Pagination) -> Self {
+        if !pagination.is_active() {
+            return self;
+        }
+        let paginate = |builds| pagination.apply(builds, BuildStatus::unix_timestamp);
+        Self {
+            aborted: paginate(self.aborted),
+            now_fail: paginate(self.now_fail),
+            now_succeed: paginate(self.now_succeed),
+            new: paginate(self.new),
+            removed: paginate(self.removed),
+            still_fail: paginate(self.still_fail),
+            still_succeed: paginate(self.still_succeed),
+            unfinished: paginate(self.unfinished),
+            ..self
+        }
+    }
+
+    /// Aggregates the failing builds of all tables, see `--summary`.
+    fn summarize(self) -> Self {
+        let builds = [&self.aborted, &self.now_fail, &self.new, &self.still_fail];
+        let summary = EvalSummary::from_builds(builds.into_iter().flatten());
+        Self {
+            summary: Some(summary),
+            ..self
+        }
+    }
+
+    /// Lists the tables of builds by their names in the `--json` output.
+    fn groups(&self) -> [(&'static str, &Vec<BuildStatus>); 8] {
+        [
+            ("aborted", &self.aborted),
+            ("now_fail", &self.now_fail),
+            ("now_succeed", &self.now_succeed),
+            ("new", &self.new),
+            ("removed", &self.removed),
+            ("still_fail", &self.still_fail),
+            ("still_succeed", &self.still_succeed),
+            ("unfinished", &self.unfinished),
+        ]
+    }
+
     fn is_success(&self) -> bool {
         self.aborted.is_empty()
             && self.now_fail.is_empty()
@@ -314,10 +385,13 @@ impl EvalReport<'_> {
 }
 
 impl ResolvedArgs {
-    pub(crate) fn fetch_and_print_evaluations(&self, evals: &[Evaluation]) -> anyhow::Result<bool> {
-        let mut status = true;
-        let mut indexmap = IndexMap::new();
-        let evals: Vec<_> = match evals.iter().any(|eval| eval.id == 0) {
+    /// Resolves the IDs of the latest evaluation (`0`) and of the
+    /// evaluations specified by an [`EvalSearch`][crate::structs::EvalSearch].
+    fn resolve_evaluations(&self, evals: &[Evaluation]) -> anyhow::Result<Vec<Evaluation>> {
+        let evals: Vec<_> = match evals
+            .iter()
+            .any(|eval| eval.id == 0 && eval.search.is_none())
+        {
             false => evals.to_owned(),
             true => {
                 info!(
@@ -335,20 +409,37 @@ impl ResolvedArgs {
                 println!();
                 evals
                     .iter()
-                    .map(|eval| match &eval.id {
-                        0 => Evaluation { id, ..eval.clone() },
+                    .map(|eval| match (&eval.id, &eval.search) {
+                        (0, None) => Evaluation { id, ..eval.clone() },
                         _ => eval.clone(),
                     })
                     .collect()
             }
         };
+        let mut resolved_evals = Vec::new();
+        for eval in evals {
+            resolved_evals.push(match (&eval.id, &eval.search) {
+                (0, Some(search)) => Evaluation {
+                    id: self.fetch_and_print_eval_search(search)?,
+                    ..eval
+                },
+                _ => eval,
+            });
+        }
+        Ok(resolved_evals)
+    }
+
+    pub(crate) fn fetch_and_print_evaluations(&self, evals: &[Evaluation]) -> anyhow::Result<bool> {
+        let mut status = true;
+        let mut indexmap = IndexMap::new();
+        let evals = self.resolve_evaluations(evals)?;
         for (idx, eval) in evals.iter().enumerate() {
             let stat = EvalReport::from(eval);
             if self.url {
                 println!("{}", stat.get_url());
                 continue;
             }
-            if !self.json {
+            if self.format.is_table() {
                 // print title first, then fetch
                 if idx > 0 && !self.short {
                     println!(); // vertical whitespace
@@ -363,11 +454,23 @@ impl ResolvedArgs {
                     format!("@ {}", stat.get_url()).dimmed(),
                 );
             }
-            let stat = stat.fetch_and_read()?;
+            let stat = stat
+                .fetch_and_read()?
+                .filter_jobs(&self.job_filter)
+                .paginate(&self.pagination);
+            let stat = match self.summary {
+                true => stat.summarize(),
+                false => stat,
+            };
+            let pin = self.pin(PinnedInput::from_inputs(&stat.inputs).as_ref());
+            let stat = EvalReport { pin, ..stat };
             if !stat.is_success() {
                 status = false;
             }
-            if self.json {
+            for (group, builds) in stat.groups() {
+                self.print_rows(ReportKind::Evals, &stat.eval.spec, Some(group), builds)?;
+            }
+            if !self.format.is_table() {
                 indexmap.insert(&stat.eval.spec, stat);
                 continue;
             }
@@ -379,6 +482,14 @@ impl ResolvedArgs {
                 println!(); // vertical separation
                 println!("{entry}");
             }
+            if stat.pin.is_some() {
+                println!();
+                self.print_pin(stat.pin.as_ref());
+            }
+            if let Some(summary) = &stat.summary {
+                summary.print(&stat);
+                continue;
+            }
             if self.short {
                 continue;
             }
@@ -400,9 +511,7 @@ impl ResolvedArgs {
                 }
             }
         }
-        if self.json {
-            println!("{}", serde_json::to_string_pretty(&indexmap)?);
-        }
+        self.print_report(ReportKind::Evals, &indexmap)?;
         Ok(status)
     }
 }
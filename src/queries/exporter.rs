@@ -0,0 +1,263 @@
+//! A module that exports Prometheus metrics of the evaluations and releases
+//! of some channels and of the latest builds of some packages, either once
+//! with `--format openmetrics`, or served on `/metrics` by a minimal HTTP
+//! server, see `hydra-check exporter --listen ADDR`.
+//!
+//! The metrics are cached for `--cache-ttl` seconds, so that Hydra is
+//! not queried on every scrape.
+
+use std::{
+    io::{BufRead, BufReader, Write},
+    net::{SocketAddr, TcpListener, TcpStream},
+    time::{Duration, Instant},
+};
+
+use anyhow::bail;
+use log::{info, warn};
+
+use super::{jobset::JobsetReport, packages::PackageReport};
+use crate::{
+    args::aggregate_job,
+    constants::HYDRA_CHECK_HOST_URL,
+    formats::{MetricRows, Metrics, OutputFormat, OPENMETRICS_CONTENT_TYPE},
+    structs::ReleaseStatus,
+    BuildStatus, EvalStatus, FetchHydraReport, ResolvedArgs,
+};
+
+/// A channel (or jobset) to export, with the jobs of the packages
+#[derive(Debug, Clone)]
+pub(crate) struct ExportedJobset {
+    pub(crate) channel: Option<String>,
+    pub(crate) jobset: String,
+    pub(crate) jobs: Vec<String>,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct ExporterQuery {
+    pub(crate) jobsets: Vec<ExportedJobset>,
+    pub(crate) listen: SocketAddr,
+    pub(crate) cache_ttl: Duration,
+}
+
+/// Rendered metrics with the time they were collected at
+type Cache = Option<(Instant, String)>;
+
+/// Returns the cached metrics, which are collected again once expired;
+/// the expired metrics are still served if Hydra cannot be reached.
+fn cached(
+    cache: &mut Cache,
+    ttl: Duration,
+    collect: impl FnOnce() -> anyhow::Result<String>,
+) -> anyhow::Result<String> {
+    if let Some((time, metrics)) = cache {
+        if time.elapsed() < ttl {
+            return Ok(metrics.clone());
+        }
+    }
+    match collect() {
+        Ok(metrics) => {
+            *cache = Some((Instant::now(), metrics.clone()));
+            Ok(metrics)
+        }
+        Err(err) => match cache {
+            Some((_, metrics)) => {
+                warn!("serving expired metrics, as collecting them failed: {err:#}");
+                Ok(metrics.clone())
+            }
+            None => Err(err),
+        },
+    }
+}
+
+/// Responds to a request for `/metrics` with the `metrics`, e.g. the
+/// [`cached`] ones, which are only collected for that path.
+fn respond(
+    mut stream: TcpStream,
+    metrics: impl FnOnce() -> anyhow::Result<String>,
+) -> anyhow::Result<()> {
+    stream.set_read_timeout(Some(Duration::from_secs(10)))?;
+    let mut reader = BufReader::new(&stream);
+    let mut request = String::new();
+    reader.read_line(&mut request)?;
+    // the headers are not needed, but read anyway before responding
+    let mut header = String::new();
+    while reader.read_line(&mut header)? > 0 && !header.trim().is_empty() {
+        header.clear();
+    }
+    let path = request.split_whitespace().nth(1).unwrap_or_default();
+    let (status, content_type, body) = match path {
+        "/metrics" => match metrics() {
+            Ok(metrics) => ("200 OK", OPENMETRICS_CONTENT_TYPE, metrics),
+            Err(err) => {
+                warn!("failed to collect the metrics: {err:#}");
+                let body = format!("failed to collect the metrics: {err:#}\n");
+                ("503 Service Unavailable", "text/plain", body)
+            }
+        },
+        "/" => {
+            let body = r#"<a href="/metrics">metrics</a>"#.to_string();
+            ("200 OK", "text/html", body)
+        }
+        _ => ("404 Not Found", "text/plain", "not found\n".into()),
+    };
+    write!(
+        stream,
+        "HTTP/1.1 {status}\r\nContent-Type: {content_type}\r\n\
+        Content-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    )?;
+    Ok(())
+}
+
+impl ResolvedArgs {
+    /// Collects the metrics of every jobset, skipping the jobsets that
+    /// fail, which are reported by `hydra_check_up`.
+    fn collect_metrics(&self, query: &ExporterQuery) -> Metrics {
+        let mut metrics = Metrics::default();
+        for exported in &query.jobsets {
+            let jobset = exported.jobset.as_str();
+            let up = match self.collect_jobset_metrics(exported) {
+                Ok(jobset_metrics) => {
+                    metrics.append(jobset_metrics);
+                    true
+                }
+                Err(err) => {
+                    warn!("could not collect the metrics of jobset '{jobset}': {err:#}");
+                    false
+                }
+            };
+            metrics.add_up(jobset, up);
+        }
+        metrics
+    }
+
+    fn collect_jobset_metrics(&self, exported: &ExportedJobset) -> anyhow::Result<Metrics> {
+        let mut metrics = Metrics::default();
+        let jobset = exported.jobset.as_str();
+        let args = ResolvedArgs {
+            channel: exported.channel.clone(),
+            jobset: jobset.into(),
+            ..self.clone()
+        };
+        info!("fetching the evaluations and release tests of jobset '{jobset}'");
+        let evals = JobsetReport::from(&args).fetch_and_read()?.evals;
+        EvalStatus::add_metrics(&mut metrics, jobset, jobset, &evals);
+        let tests =
+            PackageReport::from_package_with_args(aggregate_job(jobset), &args).fetch_and_read()?;
+        let channel = exported.channel.as_deref().unwrap_or_default();
+        let releases = ReleaseStatus::pair(evals, &tests.builds, channel, jobset);
+        ReleaseStatus::add_metrics(&mut metrics, jobset, jobset, &releases);
+        for job in &exported.jobs {
+            let stat = PackageReport::from_package_with_args(job, &args).fetch_and_read()?;
+            BuildStatus::add_metrics(&mut metrics, jobset, job, &stat.builds);
+        }
+        Ok(metrics)
+    }
+
+    /// Serves the metrics until interrupted, one request at a time.
+    fn serve_metrics(&self, query: &ExporterQuery) -> anyhow::Result<bool> {
+        let listener = TcpListener::bind(query.listen)?;
+        info!(
+            "serving metrics on http://{}/metrics",
+            listener.local_addr()?
+        );
+        let mut cache = None;
+        let collect = || Ok(self.collect_metrics(query).render()?);
+        for stream in listener.incoming() {
+            let result = stream.map_err(anyhow::Error::from).and_then(|stream| {
+                respond(stream, || cached(&mut cache, query.cache_ttl, collect))
+            });
+            if let Err(err) = result {
+                warn!("failed to respond to a request: {err:#}");
+            }
+        }
+        Ok(true)
+    }
+
+    pub(crate) fn fetch_and_print_exporter(&self, query: &ExporterQuery) -> anyhow::Result<bool> {
+        if self.url {
+            for exported in &query.jobsets {
+                println!(
+                    "{}/jobset/{}/evals",
+                    &*HYDRA_CHECK_HOST_URL, exported.jobset
+                );
+                for job in &exported.jobs {
+                    let args = ResolvedArgs {
+                        jobset: exported.jobset.clone(),
+                        ..self.clone()
+                    };
+                    println!(
+                        "{}",
+                        PackageReport::from_package_with_args(job, &args).get_url()
+                    );
+                }
+            }
+            return Ok(true);
+        }
+        match self.format {
+            OutputFormat::Table => self.serve_metrics(query),
+            OutputFormat::Openmetrics => {
+                let metrics = self.collect_metrics(query);
+                self.print_metrics(&metrics)?;
+                Ok(true)
+            }
+            _ => bail!("the exporter only supports --format openmetrics"),
+        }
+    }
+}
+
+/// Sends a request for `path` to [`respond`], and returns the response.
+#[cfg(test)]
+fn request(path: &str, metrics: impl FnOnce() -> anyhow::Result<String>) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let request = format!("GET {path} HTTP/1.1\r\nHost: {addr}\r\nAccept: */*\r\n\r\n");
+    let client = std::thread::spawn(move || {
+        let mut stream = TcpStream::connect(addr).unwrap();
+        stream.write_all(request.as_bytes()).unwrap();
+        let mut response = String::new();
+        std::io::Read::read_to_string(&mut stream, &mut response).unwrap();
+        response
+    });
+    let (stream, _) = listener.accept().unwrap();
+    respond(stream, metrics).unwrap();
+    client.join().unwrap()
+}
+
+#[test]
+fn respond_to_requests() {
+    let metrics = || Ok("hydra_eval_builds 1\n# EOF\n".to_string());
+    insta::assert_snapshot!(request("/metrics", metrics), @r#"
+    HTTP/1.1 200 OK
+    Content-Type: application/openmetrics-text; version=1.0.0; charset=utf-8
+    Content-Length: 26
+    Connection: close
+
+    hydra_eval_builds 1
+    # EOF
+    "#);
+    assert!(request("/", metrics).starts_with("HTTP/1.1 200 OK\r\n"));
+    let response = request("/favicon.ico", || unreachable!());
+    assert!(response.starts_with("HTTP/1.1 404 Not Found\r\n"));
+    let response = request("/metrics", || anyhow::bail!("connection refused"));
+    assert!(response.starts_with("HTTP/1.1 503 Service Unavailable\r\n"));
+    assert!(response.ends_with("failed to collect the metrics: connection refused\n"));
+}
+
+#[test]
+fn cache_metrics() {
+    let ttl = Duration::from_secs(60);
+    let mut cache = None;
+    let err = cached(&mut cache, ttl, || anyhow::bail!("connection refused"));
+    assert!(err.is_err() && cache.is_none());
+    let metrics = cached(&mut cache, ttl, || Ok("first".into()));
+    assert_eq!(metrics.unwrap(), "first");
+    // not collected again until expired
+    let metrics = cached(&mut cache, ttl, || unreachable!());
+    assert_eq!(metrics.unwrap(), "first");
+    let metrics = cached(&mut cache, Duration::ZERO, || Ok("second".into()));
+    assert_eq!(metrics.unwrap(), "second");
+    // the expired metrics are served if collecting them fails
+    let metrics = cached(&mut cache, Duration::ZERO, || anyhow::bail!("timed out"));
+    assert_eq!(metrics.unwrap(), "second");
+}
@@ -1,13 +1,19 @@
 //! A module that formats the details of the specified (or inferred) jobset,
 //! from an url like: <https://hydra.nixos.org/jobset/nixpkgs/unstable/evals>.
 
-use anyhow::bail;
+use anyhow::{anyhow, bail};
 use colored::Colorize;
 use indexmap::IndexMap;
+use log::info;
+use scraper::ElementRef;
 
 use crate::{
-    constants::HYDRA_CHECK_HOST_URL, is_skipable_row, EvalStatus, FetchHydraReport, ResolvedArgs,
-    SoupFind, StatusIcon, TryAttr,
+    constants::{self, HYDRA_CHECK_HOST_URL},
+    fetch_json,
+    formats::ReportKind,
+    is_skipable_row,
+    structs::{EvalSearch, JobsetConfig},
+    EvalStatus, FetchHydraReport, Pagination, ResolvedArgs, SoupFind, StatusIcon, TryAttr,
 };
 
 #[derive(Clone)]
@@ -15,6 +21,7 @@ use crate::{
 pub(crate) struct JobsetReport<'a> {
     jobset: &'a str,
     url: String,
+    pagination: Pagination,
     /// Status of recent evaluations of the jobset
     pub(crate) evals: Vec<EvalStatus>,
 }
@@ -45,6 +52,7 @@ impl<'a> From<&'a ResolvedArgs> for JobsetReport<'a> {
         Self {
             jobset: &args.jobset,
             url,
+            pagination: args.pagination,
             evals: vec![],
         }
     }
@@ -52,11 +60,62 @@ impl<'a> From<&'a ResolvedArgs> for JobsetReport<'a> {
 
 impl JobsetReport<'_> {
     pub(crate) fn fetch_and_read(self) -> anyhow::Result<Self> {
-        let doc = self.fetch_document()?;
-        let tbody = match self.find_tbody(&doc, "") {
+        let read_tbody = |tbody: ElementRef<'_>| self.read_evals(tbody);
+        let timestamp = |eval: &EvalStatus| eval.timestamp.and_then(|x| x.try_into().ok());
+        let evals = match self.fetch_pages("", &self.pagination, read_tbody, timestamp)? {
             Err(stat) => return Ok(stat),
-            Ok(tbody) => tbody,
+            Ok(evals) => evals,
         };
+        Ok(Self { evals, ..self })
+    }
+
+    /// Fetches the recent evaluations of the jobset page by page, until
+    /// the newest evaluation that matches the [`EvalSearch`] is found.
+    pub(crate) fn fetch_until(&self, search: &EvalSearch) -> anyhow::Result<Vec<EvalStatus>> {
+        let pagination = match search {
+            EvalSearch::Revision(_) => Pagination {
+                limit: Some(
+                    self.pagination
+                        .limit
+                        .unwrap_or(constants::DEFAULT_EVAL_SEARCH_LIMIT),
+                ),
+                ..self.pagination
+            },
+            EvalSearch::Date(date) => Pagination {
+                limit: Some(1),
+                until: Some(self.pagination.until.map_or(*date, |x| x.min(*date))),
+                ..self.pagination
+            },
+        };
+        self.fetch_evals_until(&pagination, |eval| search.matches(eval))
+    }
+
+    /// Fetches the recent evaluations of the jobset page by page, until
+    /// an evaluation is `found` or the limits of the [`Pagination`] are met.
+    pub(crate) fn fetch_evals_until(
+        &self,
+        pagination: &Pagination,
+        found: impl Fn(&EvalStatus) -> bool,
+    ) -> anyhow::Result<Vec<EvalStatus>> {
+        let read_tbody = |tbody: ElementRef<'_>| self.read_evals(tbody);
+        let timestamp = |eval: &EvalStatus| eval.timestamp.and_then(|x| x.try_into().ok());
+        match self.fetch_pages_until("", pagination, read_tbody, timestamp, found)? {
+            Err(stat) => {
+                let status: Vec<_> = stat.evals.into_iter().map(|eval| eval.status).collect();
+                bail!("{}", status.join(" "));
+            }
+            Ok(evals) => Ok(evals),
+        }
+    }
+
+    /// Searches the recent evaluations of the jobset page by page,
+    /// returning the newest evaluation that matches the [`EvalSearch`].
+    pub(crate) fn find_eval(self, search: &EvalSearch) -> anyhow::Result<Option<EvalStatus>> {
+        let evals = self.fetch_until(search)?;
+        Ok(evals.into_iter().find(|eval| search.matches(eval)))
+    }
+
+    fn read_evals(&self, tbody: ElementRef<'_>) -> anyhow::Result<Vec<EvalStatus>> {
         let mut evals: Vec<EvalStatus> = Vec::new();
         for row in tbody.find_all("tr") {
             let columns = row.find_all("td");
@@ -136,7 +195,7 @@ impl JobsetReport<'_> {
                 delta,
             });
         }
-        Ok(Self { evals, ..self })
+        Ok(evals)
     }
 }
 
@@ -151,15 +210,15 @@ impl ResolvedArgs {
         force_short_output: bool,
     ) -> anyhow::Result<Option<u64>> {
         let stat = JobsetReport::from(self);
-        let (short, json) = match force_short_output {
-            true => (true, false),
-            false => (self.short, self.json),
+        let (short, table) = match force_short_output {
+            true => (true, true),
+            false => (self.short, self.format.is_table()),
         };
         if self.url {
             println!("{}", stat.get_url());
             return Ok(None);
         }
-        if !json {
+        if table {
             // print title first, then fetch
             println!(
                 "Evaluations of jobset {} {}",
@@ -170,22 +229,89 @@ impl ResolvedArgs {
         let stat = stat.fetch_and_read()?;
         let first_stat = stat.evals.first();
         let latest_id = first_stat.and_then(|x| x.id);
-        if json {
+        if !table {
+            self.print_rows(ReportKind::Jobset, stat.jobset, None, &stat.evals)?;
             let mut indexmap = IndexMap::new();
-            match short {
-                true => indexmap.insert(
-                    &stat.jobset,
-                    match first_stat {
-                        Some(x) => vec![x.to_owned()],
-                        None => vec![],
-                    },
-                ),
-                false => indexmap.insert(&stat.jobset, stat.evals),
-            };
-            println!("{}", serde_json::to_string_pretty(&indexmap)?);
+            indexmap.insert(&stat.jobset, &stat.evals);
+            self.print_report(ReportKind::Jobset, &indexmap)?;
             return Ok(latest_id);
         }
         println!("{}", stat.format_table(short, &stat.evals));
         Ok(latest_id)
     }
 }
+
+impl ResolvedArgs {
+    /// Searches for the evaluation specified by `--eval rev:<commit>`
+    /// or `--eval @<date>`, prints it, and returns its ID.
+    pub(crate) fn fetch_and_print_eval_search(&self, search: &EvalSearch) -> anyhow::Result<u64> {
+        let stat = JobsetReport::from(self);
+        info!(
+            "searching the evaluations of --jobset '{}' for {search}",
+            self.jobset
+        );
+        let err = || {
+            anyhow!(
+                "could not find an evaluation of --jobset '{}' for {search}",
+                self.jobset
+            )
+        };
+        let eval = stat.clone().find_eval(search)?.ok_or_else(err)?;
+        if self.format.is_table() && !self.url {
+            println!(
+                "Evaluation of jobset {} for {search} {}",
+                self.jobset.bold(),
+                format!("@ {}", stat.get_url()).dimmed()
+            );
+            println!("{}", stat.format_table(true, &vec![eval.clone()]));
+            println!();
+        }
+        eval.id.ok_or_else(err)
+    }
+}
+
+impl ResolvedArgs {
+    /// Fetches the url of the nixpkgs input of the jobset, which its
+    /// releases are pinned to.
+    pub(crate) fn fetch_input_url(&self) -> anyhow::Result<Option<String>> {
+        let url = format!("{}/jobset/{}", &*HYDRA_CHECK_HOST_URL, self.jobset);
+        let config: JobsetConfig = fetch_json(&url)?;
+        Ok(config.input_url().map(str::to_string))
+    }
+
+    /// Prints the configuration of the jobset, as shown in the
+    /// "Configuration" tab of e.g. <https://hydra.nixos.org/jobset/nixpkgs/unstable>.
+    pub(crate) fn fetch_and_print_jobset_config(&self) -> anyhow::Result<bool> {
+        let url = format!("{}/jobset/{}", &*HYDRA_CHECK_HOST_URL, self.jobset);
+        if self.url {
+            println!("{url}");
+            return Ok(true);
+        }
+        if self.format.is_table() {
+            println!(
+                "Configuration of jobset {} {}",
+                self.jobset.bold(),
+                format!("@ {url}#tabs-configuration").dimmed()
+            );
+        }
+        let config: JobsetConfig = fetch_json(&url)?;
+        if !self.format.is_table() {
+            let mut indexmap = IndexMap::new();
+            self.print_flat_rows(
+                ReportKind::JobsetConfig,
+                Some(&self.jobset),
+                None,
+                std::slice::from_ref(&config),
+            )?;
+            indexmap.insert(&self.jobset, config);
+            self.print_report(ReportKind::JobsetConfig, &indexmap)?;
+            return Ok(true);
+        }
+        println!("{config}");
+        for input in config.jobsetinputs.values() {
+            println!(); // vertical separation
+            println!("{input}");
+        }
+        Ok(true)
+    }
+}
@@ -0,0 +1,445 @@
+//! A module that answers whether a nixpkgs commit has reached a channel,
+//! by testing its ancestry against the revisions of the recent evaluations
+//! and releases of the channel jobsets in a local nixpkgs git checkout,
+//! see `hydra-check landed`.
+
+use anyhow::bail;
+use colored::Colorize;
+use indexmap::IndexMap;
+use log::{debug, warn};
+use schemars::JsonSchema;
+use serde::Serialize;
+use serde_with::skip_serializing_none;
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use super::{jobset::JobsetReport, packages::PackageReport};
+use crate::{
+    args::aggregate_job,
+    constants,
+    formats::{cell, FlatRow, ReportKind},
+    structs::ReleaseStatus,
+    BuildStatus, EvalStatus, FetchHydraReport, Pagination, ResolvedArgs, StatusIcon,
+};
+
+/// The commit to look for, and the channels to look in
+#[derive(Debug, Clone)]
+pub(crate) struct LandedQuery {
+    pub(crate) commit: String,
+    pub(crate) nixpkgs_repo: PathBuf,
+    /// Pairs of `(channel, jobset)`, the channel is unknown for `--jobset`
+    pub(crate) channels: Vec<(Option<String>, String)>,
+}
+
+/// A local git checkout of nixpkgs
+struct Checkout<'a>(&'a Path);
+
+impl Checkout<'_> {
+    fn git(&self, args: &[&str]) -> anyhow::Result<std::process::Output> {
+        debug!("git -C {} {}", self.0.display(), args.join(" "));
+        Ok(Command::new("git")
+            .arg("-C")
+            .arg(self.0)
+            .args(args)
+            .output()?)
+    }
+
+    /// Resolves a (abbreviated) revision to the full commit hash.
+    fn resolve(&self, rev: &str) -> anyhow::Result<String> {
+        let output = self.git(&[
+            "rev-parse",
+            "--verify",
+            "--quiet",
+            &format!("{rev}^{{commit}}"),
+        ])?;
+        if !output.status.success() {
+            bail!(
+                "could not find commit '{rev}' in {}, consider running `git fetch` there",
+                self.0.display()
+            );
+        }
+        Ok(String::from_utf8(output.stdout)?.trim().into())
+    }
+
+    /// Checks if `commit` is an ancestor of `rev`, or returns `None`
+    /// if `rev` is unknown to the checkout.
+    fn contains(&self, commit: &str, rev: &str) -> Option<bool> {
+        let output = self
+            .git(&["merge-base", "--is-ancestor", commit, rev])
+            .ok()?;
+        match output.status.code() {
+            Some(0) => Some(true),
+            Some(1) => Some(false),
+            _ => None,
+        }
+    }
+}
+
+/// Container for the first evaluation and release of a channel
+/// that contain the commit
+#[skip_serializing_none]
+#[derive(Serialize, JsonSchema, Clone)]
+pub(super) struct LandedReport<'a> {
+    channel: Option<&'a str>,
+    jobset: &'a str,
+    url: String,
+    error: Option<String>,
+    landed: bool,
+    /// Whether all the fetched evaluations contain the commit, so that
+    /// it may have landed before the `first_eval`, see `--limit`
+    capped: bool,
+    first_eval: Option<EvalStatus>,
+    first_release: Option<ReleaseStatus>,
+    latest_eval: Option<EvalStatus>,
+}
+
+impl FetchHydraReport for LandedReport<'_> {
+    fn get_url(&self) -> &str {
+        &self.url
+    }
+
+    fn finish_with_error(self, status: String) -> Self {
+        Self {
+            error: Some(status),
+            ..self
+        }
+    }
+}
+
+impl LandedReport<'_> {
+    /// Reads the evaluations that contain the commit, which are the
+    /// recent evaluations up to the first one that does not, unless
+    /// the revision of that one is not in the checkout.
+    fn read_evals(
+        self,
+        evals: &[EvalStatus],
+        contains: impl Fn(&EvalStatus) -> Option<bool>,
+    ) -> (Self, Vec<EvalStatus>) {
+        let stat = Self {
+            latest_eval: evals.first().cloned(),
+            ..self
+        };
+        let boundary = evals
+            .iter()
+            .position(|eval| contains(eval) != Some(true))
+            .unwrap_or(evals.len());
+        if let Some(eval) = evals.get(boundary).filter(|x| contains(x).is_none()) {
+            let status = format!(
+                "revision '{}' of evaluation {} is not in the checkout, {}",
+                eval.short_rev.as_deref().unwrap_or_default(),
+                eval.id.unwrap_or_default(),
+                "consider running `git fetch` there"
+            );
+            return (stat.finish_with_error(status), vec![]);
+        }
+        let landed = evals[..boundary].to_vec();
+        let stat = Self {
+            landed: !landed.is_empty(),
+            capped: !landed.is_empty() && boundary == evals.len(),
+            first_eval: landed.last().cloned(),
+            ..stat
+        };
+        (stat, landed)
+    }
+
+    /// Finds the first release of the evaluations that contain the commit,
+    /// i.e. the oldest one that is finished and passed its release tests.
+    fn read_releases(self, landed: Vec<EvalStatus>, tests: &[BuildStatus]) -> Self {
+        let first_release =
+            ReleaseStatus::pair(landed, tests, self.channel.unwrap_or_default(), self.jobset)
+                .into_iter()
+                .rev()
+                .find(|x| x.test.success && x.eval.finished.unwrap_or_default());
+        Self {
+            first_release,
+            ..self
+        }
+    }
+}
+
+impl FlatRow for LandedReport<'_> {
+    const COLUMNS: &'static [&'static str] = &[
+        "channel",
+        "jobset",
+        "landed",
+        "capped",
+        "first_eval",
+        "first_eval_datetime",
+        "first_release_url",
+        "latest_eval",
+        "error",
+    ];
+
+    fn values(&self) -> Vec<String> {
+        let first_eval = self.first_eval.as_ref();
+        let first_release = self.first_release.as_ref();
+        vec![
+            cell(self.channel),
+            self.jobset.into(),
+            self.landed.to_string(),
+            self.capped.to_string(),
+            cell(first_eval.and_then(|x| x.id)),
+            cell(first_eval.and_then(|x| x.datetime.as_ref())),
+            cell(first_release.and_then(|x| x.release_url.as_ref())),
+            cell(self.latest_eval.as_ref().and_then(|x| x.id)),
+            cell(self.error.as_ref()),
+        ]
+    }
+}
+
+impl ResolvedArgs {
+    fn fetch_landed_report<'a>(
+        &self,
+        checkout: &Checkout,
+        commit: &str,
+        channel: Option<&'a str>,
+        jobset: &'a str,
+    ) -> anyhow::Result<LandedReport<'a>> {
+        let args = ResolvedArgs {
+            channel: channel.map(str::to_string),
+            jobset: jobset.into(),
+            ..self.clone()
+        };
+        let jobset_report = JobsetReport::from(&args);
+        let stat = LandedReport {
+            channel,
+            jobset,
+            url: jobset_report.get_url().into(),
+            error: None,
+            landed: false,
+            capped: false,
+            first_eval: None,
+            first_release: None,
+            latest_eval: None,
+        };
+        // evaluations often share a revision, which is only checked once
+        let ancestry = RefCell::new(HashMap::new());
+        let contains = |eval: &EvalStatus| {
+            let rev = eval.short_rev.as_deref().unwrap_or_default();
+            *ancestry
+                .borrow_mut()
+                .entry(rev.to_string())
+                .or_insert_with(|| checkout.contains(commit, rev))
+        };
+        // the evaluations are sorted from new to old, so stop at
+        // the first one that does not contain the commit
+        let pagination = Pagination {
+            limit: Some(
+                self.pagination
+                    .limit
+                    .unwrap_or(constants::DEFAULT_EVAL_SEARCH_LIMIT),
+            ),
+            ..self.pagination
+        };
+        let evals =
+            jobset_report.fetch_evals_until(&pagination, |eval| contains(eval) != Some(true))?;
+        let (stat, landed) = stat.read_evals(&evals, contains);
+        let Some(first_eval) = landed.last() else {
+            return Ok(stat);
+        };
+        // the release tests of all the evaluations that contain the commit,
+        // which are built after the first one
+        let args = ResolvedArgs {
+            pagination: Pagination {
+                since: first_eval.timestamp.and_then(|x| i64::try_from(x).ok()),
+                ..Default::default()
+            },
+            ..args
+        };
+        let tests =
+            PackageReport::from_package_with_args(aggregate_job(jobset), &args).fetch_and_read()?;
+        Ok(stat.read_releases(landed, &tests.builds))
+    }
+
+    pub(crate) fn fetch_and_print_landed(&self, query: &LandedQuery) -> anyhow::Result<bool> {
+        let checkout = Checkout(&query.nixpkgs_repo);
+        let mut status = true;
+        let mut indexmap = IndexMap::new();
+        let commit = match self.url {
+            true => query.commit.clone(),
+            false => checkout.resolve(&query.commit)?,
+        };
+        let short_commit = commit.get(..12).unwrap_or(&commit);
+        for (idx, (channel, jobset)) in query.channels.iter().enumerate() {
+            if self.url {
+                println!(
+                    "{}/jobset/{jobset}/evals",
+                    &*constants::HYDRA_CHECK_HOST_URL
+                );
+                continue;
+            }
+            if self.format.is_table() {
+                if idx > 0 {
+                    println!(); // vertical whitespace
+                }
+                println!(
+                    "Commit {} in {} {}",
+                    short_commit.bold(),
+                    match channel {
+                        Some(channel) => format!("channel {} (jobset {jobset})", channel.bold()),
+                        None => format!("jobset {}", jobset.bold()),
+                    },
+                    format!(
+                        "@ {}/jobset/{jobset}/evals",
+                        &*constants::HYDRA_CHECK_HOST_URL
+                    )
+                    .dimmed()
+                );
+            }
+            let stat = self.fetch_landed_report(&checkout, &commit, channel.as_deref(), jobset)?;
+            if !stat.landed {
+                status = false;
+            }
+            if !self.format.is_table() {
+                let row = std::slice::from_ref(&stat);
+                self.print_flat_rows(ReportKind::Landed, Some(&commit), None, row)?;
+                indexmap.insert(jobset.as_str(), stat);
+                continue;
+            }
+            if let Some(error) = &stat.error {
+                warn!("{error}");
+                continue;
+            }
+            let Some(first_eval) = &stat.first_eval else {
+                let latest = stat.latest_eval.as_ref().and_then(|x| x.id);
+                println!(
+                    "{} not in the latest evaluation {} yet",
+                    StatusIcon::Failed,
+                    latest.map(|x| x.to_string()).unwrap_or_default()
+                );
+                continue;
+            };
+            println!("{}", "First evaluation:".bold());
+            println!("{}", stat.format_table(false, &vec![first_eval.clone()]));
+            if stat.capped {
+                warn!(
+                    "{}, so it may have landed earlier, see --limit",
+                    "all the fetched evaluations contain the commit"
+                );
+            }
+            match &stat.first_release {
+                Some(release) => {
+                    println!("{}", "First release:".bold());
+                    println!("{}", stat.format_table(false, &vec![release.clone()]));
+                }
+                None => println!("{} not in a release yet", StatusIcon::Queued),
+            }
+        }
+        if !self.format.is_table() {
+            let mut json = IndexMap::new();
+            json.insert(commit.as_str(), indexmap);
+            self.print_report(ReportKind::Landed, &json)?;
+        }
+        Ok(status)
+    }
+}
+
+#[test]
+fn test_ancestry() {
+    if Command::new("git").arg("--version").output().is_err() {
+        eprintln!("git is not installed, skipping");
+        return;
+    }
+    let repo = std::env::temp_dir().join(format!("hydra-check-landed-{}", std::process::id()));
+    std::fs::create_dir_all(&repo).unwrap();
+    let checkout = Checkout(&repo);
+    let git = |args: &[&str]| assert!(checkout.git(args).unwrap().status.success());
+    git(&["init", "--quiet"]);
+    let commit = |message: &str| {
+        git(&[
+            "-c",
+            "user.name=hydra-check",
+            "-c",
+            "user.email=hydra-check@localhost",
+            "commit",
+            "--quiet",
+            "--allow-empty",
+            "-m",
+            message,
+        ]);
+        checkout.resolve("HEAD").unwrap()
+    };
+    let (first, second) = (commit("first"), commit("second"));
+    assert_eq!(checkout.contains(&first, &second[..12]), Some(true));
+    assert_eq!(checkout.contains(&second, &first), Some(false));
+    assert_eq!(checkout.contains(&first, "1e9e641a3fc1"), None);
+    assert!(checkout.resolve("1e9e641a3fc1").is_err());
+    std::fs::remove_dir_all(&repo).unwrap();
+}
+
+#[test]
+fn read_landed_report() {
+    let eval = |id: u64, rev: &str| EvalStatus {
+        id: Some(id),
+        short_rev: Some(rev.into()),
+        finished: Some(true),
+        ..Default::default()
+    };
+    let evals = [
+        eval(1_814_989, "5d8f4be"),
+        eval(1_814_988, "8c4dc69"),
+        eval(1_814_987, "1e9e641"),
+    ];
+    let test = |rev: &str, success: bool| BuildStatus {
+        success,
+        name: Some(format!("nixpkgs-25.11pre.{rev}")),
+        ..Default::default()
+    };
+    let stat = LandedReport {
+        channel: Some("nixpkgs-unstable"),
+        jobset: "nixpkgs/unstable",
+        url: String::new(),
+        error: None,
+        landed: false,
+        capped: false,
+        first_eval: None,
+        first_release: None,
+        latest_eval: None,
+    };
+    // the commit is in the two latest evaluations
+    let (landed, evals_landed) = stat.clone().read_evals(&evals, |eval| {
+        Some(eval.short_rev.as_deref() != Some("1e9e641"))
+    });
+    assert!(landed.landed && !landed.capped);
+    assert_eq!(
+        landed.first_eval.as_ref().and_then(|x| x.id),
+        Some(1_814_988)
+    );
+    assert_eq!(
+        landed.latest_eval.as_ref().and_then(|x| x.id),
+        Some(1_814_989)
+    );
+    // the first release is the oldest one with passing release tests
+    let first_release = |tests: &[BuildStatus]| {
+        let stat = landed.clone().read_releases(evals_landed.clone(), tests);
+        stat.first_release.and_then(|x| x.eval.id)
+    };
+    let tests = [test("5d8f4be", true), test("8c4dc69", true)];
+    assert_eq!(first_release(&tests), Some(1_814_988));
+    let tests = [test("5d8f4be", true), test("8c4dc69", false)];
+    assert_eq!(first_release(&tests), Some(1_814_989));
+    assert_eq!(first_release(&[]), None);
+    // the commit is in all the fetched evaluations, so it may have landed earlier
+    let (capped, evals_landed) = stat.clone().read_evals(&evals, |_| Some(true));
+    assert!(capped.landed && capped.capped && evals_landed.len() == 3);
+    // the commit is not in the latest evaluation yet
+    let (pending, evals_landed) = stat.clone().read_evals(&evals, |_| Some(false));
+    assert!(!pending.landed && !pending.capped && pending.first_eval.is_none());
+    assert!(evals_landed.is_empty());
+    // the first evaluation without the commit is not in the checkout
+    let (unknown, evals_landed) = stat.read_evals(&evals, |eval| {
+        (eval.short_rev.as_deref() != Some("1e9e641")).then_some(true)
+    });
+    assert!(!unknown.landed && evals_landed.is_empty());
+    assert_eq!(
+        unknown.error.as_deref(),
+        Some(
+            "revision '1e9e641' of evaluation 1814987 is not in the checkout, \
+            consider running `git fetch` there"
+        )
+    );
+}
@@ -1,4 +1,19 @@
 mod builds;
 mod evals;
+mod exporter;
 mod jobset;
+mod landed;
 mod packages;
+mod projects;
+mod queue;
+mod schema;
+mod search;
+mod store_paths;
+mod summary;
+mod system;
+
+pub(crate) use exporter::{ExportedJobset, ExporterQuery};
+pub(crate) use landed::LandedQuery;
+pub(crate) use schema::print_json_schemas;
+pub(crate) use store_paths::normalize_store_path;
+pub(crate) use system::SystemVersion;
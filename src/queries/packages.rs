@@ -3,24 +3,26 @@
 
 use colored::Colorize;
 use indexmap::IndexMap;
-use log::{info, warn};
-use std::collections::VecDeque;
+use log::{debug, info, warn};
 
-use super::builds::BuildReport;
+use super::{builds::BuildReport, queue::print_queue_depth, search::print_suggestions};
 use crate::{
     constants,
+    formats::ReportKind,
     queries::jobset::JobsetReport,
-    structs::{BuildStatus, EvalStatus, ReleaseStatus},
-    FetchHydraReport, ResolvedArgs, StatusIcon,
+    reproduce::ReproduceFormat,
+    structs::{BuildStatus, PinnedInput, ReleaseStatus},
+    url_exists, FetchHydraReport, Pagination, ResolvedArgs, StatusIcon,
 };
 
 #[derive(Clone)]
 /// Container for the build status and metadata of a package
-struct PackageReport<'a> {
+pub(super) struct PackageReport<'a> {
     package: &'a str,
     url: String,
+    pagination: Pagination,
     /// Status of recent builds of the package
-    builds: Vec<BuildStatus>,
+    pub(super) builds: Vec<BuildStatus>,
 }
 
 impl FetchHydraReport for PackageReport<'_> {
@@ -43,45 +45,73 @@ impl FetchHydraReport for PackageReport<'_> {
 impl<'a> PackageReport<'a> {
     /// Initializes the status container with the resolved package name
     /// and the resolved command line arguments.
-    fn from_package_with_args(package: &'a str, args: &'a ResolvedArgs) -> Self {
+    pub(super) fn from_package_with_args(package: &'a str, args: &'a ResolvedArgs) -> Self {
         //
         // Examples:
         // - https://hydra.nixos.org/job/nixos/release-19.09/nixpkgs.hello.x86_64-linux/latest
         // - https://hydra.nixos.org/job/nixos/release-19.09/nixos.tests.installer.simpleUefiGrub.aarch64-linux
         // - https://hydra.nixos.org/job/nixpkgs/unstable/hello.x86_64-linux/all
         //
-        // There is also {url}/all which is a lot slower.
+        // There is also {url}/all which is a lot slower, but paginated.
         //
         let url = format!(
             "{}/job/{}/{package}{}",
             &*constants::HYDRA_CHECK_HOST_URL,
             args.jobset,
-            if args.long { "/all" } else { "" }
+            if args.long || args.pagination.is_active() {
+                "/all"
+            } else {
+                ""
+            }
         );
         Self {
             package,
             url,
+            pagination: args.pagination,
             builds: vec![],
         }
     }
 
-    fn fetch_and_read(self) -> anyhow::Result<Self> {
-        let doc = self.fetch_document()?;
-        let tbody = match self.find_tbody(&doc, "") {
-            Err(stat) => return Ok(stat),
-            Ok(tbody) => tbody,
-        };
-        let builds = BuildStatus::from_tbody(tbody)?;
+    pub(super) fn fetch_and_read(self) -> anyhow::Result<Self> {
+        let timestamp = BuildStatus::unix_timestamp;
+        let builds =
+            match self.fetch_pages("", &self.pagination, BuildStatus::from_tbody, timestamp)? {
+                Err(stat) => return Ok(stat),
+                Ok(builds) => builds,
+            };
         Ok(Self { builds, ..self })
     }
 }
 
 impl ResolvedArgs {
+    /// Fetches the latest build of a package, with its details for
+    /// `--check-cache` and its inputs for `--reproduce` and `--pin-format`.
+    fn fetch_latest_build(&self, stat: &PackageReport) -> anyhow::Result<Option<BuildReport>> {
+        if self.check_cache.is_none() && self.reproduce.is_none() && self.pin_format.is_none() {
+            return Ok(None);
+        }
+        let Some(id) = stat.builds.first().and_then(|x| x.build_id.as_deref()) else {
+            warn!("no build of {} to check or reproduce", stat.package);
+            return Ok(None);
+        };
+        let build_report = BuildReport::from_id(id.parse()?).fetch_details()?;
+        match (self.reproduce, self.pin_format) {
+            (Some(ReproduceFormat::Nix), _) | (_, Some(_)) => {
+                Ok(Some(build_report.fetch_and_read()?))
+            }
+            _ => Ok(Some(build_report)),
+        }
+    }
+
     #[allow(clippy::too_many_lines)]
     pub(crate) fn fetch_and_print_packages(&self, packages: &[String]) -> anyhow::Result<bool> {
         let mut status = true;
         let mut all_builds = IndexMap::new();
         let mut all_releases = IndexMap::new();
+        let input_url = match self.releases && self.pin_format.is_some() && !self.url {
+            true => self.fetch_input_url()?,
+            false => None,
+        };
         for (idx, package) in packages.iter().enumerate() {
             // postpone fetching until after the title is printed
             let stat = PackageReport::from_package_with_args(package, self);
@@ -93,13 +123,11 @@ impl ResolvedArgs {
             let jobset = self.jobset.as_str();
             let jobset_report = if self.releases {
                 info!("fetching recent evals on --jobset {jobset} for --releases");
-                let jobset_report = JobsetReport::from(self).fetch_and_read()?;
-                eprintln!();
-                Some(jobset_report)
+                Some(JobsetReport::from(self).fetch_and_read()?)
             } else {
                 None
             };
-            if !self.json {
+            if self.format.is_table() {
                 // print title first, then fetch
                 if idx > 0 && !self.short {
                     println!(); // vertical whitespace
@@ -113,73 +141,109 @@ impl ResolvedArgs {
                     println!("{url_dimmed}");
                 }
             }
-            let stat = stat.fetch_and_read()?;
+            let mut stat = stat.fetch_and_read()?;
+            let latest = self.fetch_latest_build(&stat)?;
+            let (cache, reproduce) = match &latest {
+                Some(latest) => {
+                    let details = latest.details.clone().unwrap_or_default();
+                    let outputs = details.buildoutputs.iter();
+                    (
+                        self.check_cache(outputs.map(|(name, x)| (name.as_str(), x.path.as_str()))),
+                        self.reproduce(&details, &latest.inputs),
+                    )
+                }
+                None => (None, None),
+            };
             let first_stat = stat.builds.first();
             let success = first_stat.is_some_and(|build| build.success);
             if !success {
                 status = false;
             }
-            let release_stats = if let Some(jobset_report) = jobset_report {
-                // mutable refs that is quick to remove from the front
-                let mut test_builds: VecDeque<&BuildStatus> = stat.builds.iter().collect();
-
-                // if _all_ evals appear to be unfinished, it's likely that the
-                // instance is being rebooted, and we will always link to the
-                // releases as it's more practical
-                let always_link = jobset_report
-                    .evals
-                    .iter()
-                    .all(|eval| !eval.finished.unwrap_or_default());
+            let mut release_stats = if let Some(jobset_report) = jobset_report {
                 let channel = self.channel.as_deref().unwrap_or_else(|| {
                     warn!("--channel is not set, so we could not link to releases.nixos.org");
                     "" // set to empty string for ease of use below
                 });
-
-                // this captures `test_builds` mutably but it does _not_ need
-                // to be marked as `mut` because it is moved into .filter_map()
-                // and re-borrowed as mut by them.
-                let filter_eval = |eval: EvalStatus| {
-                    let short_rev = eval.short_rev.as_deref().unwrap_or_default();
-                    for index in 0..test_builds.len() {
-                        if test_builds[index]
-                            .name
-                            .as_deref()
-                            .unwrap_or_default()
-                            .contains(short_rev)
-                        {
-                            let test = test_builds.remove(index)?.clone();
-                            return Some(ReleaseStatus::new(
-                                eval,
-                                test,
-                                channel,
-                                jobset,
-                                always_link,
-                            ));
-                        }
-                    }
-                    None
-                };
-                jobset_report
-                    .evals
-                    .into_iter()
-                    .filter_map(filter_eval)
-                    .collect()
+                ReleaseStatus::pair(jobset_report.evals, &stat.builds, channel, jobset)
             } else {
                 vec![]
             };
-            if self.json {
+            let url_stripped = stat.get_url().trim_end_matches("/all").to_string();
+            // if the latest build failed, its inputs are shown below the table,
+            // and pinned instead of the inputs of the latest build
+            let latest_success = match success {
+                false
+                    if self.format.is_table() || (self.pin_format.is_some() && !self.releases) =>
+                {
+                    info!("fetching the latest success from a finished eval...");
+                    let url = format!("{url_stripped}/latest-finished");
+                    Some(BuildReport::from_url(&url).fetch_and_read()?)
+                }
+                _ => None,
+            };
+            // pin the latest successful build or published release
+            let is_published = |pinned: PinnedInput| {
+                let tarball = pinned.tarball_url().unwrap_or_default();
+                url_exists(&tarball).unwrap_or_else(|err| {
+                    debug!("could not check whether {tarball} is published: {err}");
+                    false
+                })
+            };
+            let pinned_release = input_url.as_deref().and_then(|url| {
+                release_stats
+                    .iter()
+                    .position(|x| x.pinned_input(url).is_some_and(is_published))
+            });
+            let pinned = match (self.releases, &latest_success, &latest) {
+                (true, _, _) => pinned_release
+                    .zip(input_url.as_deref())
+                    .and_then(|(x, url)| release_stats[x].pinned_input(url)),
+                (false, Some(latest_success), _) => {
+                    PinnedInput::from_inputs(&latest_success.inputs)
+                }
+                (false, None, Some(latest)) if success => PinnedInput::from_inputs(&latest.inputs),
+                (false, None, _) => None,
+            };
+            let pin = self.pin(pinned.as_ref());
+            if let Some(release) = pinned_release.and_then(|x| release_stats.get_mut(x)) {
+                release.pin.clone_from(&pin);
+            }
+            let not_found = stat.builds.first().is_some_and(|build| {
+                // as opposed to removed jobs, which still link to their builds
+                matches!(build.icon, StatusIcon::Warning) && build.build_url.is_none()
+            });
+            let suggestions = match not_found {
+                true => match self.suggest_jobs(stat.package) {
+                    Ok(search) => search.suggestions,
+                    Err(err) => {
+                        debug!("could not search for similar jobs: {err}");
+                        None
+                    }
+                },
+                false => None,
+            };
+            if let Some(build) = stat.builds.first_mut() {
+                build.suggestions.clone_from(&suggestions);
+                build.cache.clone_from(&cache);
+                build.reproduce.clone_from(&reproduce);
+                if !self.releases {
+                    build.pin.clone_from(&pin);
+                }
+            }
+            match self.releases {
+                true => self.print_rows(ReportKind::Releases, jobset, None, &release_stats)?,
+                false => self.print_rows(ReportKind::Packages, stat.package, None, &stat.builds)?,
+            }
+            if !self.format.is_table() {
+                // only printed for the table, but sets the exit status in every format
+                if !self.print_cache_status(&stat, cache.as_ref()) {
+                    status = false;
+                }
+                // not truncated by --short, to keep the shape of the output stable
                 if self.releases {
-                    let release_stats = match self.short {
-                        true => release_stats.first().cloned().into_iter().collect(),
-                        false => release_stats,
-                    };
                     all_releases.insert(jobset, release_stats);
                 } else {
-                    let build_stats = match self.short {
-                        true => first_stat.cloned().into_iter().collect(),
-                        false => stat.builds,
-                    };
-                    all_builds.insert(stat.package, build_stats);
+                    all_builds.insert(stat.package, stat.builds);
                 }
                 continue; // print later
             }
@@ -187,8 +251,16 @@ impl ResolvedArgs {
                 true => println!("{}", stat.format_table(self.short, &release_stats)),
                 false => println!("{}", stat.format_table(self.short, &stat.builds)),
             }
-            let url_stripped = stat.get_url().trim_end_matches("/all");
-            if !success {
+            if !self.print_cache_status(&stat, cache.as_ref()) {
+                status = false;
+            }
+            self.print_reproduce(reproduce.as_ref());
+            let first_stat = stat.builds.first();
+            if first_stat.is_some_and(|build| matches!(build.icon, StatusIcon::Queued)) {
+                print_queue_depth(stat.package);
+            }
+            print_suggestions(suggestions.as_ref());
+            if let Some(build_report) = &latest_success {
                 if self.short {
                     info!("latest build failed, check out: {url_dimmed}");
                 } else {
@@ -210,9 +282,6 @@ impl ResolvedArgs {
                     eprintln!();
                 }
                 info!("showing inputs for the latest success from a finished eval...");
-
-                let url = format!("{url_stripped}/latest-finished");
-                let build_report = BuildReport::from_url(&url).fetch_and_read()?;
                 for entry in &build_report.inputs {
                     if self.short {
                         if let (Some(name), Some(rev)) = (&entry.name, &entry.revision) {
@@ -224,11 +293,12 @@ impl ResolvedArgs {
                     }
                 }
             }
+            self.print_pin(pin.as_ref());
         }
-        if self.json {
+        if !self.format.is_table() {
             match self.releases {
-                true => println!("{}", serde_json::to_string_pretty(&all_releases)?),
-                false => println!("{}", serde_json::to_string_pretty(&all_builds)?),
+                true => self.print_report(ReportKind::Releases, &all_releases)?,
+                false => self.print_report(ReportKind::Packages, &all_builds)?,
             }
         }
         Ok(status)
@@ -0,0 +1,435 @@
+//! A module that lists the projects of a Hydra instance, from <https://hydra.nixos.org/>,
+//! and the jobsets of a project, e.g. from <https://hydra.nixos.org/project/nixpkgs>.
+//!
+//! The overview of the jobsets is provided by Hydra's JSON API, e.g.
+//! <https://hydra.nixos.org/api/jobsets?project=nixpkgs>, which lacks the
+//! enabled / hidden state of the jobsets, so it is complemented by the
+//! html page of the project.
+
+use colored::{ColoredString, Colorize};
+use indexmap::IndexMap;
+use log::warn;
+use schemars::JsonSchema;
+use scraper::Html;
+use serde::{Deserialize, Serialize};
+use serde_with::skip_serializing_none;
+
+use super::store_paths::query_url;
+use crate::{
+    constants, fetch_json,
+    formats::ReportKind,
+    formats::{cell, FlatRow},
+    structs::{bool_from_int, u64_from_int_or_str},
+    FetchHydraReport, ResolvedArgs, ShowHydraStatus, SoupFind, StatusIcon,
+};
+
+/// A single project, as provided by the JSON API at the root of Hydra
+#[skip_serializing_none]
+#[derive(Serialize, JsonSchema, Deserialize, Debug, Default, Clone)]
+pub(super) struct ProjectStatus {
+    name: String,
+    displayname: Option<String>,
+    description: Option<String>,
+    #[serde(default, deserialize_with = "bool_from_int")]
+    enabled: bool,
+    #[serde(default, deserialize_with = "bool_from_int")]
+    hidden: bool,
+    #[serde(default)]
+    jobsets: Vec<String>,
+}
+
+fn state(enabled: bool, hidden: bool) -> (StatusIcon, &'static str) {
+    match (enabled, hidden) {
+        (false, _) => (StatusIcon::Cancelled, "disabled"),
+        (true, true) => (StatusIcon::Succeeded, "hidden"),
+        (true, false) => (StatusIcon::Succeeded, "enabled"),
+    }
+}
+
+impl ShowHydraStatus for ProjectStatus {
+    fn format_as_vec(&self) -> Vec<ColoredString> {
+        let (icon, state) = state(self.enabled, self.hidden);
+        let icon = ColoredString::from(&icon);
+        let url = format!(
+            "{}/project/{}",
+            &*constants::HYDRA_CHECK_HOST_URL,
+            self.name
+        );
+        vec![
+            format!("{icon} {state}").into(),
+            self.name.as_str().into(),
+            format!("{} jobsets", self.jobsets.len()).into(),
+            self.displayname.clone().unwrap_or_default().into(),
+            url.dimmed(),
+        ]
+    }
+}
+
+impl FlatRow for ProjectStatus {
+    const COLUMNS: &'static [&'static str] = &[
+        "name",
+        "displayname",
+        "description",
+        "enabled",
+        "hidden",
+        "jobsets",
+    ];
+
+    fn values(&self) -> Vec<String> {
+        vec![
+            self.name.clone(),
+            cell(self.displayname.as_ref()),
+            cell(self.description.as_ref()),
+            self.enabled.to_string(),
+            self.hidden.to_string(),
+            self.jobsets.join(" "),
+        ]
+    }
+}
+
+/// A single jobset of a project, with the status of its latest evaluation
+#[skip_serializing_none]
+#[derive(Serialize, JsonSchema, Deserialize, Debug, Default, Clone)]
+struct JobsetStatus {
+    name: String,
+    project: String,
+    /// Unknown if the jobset is missing from the html page of the project
+    enabled: Option<bool>,
+    hidden: Option<bool>,
+    lastcheckedtime: Option<i64>,
+    #[serde(default, deserialize_with = "u64_from_int_or_str")]
+    nrscheduled: u64,
+    #[serde(default, deserialize_with = "u64_from_int_or_str")]
+    nrsucceeded: u64,
+    #[serde(default, deserialize_with = "u64_from_int_or_str")]
+    nrfailed: u64,
+    #[serde(default, deserialize_with = "u64_from_int_or_str")]
+    nrtotal: u64,
+    #[serde(default, deserialize_with = "bool_from_int")]
+    haserrormsg: bool,
+    fetcherrormsg: Option<String>,
+}
+
+impl ShowHydraStatus for JobsetStatus {
+    fn format_as_vec(&self) -> Vec<ColoredString> {
+        let enabled = self.enabled.unwrap_or(true);
+        let (icon, state) = state(enabled, self.hidden.unwrap_or_default());
+        let has_error = self.haserrormsg || self.fetcherrormsg.is_some();
+        let icon = match icon {
+            _ if has_error => StatusIcon::Warning,
+            StatusIcon::Succeeded if self.nrscheduled > 0 => StatusIcon::Queued,
+            StatusIcon::Succeeded if self.nrfailed > 0 => StatusIcon::Failed,
+            icon => icon,
+        };
+        let icon = ColoredString::from(&icon);
+        // when the inputs were last checked, which only leads to a new
+        // evaluation if they changed
+        let last_checked = self
+            .lastcheckedtime
+            .and_then(|x| chrono::DateTime::from_timestamp(x, 0))
+            .map_or("never checked".into(), |x| {
+                format!("checked {}", x.format("%Y-%m-%d %H:%M"))
+            });
+        let url = format!(
+            "{}/jobset/{}/{}",
+            &*constants::HYDRA_CHECK_HOST_URL,
+            self.project,
+            self.name
+        );
+        vec![
+            format!("{icon} {state}").into(),
+            self.name.as_str().into(),
+            match has_error {
+                true => format!("{last_checked} (eval error)").yellow(),
+                false => last_checked.into(),
+            },
+            format!("{} failed", self.nrfailed).into(),
+            format!("{} queued", self.nrscheduled).into(),
+            url.dimmed(),
+        ]
+    }
+}
+
+impl FlatRow for JobsetStatus {
+    const COLUMNS: &'static [&'static str] = &[
+        "project",
+        "name",
+        "enabled",
+        "hidden",
+        "lastcheckedtime",
+        "scheduled",
+        "succeeded",
+        "failed",
+        "total",
+        "fetcherrormsg",
+    ];
+
+    fn values(&self) -> Vec<String> {
+        vec![
+            self.project.clone(),
+            self.name.clone(),
+            cell(self.enabled),
+            cell(self.hidden),
+            cell(self.lastcheckedtime),
+            self.nrscheduled.to_string(),
+            self.nrsucceeded.to_string(),
+            self.nrfailed.to_string(),
+            self.nrtotal.to_string(),
+            cell(self.fetcherrormsg.as_ref()),
+        ]
+    }
+}
+
+/// Container for the projects of the Hydra instance
+#[skip_serializing_none]
+#[derive(Serialize, JsonSchema, Clone)]
+struct ProjectsReport {
+    url: String,
+    error: Option<String>,
+    projects: Vec<ProjectStatus>,
+}
+
+impl FetchHydraReport for ProjectsReport {
+    fn get_url(&self) -> &str {
+        &self.url
+    }
+
+    fn finish_with_error(self, status: String) -> Self {
+        Self {
+            error: Some(status),
+            ..self
+        }
+    }
+}
+
+impl ProjectsReport {
+    fn new() -> Self {
+        Self {
+            url: format!("{}/", &*constants::HYDRA_CHECK_HOST_URL),
+            error: None,
+            projects: vec![],
+        }
+    }
+
+    fn fetch_and_read(self) -> anyhow::Result<Self> {
+        let projects = fetch_json(&self.url)?;
+        Ok(Self { projects, ..self })
+    }
+}
+
+/// Container for the jobsets of a single project
+#[skip_serializing_none]
+#[derive(Serialize, JsonSchema, Clone)]
+pub(super) struct JobsetsReport<'a> {
+    project: &'a str,
+    url: String,
+    error: Option<String>,
+    jobsets: Vec<JobsetStatus>,
+}
+
+impl FetchHydraReport for JobsetsReport<'_> {
+    fn get_url(&self) -> &str {
+        &self.url
+    }
+
+    fn finish_with_error(self, status: String) -> Self {
+        Self {
+            error: Some(status),
+            ..self
+        }
+    }
+}
+
+impl<'a> JobsetsReport<'a> {
+    fn from_project(project: &'a str) -> Self {
+        let url = format!("{}/project/{project}", &*constants::HYDRA_CHECK_HOST_URL);
+        Self {
+            project,
+            url,
+            error: None,
+            jobsets: vec![],
+        }
+    }
+
+    /// Url of the overview of the jobsets in Hydra's JSON API.
+    fn api_url(&self) -> String {
+        query_url("/api/jobsets", &[("project", self.project)])
+    }
+
+    /// Reads the enabled / hidden state of the jobsets from the classes
+    /// of the rows in the jobset table of the project page.
+    fn read_states(doc: &Html) -> IndexMap<String, (bool, bool)> {
+        let mut states = IndexMap::new();
+        for row in doc.find_all("tr") {
+            let Some(name) = row
+                .find("a[href*='/jobset/']")
+                .ok()
+                .and_then(|x| x.attr("href"))
+                .and_then(|x| x.trim_end_matches('/').rsplit('/').next())
+            else {
+                continue;
+            };
+            let html = row.html();
+            let enabled = !html.contains("disabled-jobset");
+            let hidden = html.contains("hidden-jobset");
+            states.insert(name.to_string(), (enabled, hidden));
+        }
+        states
+    }
+
+    fn fetch_and_read(self) -> anyhow::Result<Self> {
+        let jobset_states = Self::read_states(&self.fetch_document()?);
+        match fetch_json(&self.api_url()) {
+            Ok(jobsets) => Ok(self.read_jobsets(jobsets, &jobset_states)),
+            Err(err) => {
+                let status = format!("could not list the jobsets of '{}': {err}", self.project);
+                Ok(self.finish_with_error(status))
+            }
+        }
+    }
+
+    /// Completes the jobsets from the JSON API with their states
+    /// from the html page, which are unknown for missing jobsets.
+    fn read_jobsets(
+        self,
+        mut jobsets: Vec<JobsetStatus>,
+        states: &IndexMap<String, (bool, bool)>,
+    ) -> Self {
+        for jobset in &mut jobsets {
+            if let Some((enabled, hidden)) = states.get(&jobset.name) {
+                jobset.enabled = Some(*enabled);
+                jobset.hidden = Some(*hidden);
+            }
+        }
+        Self { jobsets, ..self }
+    }
+}
+
+impl ResolvedArgs {
+    pub(crate) fn fetch_and_print_projects(&self) -> anyhow::Result<bool> {
+        let stat = ProjectsReport::new();
+        if self.url {
+            println!("{}", stat.get_url());
+            return Ok(true);
+        }
+        if self.format.is_table() {
+            println!("Projects {}", format!("@ {}", stat.get_url()).dimmed());
+        }
+        let stat = stat.fetch_and_read()?;
+        if !self.format.is_table() {
+            self.print_flat_rows(ReportKind::Projects, None, None, &stat.projects)?;
+            self.print_report(ReportKind::Projects, &stat.projects)?;
+            return Ok(true);
+        }
+        println!("{}", stat.format_table(false, &stat.projects));
+        Ok(true)
+    }
+
+    pub(crate) fn fetch_and_print_jobsets(&self, project: &str) -> anyhow::Result<bool> {
+        let stat = JobsetsReport::from_project(project);
+        if self.url {
+            println!("{}", stat.get_url());
+            return Ok(true);
+        }
+        if self.format.is_table() {
+            println!(
+                "Jobsets of project {} {}",
+                project.bold(),
+                format!("@ {}", stat.get_url()).dimmed()
+            );
+        }
+        let stat = stat.fetch_and_read()?;
+        if !self.format.is_table() {
+            let mut indexmap = IndexMap::new();
+            indexmap.insert(project, &stat);
+            self.print_flat_rows(ReportKind::Jobsets, None, None, &stat.jobsets)?;
+            self.print_report(ReportKind::Jobsets, &indexmap)?;
+            return Ok(stat.error.is_none());
+        }
+        if let Some(error) = &stat.error {
+            warn!("{error}");
+            return Ok(false);
+        }
+        println!("{}", stat.format_table(false, &stat.jobsets));
+        Ok(true)
+    }
+}
+
+#[test]
+fn read_jobsets() {
+    let json = r#"[
+        {
+            "project": "nixpkgs", "name": "trunk", "nrscheduled": "3", "nrsucceeded": 100,
+            "nrfailed": 2, "nrtotal": 105, "lastcheckedtime": 1742511337,
+            "starttime": null, "checkinterval": 14400, "triggertime": null,
+            "fetcherrormsg": null, "errortime": null, "haserrormsg": false
+        },
+        {
+            "project": "nixpkgs", "name": "staging-old", "nrscheduled": 0,
+            "nrsucceeded": 0, "nrfailed": 0, "nrtotal": 0, "lastcheckedtime": null,
+            "haserrormsg": true
+        },
+        {
+            "project": "nixpkgs", "name": "staging", "nrscheduled": 0, "nrsucceeded": 90,
+            "nrfailed": 10, "nrtotal": 100, "lastcheckedtime": 1742511337,
+            "haserrormsg": false
+        }
+    ]"#;
+    let jobsets: Vec<JobsetStatus> = serde_json::from_str(json).unwrap();
+    assert_eq!(jobsets[0].nrscheduled, 3);
+    assert!(jobsets[1].haserrormsg);
+    let html = r#"
+        <table class="table"><tbody>
+          <tr class="jobset">
+            <td></td><td><a href="https://hydra.nixos.org/jobset/nixpkgs/trunk">nixpkgs:trunk</a></td>
+          </tr>
+          <tr class="jobset disabled-jobset hidden-jobset">
+            <td></td><td><a href="https://hydra.nixos.org/jobset/nixpkgs/staging-old">nixpkgs:staging-old</a></td>
+          </tr>
+        </tbody></table>
+    "#;
+    let states = JobsetsReport::read_states(&Html::parse_document(html));
+    assert_eq!(states["trunk"], (true, false));
+    assert_eq!(states["staging-old"], (false, true));
+    let stat = JobsetsReport::from_project("nixpkgs").read_jobsets(jobsets, &states);
+    assert!(JobsetsReport::from_project("nix&os")
+        .api_url()
+        .ends_with("/api/jobsets?project=nix%26os"));
+    let states: Vec<_> = stat.jobsets.iter().map(|x| (x.enabled, x.hidden)).collect();
+    // the state of a jobset missing from the html page is unknown
+    assert_eq!(
+        states,
+        [
+            (Some(true), Some(false)),
+            (Some(false), Some(true)),
+            (None, None)
+        ]
+    );
+    // queued builds take precedence over failed ones, and eval errors over both
+    let cells: Vec<_> = stat
+        .jobsets
+        .iter()
+        .map(|jobset| {
+            let row = jobset.format_as_vec();
+            (row[0].to_string(), row[2].to_string())
+        })
+        .collect();
+    let state = |icon: &StatusIcon, state: &str| format!("{} {state}", ColoredString::from(icon));
+    assert_eq!(
+        cells,
+        [
+            (
+                state(&StatusIcon::Queued, "enabled"),
+                "checked 2025-03-20 22:55".into()
+            ),
+            (
+                state(&StatusIcon::Warning, "disabled"),
+                "never checked (eval error)".yellow().to_string()
+            ),
+            (
+                state(&StatusIcon::Failed, "enabled"),
+                "checked 2025-03-20 22:55".into()
+            ),
+        ]
+    );
+}
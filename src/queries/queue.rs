@@ -0,0 +1,513 @@
+//! A module that summarizes the build queue of a Hydra instance, from
+//! <https://hydra.nixos.org/queue-summary>, and the status of its build
+//! machines, from the JSON at <https://hydra.nixos.org/queue-runner-status>.
+
+use colored::{ColoredString, Colorize};
+use indexmap::IndexMap;
+use log::{debug, info, warn};
+use schemars::JsonSchema;
+use scraper::{ElementRef, Html};
+use serde::{Deserialize, Serialize};
+use serde_with::skip_serializing_none;
+
+use super::store_paths::find_table_after;
+use crate::{
+    constants, fetch_json,
+    formats::ReportKind,
+    formats::{cell, FlatRow},
+    structs::{bool_from_int, u64_from_int_or_str},
+    FetchHydraReport, ResolvedArgs, ShowHydraStatus, SoupFind, StatusIcon,
+};
+
+/// Number of queued builds of a single jobset or system
+#[skip_serializing_none]
+#[derive(Serialize, JsonSchema, Debug, Clone)]
+struct QueueEntry {
+    name: String,
+    queued: u64,
+    oldest: Option<String>,
+    newest: Option<String>,
+}
+
+impl ShowHydraStatus for QueueEntry {
+    fn format_as_vec(&self) -> Vec<ColoredString> {
+        let icon = ColoredString::from(&StatusIcon::Queued);
+        let mut row = vec![
+            format!("{icon} {}", self.queued).into(),
+            self.name.as_str().into(),
+        ];
+        if let Some(oldest) = &self.oldest {
+            row.push(format!("oldest: {oldest}").dimmed());
+        }
+        if let Some(newest) = &self.newest {
+            row.push(format!("newest: {newest}").dimmed());
+        }
+        row
+    }
+}
+
+impl FlatRow for QueueEntry {
+    const COLUMNS: &'static [&'static str] = &["name", "queued", "oldest", "newest"];
+
+    fn values(&self) -> Vec<String> {
+        vec![
+            self.name.clone(),
+            self.queued.to_string(),
+            cell(self.oldest.as_ref()),
+            cell(self.newest.as_ref()),
+        ]
+    }
+}
+
+impl QueueEntry {
+    fn from_tbody(tbody: ElementRef<'_>) -> anyhow::Result<Vec<Self>> {
+        let mut entries = Vec::new();
+        for row in tbody.find_all("tr") {
+            let columns: Vec<String> = row
+                .find_all("td")
+                .into_iter()
+                .map(|x| {
+                    let text: String = x.text().collect();
+                    let text: Vec<&str> = text.split_whitespace().collect();
+                    text.join(" ")
+                })
+                .collect();
+            let [name, queued, dates @ ..] = columns.as_slice() else {
+                continue;
+            };
+            let date = |idx: usize| dates.get(idx).filter(|x| !x.is_empty()).cloned();
+            entries.push(Self {
+                name: name.replacen(':', "/", 1),
+                queued: queued.parse()?,
+                oldest: date(0),
+                newest: date(1),
+            });
+        }
+        Ok(entries)
+    }
+}
+
+/// Container for the queue summary, per jobset and per system
+#[skip_serializing_none]
+#[derive(Serialize, JsonSchema, Clone)]
+pub(super) struct QueueReport {
+    url: String,
+    error: Option<String>,
+    jobsets: Vec<QueueEntry>,
+    systems: Vec<QueueEntry>,
+}
+
+impl FetchHydraReport for QueueReport {
+    fn get_url(&self) -> &str {
+        &self.url
+    }
+
+    fn finish_with_error(self, status: String) -> Self {
+        Self {
+            error: Some(status),
+            ..self
+        }
+    }
+}
+
+impl QueueReport {
+    fn new() -> Self {
+        Self {
+            url: format!("{}/queue-summary", &*constants::HYDRA_CHECK_HOST_URL),
+            error: None,
+            jobsets: vec![],
+            systems: vec![],
+        }
+    }
+
+    fn read_summary(self, doc: &Html) -> Self {
+        let read = |text: &str| match find_table_after(doc, text) {
+            Some(table) => match table.find("tbody") {
+                Ok(tbody) => QueueEntry::from_tbody(tbody),
+                Err(_) => Ok(vec![]),
+            },
+            // there is no table if the queue is empty
+            None => Ok(vec![]),
+        };
+        match (read("per jobset"), read("per system")) {
+            (Ok(jobsets), Ok(systems)) => Self {
+                jobsets,
+                systems,
+                ..self
+            },
+            (Err(err), _) | (_, Err(err)) => {
+                self.finish_with_error(format!("could not read the queue summary: {err}"))
+            }
+        }
+    }
+
+    fn fetch_and_read(self) -> anyhow::Result<Self> {
+        let doc = self.fetch_document()?;
+        Ok(self.read_summary(&doc))
+    }
+
+    /// Keeps the first `limit` jobsets, as there are only a few systems.
+    fn truncate(mut self, limit: Option<usize>) -> Self {
+        if let Some(limit) = limit {
+            self.jobsets.truncate(limit);
+        }
+        self
+    }
+
+    /// Finds how many builds are queued for the system of a job,
+    /// or `None` if the system of the job is unknown.
+    fn queued_for_job(&self, job: &str) -> Option<(&'static str, u64)> {
+        let system = system_of_job(job)?;
+        let entry = self.systems.iter().find(|x| x.name == system);
+        Some((system, entry.map_or(0, |x| x.queued)))
+    }
+}
+
+/// Status of a single build machine, as reported by the queue runner
+#[skip_serializing_none]
+#[derive(Serialize, JsonSchema, Deserialize, Debug, Default, Clone)]
+#[serde(rename_all = "camelCase")]
+struct MachineStatus {
+    #[serde(default, deserialize_with = "bool_from_int")]
+    enabled: bool,
+    #[serde(default)]
+    system_types: Vec<String>,
+    #[serde(default, deserialize_with = "u64_from_int_or_str")]
+    current_jobs: u64,
+    max_jobs: Option<u64>,
+    #[serde(default, deserialize_with = "u64_from_int_or_str")]
+    nr_steps_done: u64,
+    avg_step_build_time: Option<f64>,
+    #[serde(default, deserialize_with = "u64_from_int_or_str")]
+    consecutive_failures: u64,
+    /// Unix timestamp, `0` if the machine is not disabled after failures
+    #[serde(default)]
+    disabled_until: i64,
+}
+
+/// Overview of the queue runner, keyed by machine and by system
+#[skip_serializing_none]
+#[derive(Serialize, JsonSchema, Deserialize, Debug, Default, Clone)]
+#[serde(rename_all = "camelCase")]
+pub(super) struct QueueRunnerStatus {
+    status: Option<String>,
+    nr_queued_builds: Option<u64>,
+    nr_active_steps: Option<u64>,
+    #[serde(default)]
+    machines: IndexMap<String, MachineStatus>,
+    #[serde(default)]
+    machine_types: IndexMap<String, MachineType>,
+}
+
+/// Number of runnable (queued) and running build steps of a system
+#[derive(Serialize, JsonSchema, Deserialize, Debug, Default, Clone)]
+struct MachineType {
+    #[serde(default)]
+    runnable: u64,
+    #[serde(default)]
+    running: u64,
+}
+
+/// Container for the status of the queue runner and its build machines
+#[derive(Serialize, JsonSchema, Clone)]
+struct MachinesReport {
+    url: String,
+    status: QueueRunnerStatus,
+}
+
+impl FetchHydraReport for MachinesReport {
+    fn get_url(&self) -> &str {
+        &self.url
+    }
+
+    fn finish_with_error(self, status: String) -> Self {
+        Self {
+            status: QueueRunnerStatus {
+                status: Some(status),
+                ..self.status
+            },
+            ..self
+        }
+    }
+}
+
+impl MachinesReport {
+    fn new() -> Self {
+        Self {
+            url: format!("{}/queue-runner-status", &*constants::HYDRA_CHECK_HOST_URL),
+            status: QueueRunnerStatus::default(),
+        }
+    }
+
+    fn fetch_and_read(self) -> anyhow::Result<Self> {
+        let status = fetch_json(&self.url)?;
+        Ok(Self { status, ..self })
+    }
+}
+
+/// A [`MachineStatus`] with its name, for printing
+#[derive(Serialize)]
+struct Machine<'a>(&'a str, &'a MachineStatus);
+
+impl ShowHydraStatus for Machine<'_> {
+    fn format_as_vec(&self) -> Vec<ColoredString> {
+        let Machine(name, machine) = self;
+        let disabled = machine.disabled_until > chrono::Utc::now().timestamp();
+        let (icon, state) = match (machine.enabled, disabled) {
+            (false, _) => (StatusIcon::Cancelled, "disabled"),
+            (true, true) => (StatusIcon::Warning, "failing"),
+            (true, false) if machine.current_jobs == 0 => (StatusIcon::Succeeded, "idle"),
+            (true, false) => (StatusIcon::Queued, "busy"),
+        };
+        let icon = ColoredString::from(&icon);
+        let load = match machine.max_jobs {
+            Some(max_jobs) => format!("{}/{max_jobs} steps", machine.current_jobs),
+            None => format!("{} steps", machine.current_jobs),
+        };
+        let avg_time = machine
+            .avg_step_build_time
+            .map(|x| format!("avg {x:.0}s"))
+            .unwrap_or_default();
+        vec![
+            format!("{icon} {state}").into(),
+            (*name).into(),
+            machine.system_types.join(", ").into(),
+            load.into(),
+            format!("{} done", machine.nr_steps_done).into(),
+            avg_time.dimmed(),
+        ]
+    }
+}
+
+impl FlatRow for Machine<'_> {
+    const COLUMNS: &'static [&'static str] = &[
+        "name",
+        "enabled",
+        "systems",
+        "current_jobs",
+        "max_jobs",
+        "steps_done",
+        "avg_step_build_time",
+        "consecutive_failures",
+        "disabled_until",
+    ];
+
+    fn values(&self) -> Vec<String> {
+        let Machine(name, machine) = self;
+        vec![
+            (*name).into(),
+            machine.enabled.to_string(),
+            machine.system_types.join(" "),
+            machine.current_jobs.to_string(),
+            cell(machine.max_jobs),
+            machine.nr_steps_done.to_string(),
+            cell(machine.avg_step_build_time),
+            machine.consecutive_failures.to_string(),
+            machine.disabled_until.to_string(),
+        ]
+    }
+}
+
+/// Extracts the system (architecture) from a job name such as `hello.x86_64-linux`.
+fn system_of_job(job: &str) -> Option<&'static str> {
+    constants::KNOWN_ARCHITECTURES
+        .into_iter()
+        .find(|arch| job.ends_with(&format!(".{arch}")))
+}
+
+impl ResolvedArgs {
+    pub(crate) fn fetch_and_print_queue(&self) -> anyhow::Result<bool> {
+        let stat = QueueReport::new();
+        if self.url {
+            println!("{}", stat.get_url());
+            return Ok(true);
+        }
+        if self.format.is_table() {
+            println!("Queue summary {}", format!("@ {}", stat.get_url()).dimmed());
+        }
+        let stat = stat.fetch_and_read()?.truncate(self.pagination.limit);
+        if !self.format.is_table() {
+            self.print_flat_rows(ReportKind::Queue, None, Some("systems"), &stat.systems)?;
+            self.print_flat_rows(ReportKind::Queue, None, Some("jobsets"), &stat.jobsets)?;
+            self.print_report(ReportKind::Queue, &stat)?;
+            return Ok(stat.error.is_none());
+        }
+        if let Some(error) = &stat.error {
+            warn!("{error}");
+            return Ok(false);
+        }
+        if stat.jobsets.is_empty() && stat.systems.is_empty() {
+            info!("there are no pending builds");
+            return Ok(true);
+        }
+        println!("\n{}", "Per system:".bold());
+        println!("{}", stat.format_table(false, &stat.systems));
+        println!("\n{}", "Per jobset:".bold());
+        println!("{}", stat.format_table(false, &stat.jobsets));
+        Ok(true)
+    }
+
+    pub(crate) fn fetch_and_print_machines(&self) -> anyhow::Result<bool> {
+        let stat = MachinesReport::new();
+        if self.url {
+            println!("{}", stat.get_url());
+            return Ok(true);
+        }
+        if self.format.is_table() {
+            println!(
+                "Build machines {}",
+                format!("@ {}", stat.get_url()).dimmed()
+            );
+        }
+        let stat = stat.fetch_and_read()?;
+        let status = &stat.status;
+        let machines: Vec<_> = status
+            .machines
+            .iter()
+            .map(|(name, machine)| Machine(name, machine))
+            .collect();
+        if !self.format.is_table() {
+            self.print_flat_rows(ReportKind::Machines, None, None, &machines)?;
+            self.print_report(ReportKind::Machines, status)?;
+            return Ok(true);
+        }
+        println!("{}", stat.format_table(false, &machines));
+        if let (Some(queued), Some(active)) = (status.nr_queued_builds, status.nr_active_steps) {
+            println!(
+                "\n{}: {queued} queued builds, {active} active steps",
+                "queue runner".bold()
+            );
+        }
+        for (system, machine_type) in &status.machine_types {
+            println!(
+                "{}: {} runnable, {} running",
+                system.bold(),
+                machine_type.runnable,
+                machine_type.running
+            );
+        }
+        Ok(true)
+    }
+}
+
+/// Notes how many builds are queued for the system of a queued job,
+/// to tell a stuck build from a busy Hydra.
+pub(super) fn print_queue_depth(job: &str) {
+    if system_of_job(job).is_none() {
+        return;
+    }
+    let stat = match QueueReport::new().fetch_and_read() {
+        Ok(QueueReport {
+            error: Some(err), ..
+        }) => {
+            debug!("{err}");
+            return;
+        }
+        Ok(stat) => stat,
+        Err(err) => {
+            debug!("could not fetch the queue summary: {err}");
+            return;
+        }
+    };
+    match stat.queued_for_job(job) {
+        Some((system, 0)) => warn!("no builds are queued for {system}, the build may be stuck"),
+        Some((system, queued)) => info!(
+            "{queued} builds are queued for {system} on Hydra, see {}",
+            stat.get_url()
+        ),
+        None => {}
+    }
+}
+
+#[test]
+fn read_queue_summary() {
+    let html = r#"
+        <h2>Queue summary per jobset</h2>
+        <table class="table"><tbody>
+          <tr>
+            <td><a href="https://hydra.nixos.org/jobset/nixpkgs/staging-next">nixpkgs:staging-next</a></td>
+            <td>1234</td>
+            <td><time datetime="2025-03-20T10:00:00Z">2 days ago</time></td>
+            <td><time datetime="2025-03-22T10:00:00Z">1 hour ago</time></td>
+          </tr>
+        </tbody></table>
+        <h2>Queue summary per system type</h2>
+        <table class="table"><tbody>
+          <tr><td><tt>x86_64-linux</tt></td><td>1000</td></tr>
+          <tr><td><tt>aarch64-darwin</tt></td><td>234</td></tr>
+        </tbody></table>
+    "#;
+    let stat = QueueReport::new().read_summary(&Html::parse_document(html));
+    assert!(stat.error.is_none());
+    assert_eq!(stat.jobsets[0].name, "nixpkgs/staging-next");
+    assert_eq!(stat.jobsets[0].oldest.as_deref(), Some("2 days ago"));
+    assert_eq!(stat.systems.len(), 2);
+    assert_eq!(stat.systems[1].queued, 234);
+    assert_eq!(
+        system_of_job("nixpkgs.hello.aarch64-darwin"),
+        Some("aarch64-darwin")
+    );
+    // a queued build is likely stuck if nothing is queued for its system
+    assert_eq!(
+        stat.queued_for_job("nixpkgs.hello.aarch64-darwin"),
+        Some(("aarch64-darwin", 234))
+    );
+    assert_eq!(
+        stat.queued_for_job("nixos.tests.firefox.x86_64-darwin"),
+        Some(("x86_64-darwin", 0))
+    );
+    assert_eq!(stat.queued_for_job("nixpkgs.hello"), None);
+    // --limit only applies to the jobsets
+    let stat = stat.truncate(Some(0));
+    assert!(stat.jobsets.is_empty());
+    assert_eq!(stat.systems.len(), 2);
+    let html = html.replace("1234", "many");
+    let stat = QueueReport::new().read_summary(&Html::parse_document(&html));
+    assert_eq!(
+        stat.error.as_deref(),
+        Some("could not read the queue summary: invalid digit found in string")
+    );
+}
+
+#[test]
+fn read_machines() {
+    let json = r#"{
+        "status": "up", "nrQueuedBuilds": 1234, "nrActiveSteps": 2,
+        "machines": {
+            "root@big-parallel": {
+                "enabled": 1, "systemTypes": ["x86_64-linux"], "currentJobs": 2, "maxJobs": 8,
+                "nrStepsDone": "1000", "avgStepBuildTime": 312.4, "consecutiveFailures": 0,
+                "disabledUntil": 0
+            },
+            "root@idle": {
+                "enabled": true, "systemTypes": ["aarch64-linux"], "currentJobs": 0,
+                "nrStepsDone": 10
+            },
+            "root@flaky": {
+                "enabled": 1, "systemTypes": ["x86_64-darwin", "aarch64-darwin"],
+                "currentJobs": 0, "consecutiveFailures": 3, "disabledUntil": 4102444800
+            },
+            "root@retired": { "enabled": 0, "systemTypes": ["i686-linux"] }
+        },
+        "machineTypes": { "x86_64-linux": { "runnable": 1000, "running": 2 } }
+    }"#;
+    let status: QueueRunnerStatus = serde_json::from_str(json).unwrap();
+    assert_eq!(status.machine_types["x86_64-linux"].runnable, 1000);
+    // a machine that is disabled after failures is failing until then
+    let rows: Vec<_> = status
+        .machines
+        .iter()
+        .map(|(name, machine)| Machine(name, machine).format_as_vec())
+        .map(|row| (row[0].to_string(), row[3].to_string()))
+        .collect();
+    let state = |icon: &StatusIcon, state: &str| format!("{} {state}", ColoredString::from(icon));
+    assert_eq!(
+        rows,
+        [
+            (state(&StatusIcon::Queued, "busy"), "2/8 steps".into()),
+            (state(&StatusIcon::Succeeded, "idle"), "0 steps".into()),
+            (state(&StatusIcon::Warning, "failing"), "0 steps".into()),
+            (state(&StatusIcon::Cancelled, "disabled"), "0 steps".into()),
+        ]
+    );
+}
@@ -0,0 +1,72 @@
+//! A module that describes the versioned envelope of the `--json` output
+//! of each kind of query with a JSON Schema, see `--json-schema`.
+
+use indexmap::IndexMap;
+use schemars::{schema_for, Schema};
+use serde_json::json;
+
+use super::{
+    builds::BuildReport, evals::EvalReport, landed::LandedReport, projects::JobsetsReport,
+    projects::ProjectStatus, queue::QueueReport, queue::QueueRunnerStatus, search::SearchReport,
+    store_paths::StorePathReport, system::SystemReport,
+};
+use crate::{
+    formats::{JsonOutput, ReportKind},
+    structs::{JobsetConfig, ReleaseStatus},
+    BuildStatus, EvalStatus,
+};
+
+impl ReportKind {
+    /// Generates the JSON Schema of the envelope with this kind of `data`.
+    fn json_schema(self) -> Schema {
+        type Keyed<T> = IndexMap<String, T>;
+        let mut schema = match self {
+            Self::Jobset => schema_for!(JsonOutput<Keyed<Vec<EvalStatus>>>),
+            Self::Packages => schema_for!(JsonOutput<Keyed<Vec<BuildStatus>>>),
+            Self::Releases => schema_for!(JsonOutput<Keyed<Vec<ReleaseStatus>>>),
+            Self::Evals => schema_for!(JsonOutput<Keyed<EvalReport<'static>>>),
+            Self::Builds => schema_for!(JsonOutput<IndexMap<u64, BuildReport>>),
+            Self::StorePaths => schema_for!(JsonOutput<Keyed<StorePathReport<'static>>>),
+            Self::Search => schema_for!(JsonOutput<Keyed<SearchReport<'static>>>),
+            Self::Projects => schema_for!(JsonOutput<Vec<ProjectStatus>>),
+            Self::Jobsets => schema_for!(JsonOutput<Keyed<JobsetsReport<'static>>>),
+            Self::JobsetConfig => schema_for!(JsonOutput<Keyed<JobsetConfig>>),
+            Self::Queue => schema_for!(JsonOutput<QueueReport>),
+            Self::Machines => schema_for!(JsonOutput<QueueRunnerStatus>),
+            Self::System => schema_for!(JsonOutput<SystemReport<'static>>),
+            Self::Landed => schema_for!(JsonOutput<Keyed<Keyed<LandedReport<'static>>>>),
+        };
+        // narrow down the `kind` from all kinds to this one
+        if let Some(kind) = schema.pointer_mut("/properties/kind") {
+            *kind = json!({ "const": self });
+        }
+        schema
+    }
+}
+
+/// Prints the JSON Schemas of every kind of `--json` output, keyed by kind.
+pub(crate) fn print_json_schemas() -> anyhow::Result<()> {
+    let schemas: IndexMap<_, _> = ReportKind::ALL
+        .into_iter()
+        .map(|kind| (kind, kind.json_schema()))
+        .collect();
+    println!("{}", serde_json::to_string_pretty(&schemas)?);
+    Ok(())
+}
+
+#[test]
+fn json_envelope() {
+    let schema = ReportKind::Packages.json_schema();
+    assert_eq!(
+        schema.pointer("/properties/kind"),
+        Some(&json!({ "const": "packages" }))
+    );
+    assert_eq!(
+        schema.pointer("/required"),
+        Some(&json!(["schema_version", "kind", "data"]))
+    );
+    let icons = schema.pointer("/$defs/StatusIcon/enum").unwrap();
+    assert_eq!(icons[0], "succeeded");
+    // all kinds can be generated, including the ones with lifetimes
+    assert_eq!(ReportKind::ALL.map(ReportKind::json_schema).len(), 14);
+}
@@ -0,0 +1,350 @@
+//! A module that searches the projects, jobsets and jobs of a Hydra instance,
+//! from urls such as <https://hydra.nixos.org/search?query=hello>.
+//!
+//! The search results are also used to suggest the closest job names when
+//! a package lookup fails, e.g. for typos such as `pyhton312Packages.requests`.
+
+use colored::{ColoredString, Colorize};
+use indexmap::IndexMap;
+use log::{debug, warn};
+use schemars::JsonSchema;
+use scraper::{ElementRef, Html};
+use serde::Serialize;
+use serde_with::skip_serializing_none;
+
+use super::store_paths::{find_table_after, search_url};
+use crate::{
+    constants,
+    formats::ReportKind,
+    formats::{cell, FlatRow},
+    FetchHydraReport, ResolvedArgs, ShowHydraStatus, SoupFind, StatusIcon,
+};
+
+/// Hydra caps the number of matching jobs in the search results at 50.
+const MAX_SEARCH_LIMIT: usize = 50;
+
+/// Maximum number of "did you mean" suggestions for a failed lookup.
+const MAX_SUGGESTIONS: usize = 5;
+
+/// Minimum similarity (between 0 and 1) of a suggested job name.
+const MIN_SIMILARITY: f64 = 0.7;
+
+/// A single project, jobset or job matching the search term
+#[skip_serializing_none]
+#[derive(Serialize, JsonSchema, Debug, Default, Clone)]
+pub(super) struct SearchResult {
+    #[serde(skip)]
+    kind: &'static str,
+    /// Name in the format of Hydra's search results, e.g. `nixpkgs:unstable`
+    pub(super) name: String,
+    description: Option<String>,
+    url: Option<String>,
+}
+
+impl ShowHydraStatus for SearchResult {
+    fn format_as_vec(&self) -> Vec<ColoredString> {
+        vec![
+            self.kind.bold(),
+            self.name.as_str().into(),
+            self.description.clone().unwrap_or_default().into(),
+            self.url.clone().unwrap_or_default().dimmed(),
+        ]
+    }
+}
+
+impl FlatRow for SearchResult {
+    const COLUMNS: &'static [&'static str] = &["name", "description", "url"];
+
+    fn values(&self) -> Vec<String> {
+        vec![
+            self.name.clone(),
+            cell(self.description.as_ref()),
+            cell(self.url.as_ref()),
+        ]
+    }
+}
+
+impl SearchResult {
+    fn from_tbody(tbody: ElementRef<'_>, kind: &'static str) -> Vec<Self> {
+        let mut results = Vec::new();
+        for row in tbody.find_all("tr") {
+            let columns = row.find_all("td");
+            let Some(name) = columns.first() else {
+                continue;
+            };
+            let name: String = name.text().collect();
+            let description = columns
+                .get(1)
+                .map(|x| x.text().collect::<String>().trim().to_string())
+                .filter(|x| !x.is_empty());
+            let url = row.find("a").ok().and_then(|x| x.attr("href"));
+            results.push(Self {
+                kind,
+                name: name.trim().into(),
+                description,
+                url: url.map(str::to_string),
+            });
+        }
+        results
+    }
+}
+
+/// Container for the search results of a single term
+#[skip_serializing_none]
+#[derive(Serialize, JsonSchema, Clone)]
+pub(super) struct SearchReport<'a> {
+    term: &'a str,
+    url: String,
+    error: Option<String>,
+    projects: Vec<SearchResult>,
+    jobsets: Vec<SearchResult>,
+    pub(super) jobs: Vec<SearchResult>,
+    /// Jobs of the jobset with names similar to the term,
+    /// if the term is a job that could not be found
+    pub(super) suggestions: Option<Vec<String>>,
+}
+
+impl FetchHydraReport for SearchReport<'_> {
+    fn get_url(&self) -> &str {
+        &self.url
+    }
+
+    fn finish_with_error(self, status: String) -> Self {
+        Self {
+            error: Some(status),
+            ..self
+        }
+    }
+}
+
+impl<'a> SearchReport<'a> {
+    pub(super) fn from_term(term: &'a str, limit: Option<usize>) -> Self {
+        let limit = limit.unwrap_or(MAX_SEARCH_LIMIT).min(MAX_SEARCH_LIMIT);
+        let url = search_url(&[("query", term), ("limit", &limit.to_string())]);
+        Self {
+            term,
+            url,
+            error: None,
+            projects: vec![],
+            jobsets: vec![],
+            jobs: vec![],
+            suggestions: None,
+        }
+    }
+
+    fn read_results(self, doc: &Html) -> Self {
+        let read = |text: &str, kind| {
+            find_table_after(doc, text)
+                .and_then(|table| table.find("tbody").ok())
+                .map(|tbody| SearchResult::from_tbody(tbody, kind))
+                .unwrap_or_default()
+        };
+        Self {
+            projects: read("projects match", "project"),
+            jobsets: read("jobsets match", "jobset"),
+            jobs: read("jobs match", "job"),
+            ..self
+        }
+    }
+
+    pub(super) fn fetch_and_read(self) -> anyhow::Result<Self> {
+        let doc = self.fetch_document()?;
+        let stat = self.read_results(&doc);
+        if stat.projects.is_empty() && stat.jobsets.is_empty() && stat.jobs.is_empty() {
+            let status = match doc.find("div.alert") {
+                Ok(alert) => {
+                    let status: String = alert.text().collect();
+                    let status: Vec<&str> = status.split_whitespace().collect();
+                    status.join(" ")
+                }
+                Err(_) => format!("no projects, jobsets or jobs match '{}'", stat.term),
+            };
+            return Ok(stat.finish_with_error(status));
+        }
+        Ok(stat)
+    }
+
+    /// Suggests the jobs of the `jobset` found in the search results
+    /// with names closest to the term.
+    fn suggest(self, jobset: &str) -> Self {
+        let prefix = format!("{}:", jobset.replacen('/', ":", 1));
+        let candidates = self
+            .jobs
+            .iter()
+            .filter_map(|x| x.name.strip_prefix(&prefix));
+        let suggestions = closest_jobs(self.term, candidates)
+            .into_iter()
+            .map(str::to_string)
+            .collect();
+        Self {
+            suggestions: Some(suggestions),
+            ..self
+        }
+    }
+
+    fn results(&self) -> Vec<SearchResult> {
+        [&self.projects, &self.jobsets, &self.jobs]
+            .into_iter()
+            .flatten()
+            .cloned()
+            .collect()
+    }
+}
+
+/// Ranks the candidate job names by their similarity to the `job` that
+/// could not be found, returning the closest ones.
+fn closest_jobs<'a>(job: &str, candidates: impl IntoIterator<Item = &'a str>) -> Vec<&'a str> {
+    let mut ranked: Vec<(f64, &str)> = Vec::new();
+    for candidate in candidates {
+        let similarity = strsim::normalized_damerau_levenshtein(job, candidate);
+        if similarity >= MIN_SIMILARITY && !ranked.iter().any(|(_, x)| *x == candidate) {
+            ranked.push((similarity, candidate));
+        }
+    }
+    ranked.sort_by(|(a, _), (b, _)| b.total_cmp(a));
+    ranked
+        .into_iter()
+        .take(MAX_SUGGESTIONS)
+        .map(|(_, candidate)| candidate)
+        .collect()
+}
+
+/// Returns the terms to search for jobs similar to the `job`: the full
+/// attribute path, and the last attribute (e.g. `requests.x86_64-linux`)
+/// which is most likely spelled right if the path contains a typo.
+fn suggestion_terms(job: &str) -> Vec<String> {
+    let arch = constants::KNOWN_ARCHITECTURES
+        .iter()
+        .find(|arch| job.ends_with(&format!(".{arch}")));
+    let attrs = match arch {
+        Some(arch) => &job[..job.len() - arch.len() - 1],
+        None => job,
+    };
+    let last_attr = attrs.rsplit('.').next().unwrap_or(attrs);
+    let term = match arch {
+        Some(arch) => format!("{last_attr}.{arch}"),
+        None => last_attr.to_string(),
+    };
+    let mut terms = vec![job.to_string()];
+    if term != job {
+        terms.push(term);
+    }
+    terms
+}
+
+impl ResolvedArgs {
+    pub(crate) fn fetch_and_print_search(&self, term: &str) -> anyhow::Result<bool> {
+        let stat = SearchReport::from_term(term, self.pagination.limit);
+        if self.url {
+            println!("{}", stat.get_url());
+            return Ok(true);
+        }
+        if self.format.is_table() {
+            println!(
+                "Search results for {} {}",
+                term.bold(),
+                format!("@ {}", stat.get_url()).dimmed()
+            );
+        }
+        let stat = stat.fetch_and_read()?;
+        if !self.format.is_table() {
+            let mut indexmap = IndexMap::new();
+            indexmap.insert(term, &stat);
+            for (group, results) in [
+                ("projects", &stat.projects),
+                ("jobsets", &stat.jobsets),
+                ("jobs", &stat.jobs),
+            ] {
+                self.print_flat_rows(ReportKind::Search, Some(term), Some(group), results)?;
+            }
+            self.print_report(ReportKind::Search, &indexmap)?;
+            return Ok(stat.error.is_none());
+        }
+        if let Some(error) = &stat.error {
+            warn!("{error}");
+            return Ok(false);
+        }
+        if stat.jobs.len() >= MAX_SEARCH_LIMIT {
+            warn!("only the first {MAX_SEARCH_LIMIT} matching jobs are shown");
+        }
+        println!("{}", stat.format_table(false, &stat.results()));
+        Ok(true)
+    }
+
+    /// Searches for jobs with names similar to the `job` that could not be
+    /// found on the jobset, returning them as the suggestions of the report.
+    pub(super) fn suggest_jobs<'a>(&self, job: &'a str) -> anyhow::Result<SearchReport<'a>> {
+        // Hydra only matches substrings, so the last attribute is searched
+        // for as well, in case the attribute path contains a typo
+        let mut stat = SearchReport::from_term(job, None);
+        for term in suggestion_terms(job) {
+            debug!("searching for jobs similar to '{job}' with '{term}'");
+            let found = SearchReport::from_term(&term, None).fetch_and_read()?;
+            stat.jobs.extend(found.jobs);
+        }
+        let stat = stat.suggest(&self.jobset);
+        if stat.suggestions.iter().flatten().next().is_none() {
+            debug!("no similar jobs found on jobset {}", self.jobset);
+        }
+        Ok(stat)
+    }
+}
+
+/// Prints the suggestions of a failed lookup below the table.
+pub(super) fn print_suggestions(suggestions: Option<&Vec<String>>) {
+    let Some(suggestions) = suggestions.filter(|x| !x.is_empty()) else {
+        return;
+    };
+    eprintln!(
+        "\n{} {}",
+        StatusIcon::Warning,
+        "Did you mean one of the following jobs?".bold()
+    );
+    for suggestion in suggestions {
+        eprintln!("  - {suggestion}");
+    }
+}
+
+#[test]
+fn read_search_results() {
+    let html = r#"
+        <div class="container">
+          <p>The following projects match your query:</p>
+          <table class="table"><tbody>
+            <tr>
+              <td><a href="https://hydra.nixos.org/project/nixpkgs">nixpkgs</a></td>
+              <td>Nix Packages collection</td>
+            </tr>
+          </tbody></table>
+          <p>The following jobs match your query:</p>
+          <table class="table"><tbody>
+            <tr><td><a href="https://hydra.nixos.org/job/nixpkgs/unstable/python312Packages.requests.x86_64-linux">nixpkgs:unstable:python312Packages.requests.x86_64-linux</a></td></tr>
+            <tr><td><a href="https://hydra.nixos.org/job/nixpkgs/unstable/python313Packages.requests.x86_64-linux">nixpkgs:unstable:python313Packages.requests.x86_64-linux</a></td></tr>
+            <tr><td><a href="https://hydra.nixos.org/job/nixpkgs/unstable/python312Packages.requests-oauthlib.x86_64-linux">nixpkgs:unstable:python312Packages.requests-oauthlib.x86_64-linux</a></td></tr>
+          </tbody></table>
+        </div>
+    "#;
+    let doc = Html::parse_document(html);
+    let stat = SearchReport::from_term("requests", None).read_results(&doc);
+    assert_eq!(stat.projects.len(), 1);
+    assert!(stat.jobsets.is_empty());
+    assert_eq!(stat.jobs.len(), 3);
+    let job = "pyhton312Packages.requests.x86_64-linux";
+    assert_eq!(suggestion_terms(job), [job, "requests.x86_64-linux"]);
+    assert_eq!(suggestion_terms("hello"), ["hello"]);
+    let stat = SearchReport { term: job, ..stat }.suggest("nixpkgs/unstable");
+    let suggestions = stat.suggestions.clone().unwrap();
+    assert_eq!(suggestions[0], "python312Packages.requests.x86_64-linux");
+    let json = serde_json::to_value(&stat).unwrap();
+    assert_eq!(json["suggestions"][0], suggestions[0]);
+    assert!(stat
+        .suggest("nixos/trunk-combined")
+        .suggestions
+        .unwrap()
+        .is_empty());
+    assert!(closest_jobs("hello.x86_64-linux", ["gimp.aarch64-darwin"]).is_empty());
+    let stat = SearchReport::from_term("c++ & rust #1", Some(10));
+    assert!(stat
+        .url
+        .ends_with("/search?query=c%2B%2B+%26+rust+%231&limit=10"));
+}
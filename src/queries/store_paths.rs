@@ -0,0 +1,271 @@
+//! A module that finds the Hydra builds that produced the given store paths
+//! (or derivations), from search results such as
+//! <https://hydra.nixos.org/search?query=/nix/store/1q8w6gl1ll0mwfkqc3c2yx005s6wwfrl-hello-2.12.1>.
+
+use anyhow::bail;
+use colored::Colorize;
+use indexmap::IndexMap;
+use log::{debug, warn};
+use regex::Regex;
+use schemars::JsonSchema;
+use scraper::{ElementRef, Html};
+use serde::Serialize;
+use serde_with::skip_serializing_none;
+
+use crate::{
+    binary_cache::CacheStatus, constants, fetch_json, formats::ReportKind, structs::BuildDetails,
+    BuildStatus, FetchHydraReport, ResolvedArgs, SoupFind,
+};
+
+/// Container for the builds that produced a single store path
+#[skip_serializing_none]
+#[derive(Serialize, JsonSchema, Clone)]
+pub(super) struct StorePathReport<'a> {
+    path: &'a str,
+    url: String,
+    error: Option<String>,
+    builds: Vec<BuildDetails>,
+    cache: Option<Vec<CacheStatus>>,
+}
+
+impl FetchHydraReport for StorePathReport<'_> {
+    fn get_url(&self) -> &str {
+        &self.url
+    }
+
+    fn finish_with_error(self, status: String) -> Self {
+        Self {
+            error: Some(status),
+            ..self
+        }
+    }
+}
+
+/// Normalizes a path inside the Nix store to the top level store path,
+/// e.g. `/nix/store/<hash>-hello-2.12.1/bin/hello` becomes
+/// `/nix/store/<hash>-hello-2.12.1`, which is what Hydra searches for.
+pub(crate) fn normalize_store_path(path: &str) -> anyhow::Result<String> {
+    let store_dir = constants::NIX_STORE_DIR;
+    let err = || format!("'{path}' is not a valid path in {store_dir}");
+    let Some(name) = path.trim().strip_prefix(store_dir) else {
+        bail!(err());
+    };
+    let name = name
+        .trim_start_matches('/')
+        .split('/')
+        .next()
+        .unwrap_or_default();
+    if !Regex::new("^[0-9a-z]{32}-.+$").unwrap().is_match(name) {
+        bail!(err());
+    }
+    Ok(format!("{store_dir}/{name}"))
+}
+
+/// Finds the table that directly follows a paragraph (or heading) containing
+/// `text`, which is how Hydra's search page introduces its results.
+pub(super) fn find_table_after<'a>(doc: &'a Html, text: &str) -> Option<ElementRef<'a>> {
+    let paragraph = doc
+        .find_all("p, h2, h3")
+        .into_iter()
+        .find(|p| p.text().collect::<String>().contains(text))?;
+    let sibling = paragraph.next_siblings().find_map(ElementRef::wrap)?;
+    match sibling.value().name() {
+        "table" => Some(sibling),
+        _ => sibling.find("table").ok(),
+    }
+}
+
+/// Url of Hydra's search page, with the `params` encoded in its query,
+/// as store paths and search terms may contain e.g. `+` or `&`.
+pub(super) fn search_url(params: &[(&str, &str)]) -> String {
+    query_url("/search", params)
+}
+
+/// Url of the given `path` on Hydra, with the `params` encoded in its query.
+pub(super) fn query_url(path: &str, params: &[(&str, &str)]) -> String {
+    let url = format!("{}{path}", &*constants::HYDRA_CHECK_HOST_URL);
+    match reqwest::Url::parse(&url) {
+        Ok(mut url) => {
+            url.query_pairs_mut().extend_pairs(params);
+            url.into()
+        }
+        // an invalid host url is reported when fetching
+        Err(_) => url,
+    }
+}
+
+impl<'a> StorePathReport<'a> {
+    fn from_path(path: &'a str) -> Self {
+        let url = search_url(&[("query", path)]);
+        Self {
+            path,
+            url,
+            error: None,
+            builds: vec![],
+            cache: None,
+        }
+    }
+
+    /// Collects the build ids matching the store path, either as an
+    /// output path or as a derivation path.
+    fn read_build_ids(doc: &Html) -> anyhow::Result<Vec<String>> {
+        let mut build_ids = Vec::new();
+        for text in ["builds match", "derivations match"] {
+            let Some(table) = find_table_after(doc, text) else {
+                continue;
+            };
+            let Ok(tbody) = table.find("tbody") else {
+                continue;
+            };
+            for build in BuildStatus::from_tbody(tbody)? {
+                match build.build_id {
+                    Some(id) if !build_ids.contains(&id) => build_ids.push(id),
+                    _ => {}
+                }
+            }
+        }
+        Ok(build_ids)
+    }
+
+    fn fetch_and_read(self, args: &ResolvedArgs) -> anyhow::Result<Self> {
+        let doc = self.fetch_document()?;
+        let build_ids = Self::read_build_ids(&doc)?;
+        if build_ids.is_empty() {
+            let status = match doc.find("div.alert") {
+                Ok(alert) => {
+                    let status: String = alert.text().collect();
+                    let status: Vec<&str> = status.split_whitespace().collect();
+                    status.join(" ")
+                }
+                Err(_) => format!("no Hydra build found for {}", self.path),
+            };
+            return Ok(self.finish_with_error(status));
+        }
+        let timestamp = |build: &BuildDetails| build.stoptime.or(build.timestamp);
+        let mut builds = Vec::new();
+        for id in build_ids {
+            // every build takes a request, so stop as soon as the limit is reached
+            if args.pagination.is_full(builds.len()) {
+                break;
+            }
+            let url = format!("{}/build/{id}", &*constants::HYDRA_CHECK_HOST_URL);
+            debug!("fetching build details from {url}");
+            let details: BuildDetails = match fetch_json(&url) {
+                Ok(details) => details,
+                Err(err) => {
+                    warn!("could not fetch the details of build {id}: {err}");
+                    continue;
+                }
+            };
+            if !args.pagination.accepts(timestamp(&details)) {
+                continue;
+            }
+            builds.push(details);
+            if args.short {
+                break;
+            }
+        }
+        Ok(Self { builds, ..self })
+    }
+}
+
+impl ResolvedArgs {
+    pub(crate) fn fetch_and_print_store_paths(&self, paths: &[String]) -> anyhow::Result<bool> {
+        let mut status = true;
+        let mut indexmap = IndexMap::new();
+        for (idx, path) in paths.iter().enumerate() {
+            let stat = StorePathReport::from_path(path);
+            if self.url {
+                println!("{}", stat.get_url());
+                continue;
+            }
+            if self.format.is_table() {
+                // print title first, then fetch
+                if idx > 0 && !self.short {
+                    println!(); // vertical whitespace
+                }
+                println!(
+                    "Hydra builds of {} {}",
+                    path.bold(),
+                    format!("@ {}", stat.get_url()).dimmed()
+                );
+            }
+            let stat = stat.fetch_and_read(self)?;
+            if !stat.builds.iter().any(|build| build.buildstatus == Some(0)) {
+                status = false;
+            }
+            let builds: Vec<_> = stat
+                .builds
+                .iter()
+                .map(BuildDetails::to_build_status)
+                .collect();
+            self.print_rows(ReportKind::StorePaths, path, None, &builds)?;
+            if let Some(error) = stat.error.as_ref().filter(|_| self.format.is_table()) {
+                warn!("{error}");
+                continue;
+            }
+            if self.format.is_table() {
+                println!("{}", stat.format_table(self.short, &stat.builds));
+            }
+            // the same output may be produced by builds on multiple jobsets
+            let mut outputs: Vec<(&str, &str)> = Vec::new();
+            for (name, output) in stat.builds.iter().flat_map(|x| &x.buildoutputs) {
+                if !outputs.iter().any(|(_, path)| *path == output.path) {
+                    outputs.push((name, &output.path));
+                }
+            }
+            let cache = self.check_cache(outputs);
+            if !self.print_cache_status(&stat, cache.as_ref()) {
+                status = false;
+            }
+            if !self.format.is_table() {
+                indexmap.insert(path, StorePathReport { cache, ..stat });
+            }
+        }
+        self.print_report(ReportKind::StorePaths, &indexmap)?;
+        Ok(status)
+    }
+}
+
+#[test]
+fn normalize_store_paths() {
+    let hash = "1q8w6gl1ll0mwfkqc3c2yx005s6wwfrl";
+    let path = format!("/nix/store/{hash}-hello-2.12.1");
+    assert_eq!(normalize_store_path(&path).unwrap(), path);
+    let subpath = format!("{path}/bin/hello");
+    assert_eq!(normalize_store_path(&subpath).unwrap(), path);
+    let drv = format!("/nix/store/{hash}-hello-2.12.1.drv");
+    assert_eq!(normalize_store_path(&drv).unwrap(), drv);
+    assert!(normalize_store_path("/nix/store/hello").is_err());
+    assert!(normalize_store_path("hello").is_err());
+}
+
+#[test]
+fn find_search_results() {
+    let html = r#"
+        <div class="container">
+          <p>The following builds match your query:</p>
+          <table class="table"><tbody>
+            <tr>
+              <td><img src="/static/images/checkmark_16.png" title="Succeeded" /></td>
+              <td><a href="https://hydra.nixos.org/build/1">1</a></td>
+              <td>nixpkgs:trunk:hello.x86_64-linux</td>
+              <td><time datetime="2024-11-07T11:46:15Z">2024-11-07</time></td>
+              <td>hello-2.12.1</td>
+              <td><tt>x86_64-linux</tt></td>
+            </tr>
+          </tbody></table>
+          <p>The following derivations match your query:</p>
+          <div><table class="table"><tbody></tbody></table></div>
+        </div>
+    "#;
+    let doc = Html::parse_document(html);
+    let build_ids = StorePathReport::read_build_ids(&doc).unwrap();
+    assert_eq!(build_ids, ["1"]);
+    assert!(find_table_after(&doc, "derivations match").is_some());
+    assert!(find_table_after(&doc, "projects match").is_none());
+    let stat = StorePathReport::from_path("/nix/store/5ahd9ngb-gtk+3-3.24.43");
+    assert!(stat
+        .url
+        .ends_with("/search?query=%2Fnix%2Fstore%2F5ahd9ngb-gtk%2B3-3.24.43"));
+}
@@ -0,0 +1,182 @@
+//! A module that aggregates the failing builds of an evaluation, see `--summary`,
+//! which is more digestible than the full tables for evaluations with thousands
+//! of failures, e.g. on `nixpkgs/staging-next`.
+
+use colored::{ColoredString, Colorize};
+use indexmap::IndexMap;
+use schemars::JsonSchema;
+use serde::Serialize;
+
+use crate::{constants, BuildStatus, FetchHydraReport, ShowHydraStatus, StatusIcon};
+
+/// Number of attribute sets listed as the top offenders.
+const MAX_TOP_OFFENDERS: usize = 10;
+
+/// Counts of the failing builds of an evaluation, each sorted
+/// by the number of failures in descending order
+#[derive(Serialize, JsonSchema, Debug, Default, Clone)]
+pub(super) struct EvalSummary {
+    failures: usize,
+    /// e.g. `Failed`, `Dependency failed`, `Timed out` or `Cancelled`
+    per_status: IndexMap<String, usize>,
+    per_system: IndexMap<String, usize>,
+    /// Top-level attribute of the job, e.g. `python312Packages` or `haskellPackages`
+    per_attribute: IndexMap<String, usize>,
+}
+
+/// A single aggregated count, for printing
+struct Count<'a>(&'a str, usize);
+
+impl ShowHydraStatus for Count<'_> {
+    fn format_as_vec(&self) -> Vec<ColoredString> {
+        let Count(name, count) = self;
+        let icon = match *name {
+            "Cancelled" | "Aborted" => StatusIcon::Cancelled,
+            _ => StatusIcon::Failed,
+        };
+        let icon = ColoredString::from(&icon);
+        vec![format!("{icon} {count}").into(), (*name).into()]
+    }
+}
+
+/// Finds the top-level attribute of a job such as
+/// `python312Packages.requests.x86_64-linux`, skipping the `nixpkgs.`
+/// and `nixos.` prefixes of the jobs on the NixOS jobsets.
+fn top_level_attribute<'a>(job: &'a str, system: &str) -> &'a str {
+    let attr = constants::KNOWN_ARCHITECTURES
+        .iter()
+        .chain([&system])
+        .find_map(|arch| job.strip_suffix(&format!(".{arch}")))
+        .unwrap_or(job);
+    let attr = ["nixpkgs.", "nixos."]
+        .into_iter()
+        .find_map(|prefix| attr.strip_prefix(prefix))
+        .unwrap_or(attr);
+    attr.split('.').next().unwrap_or(attr)
+}
+
+fn top_counts(counts: &IndexMap<String, usize>, limit: usize) -> Vec<Count<'_>> {
+    counts
+        .iter()
+        .take(limit)
+        .map(|(name, count)| Count(name, *count))
+        .collect()
+}
+
+fn sort_by_count(counts: &mut IndexMap<String, usize>) {
+    counts.sort_by(|name_a, a, name_b, b| b.cmp(a).then(name_a.cmp(name_b)));
+}
+
+impl EvalSummary {
+    /// Aggregates the builds that failed, skipping the succeeded,
+    /// queued and removed ones.
+    pub(super) fn from_builds<'a>(builds: impl IntoIterator<Item = &'a BuildStatus>) -> Self {
+        let mut summary = Self::default();
+        for build in builds {
+            if build.success || build.build_id.is_none() || matches!(build.icon, StatusIcon::Queued)
+            {
+                continue;
+            }
+            let system = build.arch.as_deref().unwrap_or("unknown");
+            let job = build
+                .job_name
+                .as_deref()
+                .or(build.name.as_deref())
+                .unwrap_or_default();
+            summary.failures += 1;
+            *summary.per_status.entry(build.status.clone()).or_default() += 1;
+            *summary.per_system.entry(system.into()).or_default() += 1;
+            *summary
+                .per_attribute
+                .entry(top_level_attribute(job, system).into())
+                .or_default() += 1;
+        }
+        sort_by_count(&mut summary.per_status);
+        sort_by_count(&mut summary.per_system);
+        sort_by_count(&mut summary.per_attribute);
+        summary
+    }
+
+    pub(super) fn print(&self, report: &impl FetchHydraReport) {
+        if self.failures == 0 {
+            println!("\n{} {}", StatusIcon::Succeeded, "No failing builds".bold());
+            return;
+        }
+        println!("\n{}", format!("Failing builds: {}", self.failures).bold());
+        println!("\n{}", "Per status:".bold());
+        println!(
+            "{}",
+            report.format_table(false, &top_counts(&self.per_status, usize::MAX))
+        );
+        println!("\n{}", "Per system:".bold());
+        println!(
+            "{}",
+            report.format_table(false, &top_counts(&self.per_system, usize::MAX))
+        );
+        println!("\n{}", "Top offenders:".bold());
+        println!(
+            "{}",
+            report.format_table(false, &top_counts(&self.per_attribute, MAX_TOP_OFFENDERS))
+        );
+        let rest = self.per_attribute.len().saturating_sub(MAX_TOP_OFFENDERS);
+        if rest > 0 {
+            println!("{}", format!("... and {rest} more attributes").dimmed());
+        }
+    }
+}
+
+#[test]
+fn summarize_failures() {
+    let build = |job: &str, status: &str, arch: &str| BuildStatus {
+        icon: StatusIcon::Failed,
+        status: status.into(),
+        build_id: Some("1".into()),
+        job_name: Some(format!("{job}.{arch}")),
+        arch: Some(arch.into()),
+        ..Default::default()
+    };
+    let builds = [
+        build("python312Packages.requests", "Failed", "x86_64-linux"),
+        build(
+            "python312Packages.numpy",
+            "Dependency failed",
+            "x86_64-linux",
+        ),
+        build("python312Packages.numpy", "Timed out", "aarch64-linux"),
+        build("haskellPackages.aeson", "Failed", "aarch64-darwin"),
+        BuildStatus {
+            success: true,
+            ..build("hello", "Succeeded", "x86_64-linux")
+        },
+        BuildStatus {
+            icon: StatusIcon::Warning,
+            status: "Removed".into(),
+            build_id: None,
+            ..build("gimp", "Removed", "x86_64-linux")
+        },
+    ];
+    let summary = EvalSummary::from_builds(&builds);
+    assert_eq!(summary.failures, 4);
+    assert_eq!(summary.per_status.first(), Some((&"Failed".into(), &2)));
+    assert_eq!(summary.per_system["x86_64-linux"], 2);
+    assert_eq!(
+        summary.per_attribute.keys().collect::<Vec<_>>(),
+        ["python312Packages", "haskellPackages"]
+    );
+}
+
+#[test]
+fn strip_jobset_prefixes() {
+    let system = "x86_64-linux";
+    for (job, attr) in [
+        ("nixpkgs.foo.x86_64-linux", "foo"),
+        ("nixos.tests.foo.x86_64-linux", "tests"),
+        (
+            "python312Packages.requests.x86_64-linux",
+            "python312Packages",
+        ),
+        ("nixpkgs.x86_64-linux", "nixpkgs"),
+    ] {
+        assert_eq!(top_level_attribute(job, system), attr);
+    }
+}
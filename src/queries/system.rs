@@ -0,0 +1,433 @@
+//! A module that compares the nixpkgs revision of the running NixOS system
+//! with the recent evaluations and releases of its channel, which answers
+//! how far the system is behind, see `hydra-check self`.
+
+use anyhow::bail;
+use colored::Colorize;
+use log::{debug, warn};
+use regex::Regex;
+use schemars::JsonSchema;
+use serde::Serialize;
+use serde_json::Value;
+use serde_with::skip_serializing_none;
+
+use super::{jobset::JobsetReport, packages::PackageReport};
+use crate::{
+    args::aggregate_job,
+    formats::{cell, FlatRow, ReportKind},
+    structs::{EvalSearch, ReleaseStatus},
+    BuildStatus, EvalStatus, FetchHydraReport, ResolvedArgs, StatusIcon,
+};
+
+const NIXOS_VERSION_PATH: &str = "/run/current-system/nixos-version";
+const OS_RELEASE_PATH: &str = "/etc/os-release";
+const FLAKE_REGISTRY_PATH: &str = "/etc/nix/registry.json";
+
+/// Version and nixpkgs revision of the running NixOS system
+#[skip_serializing_none]
+#[derive(Serialize, JsonSchema, Debug, Clone)]
+pub(crate) struct SystemVersion {
+    /// NixOS release, e.g. `25.05`
+    version: Option<String>,
+    /// Whether the version is a pre-release of unstable, e.g. `25.05pre…`
+    is_pre: bool,
+    /// Nixpkgs revision, usually abbreviated
+    revision: String,
+    /// File that the revision is read from
+    source: String,
+}
+
+/// Parses a NixOS version such as `25.05.20250601.1e9e641` or
+/// `25.11pre854150.5d8f4beac036` into the release, whether it is a
+/// pre-release, and the revision.
+fn parse_nixos_version(text: &str) -> Option<(String, bool, String)> {
+    let regex = Regex::new(r"^([0-9]+\.[0-9]+)(pre[0-9]+|\.[0-9]+)?\.([0-9a-f]{7,40})\b").unwrap();
+    let captures = regex.captures(text.trim())?;
+    let is_pre = captures
+        .get(2)
+        .is_some_and(|x| x.as_str().starts_with("pre"));
+    Some((captures[1].into(), is_pre, captures[3].into()))
+}
+
+/// Finds the `BUILD_ID` (a NixOS version) in the content of `/etc/os-release`.
+fn parse_os_release(text: &str) -> Option<(String, bool, String)> {
+    text.lines()
+        .find_map(|line| line.strip_prefix("BUILD_ID="))
+        .and_then(|x| parse_nixos_version(x.trim_matches('"')))
+}
+
+/// Finds the revision of `nixpkgs` in the flake registry of the system,
+/// which is only pinned for systems built from flakes.
+fn parse_registry(text: &str) -> Option<String> {
+    let registry: Value = serde_json::from_str(text).ok()?;
+    registry["flakes"]
+        .as_array()?
+        .iter()
+        .find(|x| x["from"]["id"] == "nixpkgs")?["to"]["rev"]
+        .as_str()
+        .map(str::to_string)
+}
+
+impl SystemVersion {
+    /// Reads the version and revision of the running system from
+    /// `/run/current-system/nixos-version` or `/etc/os-release`,
+    /// complemented by the full revision in the flake registry.
+    pub(crate) fn detect() -> anyhow::Result<Self> {
+        let read = |path: &str| std::fs::read_to_string(path).ok();
+        let registry_rev = read(FLAKE_REGISTRY_PATH).and_then(|x| parse_registry(&x));
+        let nixos_version = read(NIXOS_VERSION_PATH)
+            .and_then(|x| parse_nixos_version(&x))
+            .map(|x| (x, NIXOS_VERSION_PATH))
+            .or_else(|| {
+                read(OS_RELEASE_PATH)
+                    .and_then(|x| parse_os_release(&x))
+                    .map(|x| (x, OS_RELEASE_PATH))
+            });
+        match (nixos_version, registry_rev) {
+            (Some(((version, is_pre, revision), source)), registry_rev) => {
+                let revision = match registry_rev {
+                    Some(full_rev) if full_rev.starts_with(&revision) => full_rev,
+                    _ => revision,
+                };
+                Ok(Self {
+                    version: Some(version),
+                    is_pre,
+                    revision,
+                    source: source.into(),
+                })
+            }
+            (None, Some(revision)) => Ok(Self {
+                version: None,
+                is_pre: false,
+                revision,
+                source: FLAKE_REGISTRY_PATH.into(),
+            }),
+            (None, None) => bail!(
+                "could not find the nixpkgs revision of the running system in {}, {} or {}",
+                NIXOS_VERSION_PATH,
+                OS_RELEASE_PATH,
+                FLAKE_REGISTRY_PATH
+            ),
+        }
+    }
+
+    /// Guesses the channel of the system from its release, given the
+    /// `stable` release: the stable channel (or an older one) if released,
+    /// or else `nixos-unstable`, which pre-releases always belong to.
+    pub(crate) fn channel(&self, stable: &str) -> String {
+        match self.version.as_deref().and_then(parse_release) {
+            Some(_) if self.is_pre => "nixos-unstable".into(),
+            Some(version) if parse_release(stable).is_some_and(|x| version <= x) => {
+                format!("nixos-{}", self.version.as_deref().unwrap_or_default())
+            }
+            _ => "nixos-unstable".into(),
+        }
+    }
+}
+
+/// Parses a NixOS release such as `25.05` into its year and month,
+/// to be compared numerically.
+fn parse_release(version: &str) -> Option<(u32, u32)> {
+    let (year, month) = version.split_once('.')?;
+    Some((year.parse().ok()?, month.parse().ok()?))
+}
+
+/// Container for the position of the system in the evaluations of its channel
+#[skip_serializing_none]
+#[derive(Serialize, JsonSchema, Clone)]
+pub(super) struct SystemReport<'a> {
+    system: &'a SystemVersion,
+    jobset: &'a str,
+    url: String,
+    error: Option<String>,
+    /// Evaluation of the system's revision
+    eval: Option<EvalStatus>,
+    latest_eval: Option<EvalStatus>,
+    evals_behind: Option<usize>,
+    days_behind: Option<u64>,
+    /// Releases of the channel that succeeded after the system's revision
+    newer_releases: Vec<ReleaseStatus>,
+}
+
+impl FetchHydraReport for SystemReport<'_> {
+    fn get_url(&self) -> &str {
+        &self.url
+    }
+
+    fn finish_with_error(self, status: String) -> Self {
+        Self {
+            error: Some(status),
+            ..self
+        }
+    }
+}
+
+impl SystemReport<'_> {
+    /// Finds the evaluation of the system's revision in the recent
+    /// evaluations of the jobset, latest first.
+    fn read_evals(self, evals: &[EvalStatus]) -> Self {
+        let search = EvalSearch::Revision(self.system.revision.to_lowercase());
+        let Some(index) = evals.iter().position(|x| search.matches(x)) else {
+            let status = format!(
+                "could not find {search} in the {} recent evaluations of jobset '{}'",
+                evals.len(),
+                self.jobset
+            );
+            return self.finish_with_error(status);
+        };
+        let (eval, latest_eval) = (&evals[index], &evals[0]);
+        let days_behind = match (eval.timestamp, latest_eval.timestamp) {
+            (Some(eval), Some(latest)) => Some(latest.saturating_sub(eval) / 86400),
+            _ => None,
+        };
+        Self {
+            eval: Some(eval.clone()),
+            latest_eval: Some(latest_eval.clone()),
+            evals_behind: Some(index),
+            days_behind,
+            ..self
+        }
+    }
+
+    /// Finds the releases of the evaluations after the system's one,
+    /// of which the release tests succeeded.
+    fn read_releases(self, evals: &[EvalStatus], tests: &[BuildStatus], channel: &str) -> Self {
+        let newer_evals = evals[..self.evals_behind.unwrap_or_default()].to_vec();
+        let newer_releases = ReleaseStatus::pair(newer_evals, tests, channel, self.jobset)
+            .into_iter()
+            .filter(|x| x.test.success)
+            .collect();
+        Self {
+            newer_releases,
+            ..self
+        }
+    }
+}
+
+impl FlatRow for SystemReport<'_> {
+    const COLUMNS: &'static [&'static str] = &[
+        "version",
+        "revision",
+        "jobset",
+        "eval",
+        "latest_eval",
+        "evals_behind",
+        "days_behind",
+        "newer_releases",
+        "error",
+    ];
+
+    fn values(&self) -> Vec<String> {
+        vec![
+            cell(self.system.version.as_ref()),
+            self.system.revision.clone(),
+            self.jobset.into(),
+            cell(self.eval.as_ref().and_then(|x| x.id)),
+            cell(self.latest_eval.as_ref().and_then(|x| x.id)),
+            cell(self.evals_behind),
+            cell(self.days_behind),
+            self.newer_releases.len().to_string(),
+            cell(self.error.as_ref()),
+        ]
+    }
+}
+
+impl ResolvedArgs {
+    fn fetch_system_report<'a>(
+        &'a self,
+        system: &'a SystemVersion,
+        jobset_report: &JobsetReport,
+    ) -> anyhow::Result<SystemReport<'a>> {
+        let stat = SystemReport {
+            system,
+            jobset: &self.jobset,
+            url: jobset_report.get_url().into(),
+            error: None,
+            eval: None,
+            latest_eval: None,
+            evals_behind: None,
+            days_behind: None,
+            newer_releases: vec![],
+        };
+        let search = EvalSearch::Revision(system.revision.to_lowercase());
+        let evals = jobset_report.fetch_until(&search)?;
+        let stat = stat.read_evals(&evals);
+        if stat.error.is_some() {
+            return Ok(stat);
+        }
+        let job = aggregate_job(&self.jobset);
+        debug!(
+            "fetching the release tests '{job}' of jobset '{}'",
+            self.jobset
+        );
+        let tests = PackageReport::from_package_with_args(job, self).fetch_and_read()?;
+        let channel = self.channel.as_deref().unwrap_or_default();
+        Ok(stat.read_releases(&evals, &tests.builds, channel))
+    }
+
+    pub(crate) fn fetch_and_print_system(&self, system: &SystemVersion) -> anyhow::Result<bool> {
+        let jobset_report = JobsetReport::from(self);
+        if self.url {
+            println!("{}", jobset_report.get_url());
+            return Ok(true);
+        }
+        if self.format.is_table() {
+            let version = system.version.as_deref().unwrap_or("unknown version");
+            println!(
+                "System revision {} {}",
+                system.revision.bold(),
+                format!("({version}, from {})", system.source).dimmed()
+            );
+            println!(
+                "Evaluations of jobset {} {}",
+                self.jobset.bold(),
+                format!("@ {}", jobset_report.get_url()).dimmed()
+            );
+        }
+        let stat = self.fetch_system_report(system, &jobset_report)?;
+        if !self.format.is_table() {
+            self.print_flat_rows(ReportKind::System, None, None, std::slice::from_ref(&stat))?;
+            self.print_report(ReportKind::System, &stat)?;
+            return Ok(stat.error.is_none());
+        }
+        if let Some(error) = &stat.error {
+            warn!("{error}");
+            return Ok(false);
+        }
+        // the latest evaluation, followed by the one of the system if older
+        let mut evals: Vec<EvalStatus> = stat.latest_eval.iter().cloned().collect();
+        if stat.evals_behind != Some(0) {
+            evals.extend(stat.eval.clone());
+        }
+        println!("{}", stat.format_table(false, &evals));
+        match (stat.evals_behind, stat.days_behind) {
+            (Some(0), _) => println!(
+                "\n{} {}",
+                StatusIcon::Succeeded,
+                "The system is up to date with the latest evaluation".bold()
+            ),
+            (Some(evals), days) => println!(
+                "\n{} {}",
+                StatusIcon::Queued,
+                format!(
+                    "The system is {evals} evaluations and {} days behind the channel head",
+                    days.unwrap_or_default()
+                )
+                .bold()
+            ),
+            (None, _) => {}
+        }
+        if !stat.newer_releases.is_empty() {
+            println!("\n{}", "Newer releases:".bold());
+            println!("{}", stat.format_table(false, &stat.newer_releases));
+        }
+        Ok(true)
+    }
+}
+
+#[test]
+fn parse_system_version() {
+    assert_eq!(
+        parse_nixos_version("25.05.20250601.1e9e641 (Warbler)\n"),
+        Some(("25.05".into(), false, "1e9e641".into()))
+    );
+    assert_eq!(
+        parse_nixos_version("25.11pre854150.5d8f4beac036"),
+        Some(("25.11".into(), true, "5d8f4beac036".into()))
+    );
+    let os_release = "NAME=NixOS\nBUILD_ID=\"24.11.20250101.edf04b7\"\nVERSION_ID=\"24.11\"\n";
+    assert_eq!(
+        parse_os_release(os_release),
+        Some(("24.11".into(), false, "edf04b7".into()))
+    );
+    let registry = r#"{
+        "flakes": [{
+            "from": { "id": "nixpkgs", "type": "indirect" },
+            "to": { "type": "path", "path": "/nix/store/ln479gq56q3kyzyl0mm00xglpmfpzqx4-source",
+                    "rev": "edf04b75c13c2ac0e54df5ec5c543e300f76f1c9" }
+        }],
+        "version": 2
+    }"#;
+    assert_eq!(
+        parse_registry(registry).as_deref(),
+        Some("edf04b75c13c2ac0e54df5ec5c543e300f76f1c9")
+    );
+    let system = |version: &str| SystemVersion {
+        version: Some(version.into()),
+        is_pre: false,
+        revision: "1e9e641".into(),
+        source: NIXOS_VERSION_PATH.into(),
+    };
+    assert_eq!(system("25.05").channel("25.05"), "nixos-25.05");
+    assert_eq!(system("9.09").channel("25.05"), "nixos-9.09");
+    assert_eq!(system("25.11").channel("25.05"), "nixos-unstable");
+    assert_eq!(system("100.05").channel("25.05"), "nixos-unstable");
+    // the pre-release of 25.05 is built from unstable, before 25.05 is released
+    let (version, is_pre, revision) = parse_nixos_version("25.05pre790122.1e9e641").unwrap();
+    let pre_release = SystemVersion {
+        version: Some(version),
+        is_pre,
+        revision,
+        source: NIXOS_VERSION_PATH.into(),
+    };
+    assert_eq!(pre_release.channel("25.05"), "nixos-unstable");
+}
+
+#[test]
+fn read_system_report() {
+    let eval = |id: u64, rev: &str, timestamp: u64| EvalStatus {
+        id: Some(id),
+        short_rev: Some(rev.into()),
+        timestamp: Some(timestamp),
+        finished: Some(true),
+        ..Default::default()
+    };
+    let evals = [
+        eval(1_815_432, "5d8f4be", 1_749_600_000),
+        eval(1_815_123, "8c4dc69", 1_749_081_600),
+        eval(1_814_987, "1e9e641", 1_748_736_000),
+    ];
+    let test = |rev: &str, success: bool| BuildStatus {
+        icon: match success {
+            true => StatusIcon::Succeeded,
+            false => StatusIcon::Failed,
+        },
+        success,
+        name: Some(format!("nixos-25.05.{rev}")),
+        ..Default::default()
+    };
+    let tests = [test("5d8f4be", true), test("8c4dc69", false)];
+    let system = SystemVersion {
+        version: Some("25.05".into()),
+        is_pre: false,
+        revision: "1E9E641A".into(),
+        source: NIXOS_VERSION_PATH.into(),
+    };
+    let stat = SystemReport {
+        system: &system,
+        jobset: "nixos/release-25.05",
+        url: String::new(),
+        error: None,
+        eval: None,
+        latest_eval: None,
+        evals_behind: None,
+        days_behind: None,
+        newer_releases: vec![],
+    };
+    // the system is two evaluations and 10 days behind the latest one,
+    // of which only the latest one passed the release tests
+    let found = stat
+        .clone()
+        .read_evals(&evals)
+        .read_releases(&evals, &tests, "nixos-25.05");
+    assert_eq!((found.evals_behind, found.days_behind), (Some(2), Some(10)));
+    assert_eq!(found.eval.and_then(|x| x.id), Some(1_814_987));
+    let releases: Vec<_> = found.newer_releases.iter().map(|x| x.eval.id).collect();
+    assert_eq!(releases, [Some(1_815_432)]);
+    // the revision may be older than the fetched evaluations
+    let missing = stat.read_evals(&evals[..2]);
+    assert_eq!(
+        missing.error.as_deref(),
+        Some("could not find revision '1e9e641a' in the 2 recent evaluations of jobset 'nixos/release-25.05'")
+    );
+}
@@ -0,0 +1,126 @@
+//! Commands that rebuild a job locally at exactly the revision of nixpkgs
+//! that Hydra built it from, see `--reproduce`.
+
+use clap::ValueEnum;
+use colored::Colorize;
+use log::warn;
+
+use crate::{
+    constants,
+    structs::{BuildDetails, PinnedInput},
+    EvalInput, ResolvedArgs,
+};
+
+/// Kind of commands printed by `--reproduce`
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ReproduceFormat {
+    /// `nix build` of the pinned source tarball and flake reference
+    Nix,
+    /// Hydra's reproduce script at `/build/<id>/reproduce`
+    Hydra,
+}
+
+/// Finds the attribute path of a job such as `nixpkgs.hello.x86_64-linux`,
+/// relative to the top-level of nixpkgs, or `None` if the job is not a
+/// package (e.g. `nixos.tests.simple.x86_64-linux`).
+fn package_attribute(details: &BuildDetails) -> Option<&str> {
+    let job = details.job.as_deref()?;
+    let attr = match &details.system {
+        Some(system) => job.strip_suffix(&format!(".{system}")).unwrap_or(job),
+        None => job,
+    };
+    match details.project.as_deref() {
+        // the jobs of nixos/release-combined.nix
+        Some("nixos") => attr.strip_prefix("nixpkgs."),
+        _ => Some(attr),
+    }
+}
+
+/// Generates the `nix build` commands for a build of the given inputs.
+fn nix_commands(details: &BuildDetails, inputs: &[EvalInput]) -> Option<Vec<String>> {
+    let job = details.job.as_deref().unwrap_or_default();
+    let Some(pinned) = PinnedInput::from_inputs(inputs) else {
+        warn!("could not find the revision of nixpkgs that {job} was built from");
+        return None;
+    };
+    let Some(attr) = package_attribute(details) else {
+        warn!("{job} is not a package attribute, consider --reproduce=hydra");
+        return None;
+    };
+    let system = details
+        .system
+        .as_deref()
+        .unwrap_or(constants::KNOWN_ARCHITECTURES[0]);
+    let mut commands = Vec::new();
+    if let Some(tarball_url) = pinned.tarball_url() {
+        commands.push(format!(
+            "nix build --file {tarball_url} --argstr system {system} {attr}"
+        ));
+    }
+    commands.push(format!(
+        "nix build '{}#legacyPackages.{system}.{attr}'",
+        pinned.flake_ref()
+    ));
+    Some(commands)
+}
+
+impl ResolvedArgs {
+    /// Generates the commands of `--reproduce` for a single build,
+    /// or returns `None` if the flag is not set.
+    pub(crate) fn reproduce(
+        &self,
+        details: &BuildDetails,
+        inputs: &[EvalInput],
+    ) -> Option<Vec<String>> {
+        match self.reproduce? {
+            ReproduceFormat::Nix => nix_commands(details, inputs),
+            ReproduceFormat::Hydra => Some(vec![format!(
+                "bash <(curl -sSfL {}/reproduce)",
+                details.url()
+            )]),
+        }
+    }
+
+    /// Prints the results of [`Self::reproduce`] below a report.
+    pub(crate) fn print_reproduce(&self, commands: Option<&Vec<String>>) {
+        let Some(commands) = commands.filter(|x| !x.is_empty()) else {
+            return;
+        };
+        if !self.format.is_table() {
+            return;
+        }
+        println!("{}", "Reproduce with:".bold());
+        for command in commands {
+            println!("{command}");
+        }
+    }
+}
+
+#[test]
+fn reproduce_with_nix() {
+    let details = BuildDetails {
+        project: Some("nixos".into()),
+        job: Some("nixpkgs.hello.aarch64-linux".into()),
+        system: Some("aarch64-linux".into()),
+        ..Default::default()
+    };
+    let inputs = [EvalInput {
+        name: Some("nixpkgs".into()),
+        value: Some("https://github.com/NixOS/nixpkgs.git".into()),
+        revision: Some("1e9e641a3fc1b22fbdb823a99d8ff96692cc4fba".into()),
+        ..Default::default()
+    }];
+    let commands = nix_commands(&details, &inputs).unwrap();
+    assert_eq!(
+        commands,
+        [
+            "nix build --file https://github.com/NixOS/nixpkgs/archive/1e9e641a3fc1b22fbdb823a99d8ff96692cc4fba.tar.gz --argstr system aarch64-linux hello",
+            "nix build 'github:NixOS/nixpkgs/1e9e641a3fc1b22fbdb823a99d8ff96692cc4fba#legacyPackages.aarch64-linux.hello'",
+        ]
+    );
+    let tests = BuildDetails {
+        job: Some("nixos.tests.simple.aarch64-linux".into()),
+        ..details
+    };
+    assert!(nix_commands(&tests, &inputs).is_none());
+}
@@ -1,13 +1,18 @@
 use anyhow::bail;
 use colored::{ColoredString, Colorize};
+use schemars::JsonSchema;
 use scraper::ElementRef;
 use serde::Serialize;
 use serde_with::skip_serializing_none;
 
-use crate::{is_skipable_row, ShowHydraStatus, SoupFind, StatusIcon, TryAttr};
+use crate::{
+    binary_cache::CacheStatus,
+    formats::{cell, FlatRow},
+    is_skipable_row, ShowHydraStatus, SoupFind, StatusIcon, TryAttr,
+};
 
 #[skip_serializing_none]
-#[derive(Serialize, Debug, Default, Clone)]
+#[derive(Serialize, JsonSchema, Debug, Default, Clone)]
 /// Status of a single build attempt, can be serialized to a JSON entry
 pub(crate) struct BuildStatus {
     pub(crate) icon: StatusIcon,
@@ -20,6 +25,14 @@ pub(crate) struct BuildStatus {
     pub(crate) arch: Option<String>,
     pub(crate) evals: bool,
     pub(crate) job_name: Option<String>,
+    /// Presence of the build outputs in a binary cache, see `--check-cache`
+    pub(crate) cache: Option<Vec<CacheStatus>>,
+    /// Commands to rebuild the job locally, see `--reproduce`
+    pub(crate) reproduce: Option<Vec<String>>,
+    /// Snippet that pins nixpkgs to the latest successful build, see `--pin-format`
+    pub(crate) pin: Option<String>,
+    /// Similar job names if the job could not be found, see the `search` subcommand
+    pub(crate) suggestions: Option<Vec<String>>,
 }
 
 impl ShowHydraStatus for BuildStatus {
@@ -57,7 +70,42 @@ impl ShowHydraStatus for BuildStatus {
     }
 }
 
+impl FlatRow for BuildStatus {
+    const COLUMNS: &'static [&'static str] = &[
+        "job",
+        "name",
+        "arch",
+        "status",
+        "build_id",
+        "timestamp",
+        "url",
+    ];
+
+    fn values(&self) -> Vec<String> {
+        vec![
+            cell(self.job_name.as_ref()),
+            cell(self.name.as_ref()),
+            cell(self.arch.as_ref()),
+            self.status.clone(),
+            cell(self.build_id.as_ref()),
+            cell(self.timestamp.as_ref()),
+            cell(self.build_url.as_ref()),
+        ]
+    }
+
+    fn icon(&self) -> Option<&StatusIcon> {
+        Some(&self.icon)
+    }
+}
+
 impl BuildStatus {
+    /// Parses the [`BuildStatus::timestamp`] into a unix timestamp.
+    pub(crate) fn unix_timestamp(&self) -> Option<i64> {
+        let timestamp = self.timestamp.as_deref()?;
+        let datetime = chrono::DateTime::parse_from_rfc3339(timestamp).ok()?;
+        Some(datetime.timestamp())
+    }
+
     pub(crate) fn from_tbody(tbody: ElementRef<'_>) -> anyhow::Result<Vec<Self>> {
         let mut builds = Vec::new();
         for row in tbody.find_all("tr") {
@@ -137,6 +185,10 @@ impl BuildStatus {
                 arch: Some(arch),
                 evals,
                 job_name,
+                cache: None,
+                reproduce: None,
+                pin: None,
+                suggestions: None,
             });
         }
         Ok(builds)
@@ -0,0 +1,251 @@
+use colored::Colorize;
+use indexmap::IndexMap;
+use schemars::JsonSchema;
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_with::skip_serializing_none;
+use std::fmt::Display;
+
+#[cfg(test)]
+use insta::assert_snapshot;
+
+use super::{bool_from_int, u64_from_int_or_str};
+use crate::formats::{cell, FlatRow};
+
+/// Configuration of a jobset, as provided by Hydra's JSON API from urls
+/// such as <https://hydra.nixos.org/jobset/nixpkgs/unstable>, which is
+/// shown in the "Configuration" tab of the jobset page.
+#[skip_serializing_none]
+#[derive(Serialize, JsonSchema, Deserialize, Clone, Default, Debug)]
+pub(crate) struct JobsetConfig {
+    pub(crate) project: Option<String>,
+    pub(crate) name: Option<String>,
+    pub(crate) description: Option<String>,
+    /// `0`: disabled, `1`: enabled, `2`: one-shot, `3`: one-at-a-time
+    #[serde(default, deserialize_with = "u64_from_int_or_str")]
+    pub(crate) enabled: u64,
+    #[serde(default, deserialize_with = "bool_from_int")]
+    pub(crate) hidden: bool,
+    /// `0`: legacy (Nix expression), `1`: flake
+    #[serde(default, rename = "type", deserialize_with = "u64_from_int_or_str")]
+    pub(crate) jobset_type: u64,
+    pub(crate) flake: Option<String>,
+    pub(crate) nixexprinput: Option<String>,
+    pub(crate) nixexprpath: Option<String>,
+    /// Interval between evaluations in seconds, `0` if not polled
+    #[serde(default, deserialize_with = "u64_from_int_or_str")]
+    pub(crate) checkinterval: u64,
+    #[serde(default, deserialize_with = "u64_from_int_or_str")]
+    pub(crate) schedulingshares: u64,
+    #[serde(default, deserialize_with = "bool_from_int")]
+    pub(crate) enableemail: bool,
+    pub(crate) emailoverride: Option<String>,
+    #[serde(default, deserialize_with = "u64_from_int_or_str")]
+    pub(crate) keepnr: u64,
+    #[serde(default, alias = "inputs")]
+    pub(crate) jobsetinputs: IndexMap<String, JobsetInput>,
+}
+
+/// A single input declared in the configuration of a jobset
+#[skip_serializing_none]
+#[derive(Serialize, JsonSchema, Deserialize, Clone, Default, Debug)]
+pub(crate) struct JobsetInput {
+    pub(crate) name: Option<String>,
+    #[serde(rename = "type")]
+    pub(crate) input_type: Option<String>,
+    /// Values of the input, usually a single one (e.g. `<url> <branch>`)
+    #[serde(default, alias = "value", deserialize_with = "strings_from_str_or_vec")]
+    pub(crate) jobsetinputalts: Vec<String>,
+    #[serde(default, deserialize_with = "bool_from_int")]
+    pub(crate) emailresponsible: bool,
+}
+
+/// Hydra's JSON API may encode the values of an input as a list
+/// (of alternatives) or as a single string.
+fn strings_from_str_or_vec<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Vec<String>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum StrOrVec {
+        Str(String),
+        Vec(Vec<String>),
+    }
+    Ok(match Option::<StrOrVec>::deserialize(deserializer)? {
+        Some(StrOrVec::Str(x)) => vec![x],
+        Some(StrOrVec::Vec(x)) => x,
+        None => vec![],
+    })
+}
+
+impl JobsetConfig {
+    /// Url of the git input that provides nixpkgs, i.e. the `nixpkgs` input,
+    /// or else the input of the Nix expression or the first git input.
+    pub(crate) fn input_url(&self) -> Option<&str> {
+        let inputs = &self.jobsetinputs;
+        let git_inputs = inputs
+            .values()
+            .filter(|x| x.input_type.as_deref() == Some("git"));
+        ["nixpkgs", self.nixexprinput.as_deref().unwrap_or_default()]
+            .into_iter()
+            .filter_map(|name| inputs.get(name))
+            .chain(git_inputs)
+            .find_map(|x| x.jobsetinputalts.first()?.split_whitespace().next())
+    }
+}
+
+fn format_entries(f: &mut std::fmt::Formatter<'_>, entries: &[(&str, String)]) -> std::fmt::Result {
+    let strings: Vec<_> = entries
+        .iter()
+        .filter(|(_, value)| !value.is_empty())
+        .map(|(key, value)| format!("{}: {value}", key.bold()))
+        .collect();
+    write!(f, "{}", strings.join("\n"))
+}
+
+impl Display for JobsetInput {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        format_entries(
+            f,
+            &[
+                ("input", self.name.clone().unwrap_or_default()),
+                ("type", self.input_type.clone().unwrap_or_default()),
+                ("value", self.jobsetinputalts.join(", ")),
+                ("notify committers", self.emailresponsible.to_string()),
+            ],
+        )
+    }
+}
+
+impl Display for JobsetConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let state = match self.enabled {
+            0 => "disabled",
+            2 => "one-shot",
+            3 => "one-at-a-time",
+            _ => "enabled",
+        };
+        let state = match self.hidden {
+            true => format!("{state}, hidden"),
+            false => state.into(),
+        };
+        let (expression_key, expression) = match (self.jobset_type, &self.flake) {
+            (1, Some(flake)) => ("flake", flake.clone()),
+            _ => (
+                "nix expression",
+                format!(
+                    "{} in input {}",
+                    self.nixexprpath.as_deref().unwrap_or("?"),
+                    self.nixexprinput.as_deref().unwrap_or("?")
+                ),
+            ),
+        };
+        let check_interval = match self.checkinterval {
+            0 => "disabled".into(),
+            x => format!("{x} seconds"),
+        };
+        let email = match self.enableemail {
+            true => "enabled",
+            false => "disabled",
+        };
+        format_entries(
+            f,
+            &[
+                ("state", state),
+                ("description", self.description.clone().unwrap_or_default()),
+                (expression_key, expression),
+                ("check interval", check_interval),
+                ("scheduling shares", self.schedulingshares.to_string()),
+                ("email notification", email.into()),
+                (
+                    "email override",
+                    self.emailoverride.clone().unwrap_or_default(),
+                ),
+                ("evaluations to keep", self.keepnr.to_string()),
+            ],
+        )
+    }
+}
+
+impl FlatRow for JobsetConfig {
+    const COLUMNS: &'static [&'static str] = &[
+        "project",
+        "name",
+        "description",
+        "enabled",
+        "hidden",
+        "type",
+        "flake",
+        "nixexprinput",
+        "nixexprpath",
+        "checkinterval",
+        "schedulingshares",
+        "keepnr",
+        "inputs",
+    ];
+
+    fn values(&self) -> Vec<String> {
+        let inputs: Vec<_> = self
+            .jobsetinputs
+            .iter()
+            .map(|(name, input)| format!("{name}={}", input.jobsetinputalts.join(", ")))
+            .collect();
+        vec![
+            cell(self.project.as_ref()),
+            cell(self.name.as_ref()),
+            cell(self.description.as_ref()),
+            self.enabled.to_string(),
+            self.hidden.to_string(),
+            self.jobset_type.to_string(),
+            cell(self.flake.as_ref()),
+            cell(self.nixexprinput.as_ref()),
+            cell(self.nixexprpath.as_ref()),
+            self.checkinterval.to_string(),
+            self.schedulingshares.to_string(),
+            self.keepnr.to_string(),
+            inputs.join("; "),
+        ]
+    }
+}
+
+#[test]
+fn format_jobset_config() {
+    let json = r#"{
+        "project": "nixpkgs", "name": "trunk", "description": "Nixpkgs on master",
+        "enabled": 1, "hidden": false, "type": 0, "flake": "",
+        "nixexprinput": "nixpkgs", "nixexprpath": "pkgs/top-level/release.nix",
+        "checkinterval": 14400, "schedulingshares": "5000", "enableemail": 0,
+        "emailoverride": "", "keepnr": 2,
+        "jobsetinputs": {
+            "nixpkgs": {
+                "name": "nixpkgs", "type": "git", "emailresponsible": false,
+                "jobsetinputalts": ["https://github.com/NixOS/nixpkgs.git master"]
+            },
+            "officialRelease": { "name": "officialRelease", "type": "boolean", "value": "false" }
+        }
+    }"#;
+    let config: JobsetConfig = serde_json::from_str(json).unwrap();
+    assert_eq!(config.jobsetinputs.len(), 2);
+    assert_eq!(
+        config.jobsetinputs["officialRelease"].jobsetinputalts,
+        ["false"]
+    );
+    assert_eq!(
+        config.input_url(),
+        Some("https://github.com/NixOS/nixpkgs.git")
+    );
+    assert_snapshot!(config.to_string(), @r#"
+        [1mstate[0m: enabled
+        [1mdescription[0m: Nixpkgs on master
+        [1mnix expression[0m: pkgs/top-level/release.nix in input nixpkgs
+        [1mcheck interval[0m: 14400 seconds
+        [1mscheduling shares[0m: 5000
+        [1memail notification[0m: disabled
+        [1mevaluations to keep[0m: 2
+    "#);
+    assert_snapshot!(config.jobsetinputs["nixpkgs"].to_string(), @r#"
+        [1minput[0m: nixpkgs
+        [1mtype[0m: git
+        [1mvalue[0m: https://github.com/NixOS/nixpkgs.git master
+        [1mnotify committers[0m: false
+    "#);
+}
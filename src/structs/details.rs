@@ -0,0 +1,213 @@
+use colored::{ColoredString, Colorize};
+use indexmap::IndexMap;
+use schemars::JsonSchema;
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_with::skip_serializing_none;
+
+use crate::{constants, BuildStatus, ShowHydraStatus, StatusIcon};
+
+/// Details of a single build, as provided by Hydra's JSON API from
+/// urls such as <https://hydra.nixos.org/build/290062156>.
+#[skip_serializing_none]
+#[derive(Serialize, JsonSchema, Deserialize, Debug, Default, Clone)]
+pub(crate) struct BuildDetails {
+    pub(crate) id: u64,
+    pub(crate) project: Option<String>,
+    pub(crate) jobset: Option<String>,
+    pub(crate) job: Option<String>,
+    pub(crate) system: Option<String>,
+    pub(crate) nixname: Option<String>,
+    #[serde(default, deserialize_with = "bool_from_int")]
+    pub(crate) finished: bool,
+    pub(crate) buildstatus: Option<u64>,
+    pub(crate) timestamp: Option<i64>,
+    pub(crate) starttime: Option<i64>,
+    pub(crate) stoptime: Option<i64>,
+    pub(crate) drvpath: Option<String>,
+    #[serde(default)]
+    pub(crate) buildoutputs: IndexMap<String, BuildOutput>,
+    #[serde(default)]
+    pub(crate) jobsetevals: Vec<u64>,
+}
+
+/// A single output of a build, e.g. `out`, `dev` or `man`.
+#[derive(Serialize, JsonSchema, Deserialize, Debug, Default, Clone)]
+pub(crate) struct BuildOutput {
+    pub(crate) path: String,
+}
+
+/// Hydra's JSON API encodes booleans either as `true` / `false` or as `1` / `0`.
+pub(crate) fn bool_from_int<'de, D: Deserializer<'de>>(deserializer: D) -> Result<bool, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum BoolOrInt {
+        Bool(bool),
+        Int(i64),
+    }
+    Ok(match Option::<BoolOrInt>::deserialize(deserializer)? {
+        Some(BoolOrInt::Bool(x)) => x,
+        Some(BoolOrInt::Int(x)) => x != 0,
+        None => false,
+    })
+}
+
+/// Hydra's JSON API may encode counts either as integers or as strings.
+pub(crate) fn u64_from_int_or_str<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<u64, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum IntOrStr {
+        Int(u64),
+        Str(String),
+    }
+    match Option::<IntOrStr>::deserialize(deserializer)? {
+        Some(IntOrStr::Int(x)) => Ok(x),
+        Some(IntOrStr::Str(x)) => x.trim().parse().map_err(serde::de::Error::custom),
+        None => Ok(0),
+    }
+}
+
+impl ShowHydraStatus for BuildDetails {
+    fn format_as_vec(&self) -> Vec<ColoredString> {
+        let (icon, status) = self.status();
+        let icon = ColoredString::from(&icon);
+        let evals: Vec<_> = self.jobsetevals.iter().map(u64::to_string).collect();
+        let evals = match evals.as_slice() {
+            [] => "".into(),
+            [eval] => format!("eval {eval}"),
+            evals => format!("evals {}", evals.join(", ")),
+        };
+        let date = self
+            .to_build_status()
+            .timestamp
+            .unwrap_or_default()
+            .split_once('T')
+            .unwrap_or_default()
+            .0
+            .to_string();
+        vec![
+            format!("{icon} {status}").into(),
+            self.full_job_name().into(),
+            evals.into(),
+            date.into(),
+            self.url().dimmed(),
+        ]
+    }
+}
+
+/// Texts of the `buildstatus` codes of finished builds,
+/// see `buildStatusToString` in Hydra's `common.tt`.
+const BUILD_STATUSES: [(u64, &str); 11] = [
+    (0, "Succeeded"),
+    (1, "Failed"),
+    (2, "Dependency failed"),
+    (3, "Aborted"),
+    (4, "Cancelled"),
+    (6, "Failed with output"),
+    (7, "Timed out"),
+    (9, "Unsupported system type"),
+    (10, "Log limit exceeded"),
+    (11, "Output limit exceeded"),
+    (12, "Non-deterministic build"),
+];
+
+/// Translates the text that Hydra displays back into the `buildstatus` code,
+/// or returns `None` for unfinished builds and unknown texts.
+pub(crate) fn buildstatus_code(status: &str) -> Option<u64> {
+    BUILD_STATUSES
+        .iter()
+        .find(|(_, x)| *x == status)
+        .map(|(code, _)| *code)
+}
+
+impl BuildDetails {
+    pub(crate) fn url(&self) -> String {
+        format!("{}/build/{}", &*constants::HYDRA_CHECK_HOST_URL, self.id)
+    }
+
+    /// Full job name in the format of Hydra's search results,
+    /// i.e. `<project>:<jobset>:<job>`.
+    pub(crate) fn full_job_name(&self) -> String {
+        [&self.project, &self.jobset, &self.job]
+            .map(|x| x.as_deref().unwrap_or("?"))
+            .join(":")
+    }
+
+    /// Translates the `buildstatus` code into the text that Hydra displays.
+    /// See `buildStatusToString` in Hydra's `common.tt`.
+    pub(crate) fn status(&self) -> (StatusIcon, &'static str) {
+        if !self.finished {
+            return (StatusIcon::Queued, "Queued");
+        }
+        let status = BUILD_STATUSES
+            .iter()
+            .find(|(code, _)| self.buildstatus == Some(*code))
+            .map_or("Failed", |(_, status)| status);
+        match self.buildstatus {
+            Some(0) => (StatusIcon::Succeeded, status),
+            Some(4) => (StatusIcon::Cancelled, status),
+            _ => (StatusIcon::Failed, status),
+        }
+    }
+
+    /// Converts the details into a [`BuildStatus`] so that it can be
+    /// printed in the same way as the builds from the html tables.
+    pub(crate) fn to_build_status(&self) -> BuildStatus {
+        let (icon, status) = self.status();
+        let timestamp = self
+            .stoptime
+            .or(self.timestamp)
+            .and_then(|x| chrono::DateTime::from_timestamp(x, 0))
+            .map(|x| x.to_rfc3339_opts(chrono::SecondsFormat::Secs, true));
+        BuildStatus {
+            success: matches!(icon, StatusIcon::Succeeded),
+            icon,
+            status: status.into(),
+            timestamp,
+            build_id: Some(self.id.to_string()),
+            build_url: Some(self.url()),
+            name: self.nixname.clone(),
+            arch: self.system.clone(),
+            evals: true,
+            job_name: Some(self.full_job_name()),
+            cache: None,
+            reproduce: None,
+            pin: None,
+            suggestions: None,
+        }
+    }
+}
+
+#[test]
+fn deserialize_build_details() {
+    let json = r#"{
+        "id": 290062156,
+        "project": "nixpkgs",
+        "jobset": "unstable",
+        "job": "hello.x86_64-linux",
+        "system": "x86_64-linux",
+        "nixname": "hello-2.12.1",
+        "finished": 1,
+        "buildstatus": 2,
+        "stoptime": 1742511337,
+        "drvpath": "/nix/store/8rl9ydmr7vyvyx4a0pbz6npc8bn3kmrr-hello-2.12.1.drv",
+        "buildoutputs": {
+            "out": { "path": "/nix/store/1q8w6gl1ll0mwfkqc3c2yx005s6wwfrl-hello-2.12.1" }
+        },
+        "jobsetevals": [1812345],
+        "buildproducts": {}
+    }"#;
+    let details: BuildDetails = serde_json::from_str(json).unwrap();
+    assert_eq!(
+        details.full_job_name(),
+        "nixpkgs:unstable:hello.x86_64-linux"
+    );
+    let status = details.to_build_status();
+    assert!(!status.success);
+    assert_eq!(status.status, "Dependency failed");
+    assert_eq!(buildstatus_code(&status.status), Some(2));
+    assert_eq!(buildstatus_code("Queued"), None);
+    assert_eq!(status.timestamp.as_deref(), Some("2025-03-20T22:55:37Z"));
+    assert!(details.buildoutputs["out"].path.ends_with("-hello-2.12.1"));
+}
@@ -1,25 +1,101 @@
 use colored::{ColoredString, Colorize};
 use log::info;
+use regex::Regex;
+use schemars::JsonSchema;
 use serde::Serialize;
 use serde_with::skip_serializing_none;
+use std::{fmt::Display, sync::LazyLock};
 
-use crate::{constants, ShowHydraStatus, StatusIcon};
+use crate::{
+    constants,
+    formats::{cell, FlatRow},
+    pagination, ShowHydraStatus, StatusIcon,
+};
 
 /// Specification for a single Hydra evaluation, with an optional filter.
 /// Should only be constructed with [`Evaluation::guess_from_spec`]
 /// to ensure a correct [`Evaluation::spec`], thus marked [`non_exhaustive`].
 #[non_exhaustive]
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Serialize, JsonSchema)]
 pub(crate) struct Evaluation {
     #[serde(skip)]
     pub(crate) spec: String,
     pub(crate) id: u64,
     pub(crate) filter: Option<String>,
     pub(crate) long: bool,
+    /// Criteria to find the evaluation with, if the `id` is unknown (`0`)
+    #[serde(skip)]
+    pub(crate) search: Option<EvalSearch>,
+}
+
+/// Criteria for finding an evaluation in the recent evaluations of a jobset.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum EvalSearch {
+    /// `rev:<commit>`: the evaluation with a matching input revision
+    Revision(String),
+    /// `@<date>`: the newest evaluation at or before the (unix) timestamp
+    Date(i64),
+}
+
+impl Display for EvalSearch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Revision(rev) => write!(f, "revision '{rev}'"),
+            Self::Date(timestamp) => match chrono::DateTime::from_timestamp(*timestamp, 0) {
+                Some(date) => write!(f, "date '{}'", date.format("%Y-%m-%d %H:%M:%S UTC")),
+                None => write!(f, "timestamp '{timestamp}'"),
+            },
+        }
+    }
+}
+
+/// An (abbreviated) git revision, e.g. of `rev:<commit>`, at least as long
+/// as the revisions shown by Hydra to avoid matching unrelated commits
+static REVISION: LazyLock<Regex> = LazyLock::new(|| Regex::new("^[0-9a-f]{7,40}$").unwrap());
+
+impl EvalSearch {
+    /// Parses `rev:<commit>` or `@<date>`, returning `None` for other specs.
+    fn parse(spec: &str) -> Option<anyhow::Result<Self>> {
+        if let Some(rev) = spec.strip_prefix("rev:") {
+            let rev = rev.trim().to_lowercase();
+            if !REVISION.is_match(&rev) {
+                let err = anyhow::anyhow!("'{rev}' is not a valid (abbreviated) git revision");
+                return Some(Err(err));
+            }
+            return Some(Ok(Self::Revision(rev)));
+        }
+        let date = spec.strip_prefix('@')?;
+        Some(pagination::parse_until(date).map(Self::Date))
+    }
+
+    /// Checks if the evaluation status satisfies the search criteria,
+    /// i.e. its `short_rev` or any revision of its input changes,
+    /// which are listed as `<input> → <revision>`.
+    pub(crate) fn matches(&self, eval: &EvalStatus) -> bool {
+        match self {
+            Self::Revision(rev) => {
+                let input_revs = eval.input_changes.iter().flat_map(|x| {
+                    x.split('→')
+                        .skip(1)
+                        .filter_map(|x| x.split_whitespace().next())
+                });
+                eval.short_rev
+                    .as_deref()
+                    .into_iter()
+                    .chain(input_revs)
+                    .map(|x| x.trim().to_lowercase())
+                    .filter(|x| REVISION.is_match(x))
+                    .any(|x| rev.starts_with(&x) || x.starts_with(rev.as_str()))
+            }
+            Self::Date(date) => eval
+                .timestamp
+                .is_some_and(|timestamp| i64::try_from(timestamp).is_ok_and(|x| x <= *date)),
+        }
+    }
 }
 
 #[skip_serializing_none]
-#[derive(Serialize, Debug, Default, Clone)]
+#[derive(Serialize, JsonSchema, Debug, Default, Clone)]
 /// Status of a single evaluation, can be serialized to a JSON entry
 pub(crate) struct EvalStatus {
     pub(crate) icon: StatusIcon,
@@ -85,17 +161,67 @@ impl ShowHydraStatus for EvalStatus {
     }
 }
 
+impl FlatRow for EvalStatus {
+    const COLUMNS: &'static [&'static str] = &[
+        "id",
+        "status",
+        "datetime",
+        "short_rev",
+        "succeeded",
+        "failed",
+        "queued",
+        "delta",
+        "url",
+    ];
+
+    fn values(&self) -> Vec<String> {
+        vec![
+            cell(self.id),
+            self.status.clone(),
+            cell(self.datetime.as_ref()),
+            cell(self.short_rev.as_ref()),
+            cell(self.succeeded),
+            cell(self.failed),
+            cell(self.queued),
+            cell(self.delta.as_ref()),
+            cell(self.url.as_ref()),
+        ]
+    }
+
+    fn icon(&self) -> Option<&StatusIcon> {
+        Some(&self.icon)
+    }
+}
+
 impl Evaluation {
     /// Parses an evaluation from a plain text specification.
-    pub(crate) fn guess_from_spec(spec: &str, long: bool) -> Self {
+    /// If the specification has no package filter, the `default_filter` is
+    /// used, or else [`constants::DEFAULT_EVALUATION_FILTER`].
+    pub(crate) fn guess_from_spec(
+        spec: &str,
+        long: bool,
+        default_filter: Option<&str>,
+    ) -> anyhow::Result<Self> {
         let spec = spec.trim();
 
         let mut split_spec = spec.splitn(2, '/');
         let id = split_spec.next().unwrap().trim();
         let filter = split_spec.next();
 
+        let search = match EvalSearch::parse(id) {
+            Some(Ok(search)) => Some(search),
+            Some(Err(err)) => anyhow::bail!("could not parse --eval '{spec}': {err}"),
+            None => None,
+        };
+        let id_spec = match search {
+            Some(_) => id.to_string(),
+            None => "".into(),
+        };
+
         let (id, filter) = match id.parse() {
             Ok(x) => (x, filter),
+            // for the latest eval, or an eval that is searched for
+            Err(_) if search.is_some() => (0u64, filter),
             Err(_) => (
                 0u64, // for the latest eval
                 match id.is_empty() {
@@ -104,8 +230,16 @@ impl Evaluation {
                 },
             ),
         };
-        let filter = match filter {
-            None => {
+        let id_spec = match (id, id_spec.is_empty()) {
+            (0, false) => id_spec,
+            (x, _) => x.to_string(),
+        };
+        let filter = match (filter, default_filter) {
+            (None, Some(default)) => {
+                info!("the filter '/{default}' is derived from --match, --glob or --system");
+                Some(default.into())
+            }
+            (None, None) => {
                 let default = constants::DEFAULT_EVALUATION_FILTER.to_string();
                 info!(
                     "{}, so the default filter '/{default}' is used {}",
@@ -115,21 +249,21 @@ impl Evaluation {
                     "specify another filter with --eval '{}', {}\n",
                     format!(
                         "{}/<filter>",
-                        match id {
-                            0 => "<id>".into(),
-                            x => x.to_string(),
+                        match (id, &search) {
+                            (0, None) => "<id>".into(),
+                            _ => id_spec.clone(),
                         }
                     ),
                     "or force an empty filter with a trailing slash '/'",
                 );
                 Some(default)
             }
-            Some(x) if x.trim().is_empty() => None,
-            Some(x) => Some(x.into()),
+            (Some(x), _) if x.trim().is_empty() => None,
+            (Some(x), _) => Some(x.into()),
         };
-        Self {
+        Ok(Self {
             spec: format!(
-                "{id}{}",
+                "{id_spec}{}",
                 match &filter {
                     Some(x) => format!("/{x}"),
                     None => "".into(),
@@ -138,7 +272,8 @@ impl Evaluation {
             id,
             filter,
             long,
-        }
+            search,
+        })
     }
 }
 
@@ -156,8 +291,63 @@ fn guess_eval_from_spec() {
         ("rustc", 0, Some("rustc".into())),
         ("weird/filter", 0, Some("weird/filter".into())),
     ] {
-        let eval = Evaluation::guess_from_spec(spec, false);
+        let eval = Evaluation::guess_from_spec(spec, false, None).unwrap();
         println!("{eval:?}");
         assert!(eval.id == id && eval.filter == filter);
     }
+    let eval = Evaluation::guess_from_spec("123456", true, Some("python312Packages.")).unwrap();
+    assert_eq!(eval.filter.as_deref(), Some("python312Packages."));
+    assert_eq!(eval.spec, "123456/python312Packages.");
+}
+
+#[test]
+fn guess_eval_search_from_spec() {
+    let default_filter = constants::DEFAULT_EVALUATION_FILTER;
+    for (spec, search, filter) in [
+        (
+            "rev:1E9E641A",
+            EvalSearch::Revision("1e9e641a".into()),
+            Some(default_filter.into()),
+        ),
+        (
+            "rev:1e9e641a/rustc",
+            EvalSearch::Revision("1e9e641a".into()),
+            Some("rustc".into()),
+        ),
+        ("@2025-06-01/", EvalSearch::Date(1_748_822_399), None),
+    ] {
+        let eval = Evaluation::guess_from_spec(spec, false, None).unwrap();
+        println!("{eval:?}");
+        assert!(eval.id == 0 && eval.search == Some(search.clone()) && eval.filter == filter);
+        assert!(eval.spec.starts_with(spec.trim_end_matches('/')));
+    }
+    let err = Evaluation::guess_from_spec("rev:main/rustc", false, None).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "could not parse --eval 'rev:main/rustc': 'main' is not a valid (abbreviated) git revision"
+    );
+    let err = Evaluation::guess_from_spec("rev:1e9e/rustc", false, None).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "could not parse --eval 'rev:1e9e/rustc': '1e9e' is not a valid (abbreviated) git revision"
+    );
+    let eval = EvalStatus {
+        short_rev: Some("1e9e641".into()),
+        input_changes: Some("nixpkgs → 1e9e641 nixos-hardware → 8a7d0ee".into()),
+        timestamp: Some(1_748_736_000),
+        ..Default::default()
+    };
+    assert!(EvalSearch::Revision("1e9e641a3fc1".into()).matches(&eval));
+    assert!(EvalSearch::Revision("1e9e641".into()).matches(&eval));
+    assert!(!EvalSearch::Revision("8c4dc69b".into()).matches(&eval));
+    assert!(EvalSearch::Revision("8a7d0eec".into()).matches(&eval));
+    assert!(!EvalSearch::Revision("nixpkgs".into()).matches(&eval));
+    assert!(EvalSearch::Date(1_748_822_399).matches(&eval));
+    assert!(!EvalSearch::Date(1_700_000_000).matches(&eval));
+    let eval = EvalStatus {
+        input_changes: Some("nixpkgs → 1e9e641 (deadbeef0 was reverted)".into()),
+        ..Default::default()
+    };
+    assert!(EvalSearch::Revision("1e9e641a3fc1".into()).matches(&eval));
+    assert!(!EvalSearch::Revision("deadbeef0".into()).matches(&eval));
 }
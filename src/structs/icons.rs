@@ -1,7 +1,11 @@
 use colored::{ColoredString, Colorize};
-use serde_with::SerializeDisplay;
+use schemars::JsonSchema;
+use serde::Serialize;
 
-#[derive(SerializeDisplay, Debug, Clone, Default)]
+/// Status of a build or an evaluation, printed as an icon and
+/// serialized as its (snake_case) name, e.g. `"succeeded"`
+#[derive(Serialize, JsonSchema, Debug, Clone, Default)]
+#[serde(rename_all = "snake_case")]
 pub(crate) enum StatusIcon {
     Succeeded,
     Failed,
@@ -36,7 +40,9 @@ use insta::assert_snapshot;
 #[test]
 fn serialize_and_colorize_icons() {
     let success_icon = serde_json::to_string(&StatusIcon::Succeeded).unwrap();
-    debug_assert_eq!(success_icon, r#""✔""#);
+    debug_assert_eq!(success_icon, r#""succeeded""#);
+    // the table still prints the glyph
+    debug_assert_eq!(StatusIcon::Succeeded.to_string(), "✔");
     let colored_icon = ColoredString::from(&StatusIcon::Queued);
     assert_snapshot!(colored_icon.to_string(), @"[33m⧖[0m");
 }
@@ -1,5 +1,6 @@
 use anyhow::bail;
 use colored::Colorize;
+use schemars::JsonSchema;
 use scraper::ElementRef;
 use serde::Serialize;
 use serde_json::Value;
@@ -12,7 +13,7 @@ use insta::assert_snapshot;
 use crate::{is_skipable_row, SoupFind};
 
 #[skip_serializing_none]
-#[derive(Serialize, Clone, Default, Debug)]
+#[derive(Serialize, JsonSchema, Clone, Default, Debug)]
 /// Inputs of a given evaluation (which is also the inputs of a package build)
 pub(crate) struct EvalInput {
     pub(crate) name: Option<String>,
@@ -85,6 +86,68 @@ impl EvalInput {
     }
 }
 
+/// A git input pinned to the revision that Hydra evaluated,
+/// such as nixpkgs in the inputs of an evaluation or a build
+#[derive(Serialize, JsonSchema, Clone, Debug)]
+pub(crate) struct PinnedInput {
+    pub(crate) url: String,
+    pub(crate) revision: String,
+    /// Channel release of the revision, e.g. on <https://releases.nixos.org>,
+    /// which provides a tarball of the pinned nixpkgs
+    pub(crate) release_url: Option<String>,
+}
+
+impl PinnedInput {
+    /// Finds the `nixpkgs` input, or else the first git input with a revision.
+    pub(crate) fn from_inputs(inputs: &[EvalInput]) -> Option<Self> {
+        let pinned = |input: &EvalInput| {
+            let url = input.value.as_deref()?.split_whitespace().next()?;
+            let revision = input.revision.as_deref()?;
+            Some(Self {
+                url: url.trim_end_matches('/').into(),
+                revision: revision.into(),
+                release_url: None,
+            })
+        };
+        inputs
+            .iter()
+            .filter(|x| x.name.as_deref() == Some("nixpkgs"))
+            .chain(inputs)
+            .find_map(pinned)
+    }
+
+    /// Returns the owner and the name of a repository on GitHub.
+    pub(crate) fn github(&self) -> Option<(&str, &str)> {
+        let path = self.url.strip_prefix("https://github.com/")?;
+        let path = path.strip_suffix(".git").unwrap_or(path);
+        path.split_once('/')
+    }
+
+    /// Url of the source tarball of the revision, only known for
+    /// channel releases and GitHub.
+    pub(crate) fn tarball_url(&self) -> Option<String> {
+        if let Some(release_url) = &self.release_url {
+            return Some(format!("{release_url}/nixexprs.tar.xz"));
+        }
+        let (owner, repo) = self.github()?;
+        Some(format!(
+            "https://github.com/{owner}/{repo}/archive/{}.tar.gz",
+            self.revision
+        ))
+    }
+
+    /// Flake reference of the revision, e.g. `github:NixOS/nixpkgs/<rev>`.
+    pub(crate) fn flake_ref(&self) -> String {
+        if let Some(tarball_url) = self.release_url.as_ref().and(self.tarball_url()) {
+            return tarball_url;
+        }
+        match self.github() {
+            Some((owner, repo)) => format!("github:{owner}/{repo}/{}", self.revision),
+            None => format!("git+{}?rev={}", self.url, self.revision),
+        }
+    }
+}
+
 #[test]
 fn format_eval_input() {
     let eval_input = EvalInput {
@@ -101,4 +164,13 @@ fn format_eval_input() {
         [1mrevision[0m: 1e9e641a3fc1b22fbdb823a99d8ff96692cc4fba
         [1mstore_path[0m: /nix/store/ln479gq56q3kyzyl0mm00xglpmfpzqx4-source
     "#);
+    let pinned = PinnedInput::from_inputs(&[eval_input]).unwrap();
+    assert_eq!(
+        pinned.tarball_url().unwrap(),
+        "https://github.com/nixos/nixpkgs/archive/1e9e641a3fc1b22fbdb823a99d8ff96692cc4fba.tar.gz"
+    );
+    assert_eq!(
+        pinned.flake_ref(),
+        "github:nixos/nixpkgs/1e9e641a3fc1b22fbdb823a99d8ff96692cc4fba"
+    );
 }
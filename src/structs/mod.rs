@@ -1,11 +1,15 @@
 mod build;
+mod config;
+mod details;
 mod eval;
 mod icons;
 mod inputs;
 mod release;
 
 pub(crate) use build::BuildStatus;
-pub(crate) use eval::{EvalStatus, Evaluation};
+pub(crate) use config::JobsetConfig;
+pub(crate) use details::{bool_from_int, buildstatus_code, u64_from_int_or_str, BuildDetails};
+pub(crate) use eval::{EvalSearch, EvalStatus, Evaluation};
 pub(crate) use icons::StatusIcon;
-pub(crate) use inputs::EvalInput;
+pub(crate) use inputs::{EvalInput, PinnedInput};
 pub(crate) use release::ReleaseStatus;
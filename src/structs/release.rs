@@ -1,17 +1,23 @@
-use std::fmt::Display;
+use std::{collections::VecDeque, fmt::Display};
 
 use colored::{ColoredString, Colorize};
+use schemars::JsonSchema;
 use serde::Serialize;
 use serde_with::skip_serializing_none;
 use yansi::hyperlink::HyperlinkExt;
 
-use crate::{constants, BuildStatus, EvalStatus, ShowHydraStatus, StatusIcon};
+use super::PinnedInput;
+use crate::{
+    constants,
+    formats::{cell, FlatRow},
+    BuildStatus, EvalStatus, ShowHydraStatus, StatusIcon,
+};
 
 /// Container for the evaluation and test build status of a (potential)
 /// channel release.
 #[non_exhaustive]
 #[skip_serializing_none]
-#[derive(Serialize, Debug, Default, Clone)]
+#[derive(Serialize, JsonSchema, Debug, Default, Clone)]
 pub(crate) struct ReleaseStatus {
     pub(crate) eval: EvalStatus,
     pub(crate) test: BuildStatus,
@@ -19,6 +25,8 @@ pub(crate) struct ReleaseStatus {
     pub(crate) report_url: Option<String>,
     pub(crate) git_revision: Option<String>,
     pub(crate) git_revision_url: Option<String>,
+    /// Snippet that pins nixpkgs to the release, see `--pin-format`
+    pub(crate) pin: Option<String>,
 }
 
 impl ShowHydraStatus for ReleaseStatus {
@@ -113,6 +121,32 @@ impl ShowHydraStatus for ReleaseStatus {
     }
 }
 
+impl FlatRow for ReleaseStatus {
+    const COLUMNS: &'static [&'static str] = &[
+        "eval",
+        "datetime",
+        "git_revision",
+        "test_status",
+        "test_build_id",
+        "release_url",
+    ];
+
+    fn values(&self) -> Vec<String> {
+        vec![
+            cell(self.eval.id),
+            cell(self.eval.datetime.as_ref()),
+            cell(self.git_revision.as_ref()),
+            self.test.status.clone(),
+            cell(self.test.build_id.as_ref()),
+            cell(self.release_url.as_ref()),
+        ]
+    }
+
+    fn icon(&self) -> Option<&StatusIcon> {
+        Some(&self.test.icon)
+    }
+}
+
 impl ReleaseStatus {
     pub(crate) fn new(
         eval: EvalStatus,
@@ -177,6 +211,84 @@ impl ReleaseStatus {
             report_url,
             git_revision,
             git_revision_url,
+            pin: None,
+        }
+    }
+
+    /// Pairs the evaluations of a channel jobset with the builds of its
+    /// release tests (e.g. `tested` or `unstable`), matched by the revision
+    /// in the name of the test build. Evaluations without tests are skipped.
+    pub(crate) fn pair(
+        evals: Vec<EvalStatus>,
+        tests: &[BuildStatus],
+        channel: &str,
+        jobset: &str,
+    ) -> Vec<Self> {
+        // mutable refs that is quick to remove from the front
+        let mut test_builds: VecDeque<&BuildStatus> = tests.iter().collect();
+
+        // if _all_ evals appear to be unfinished, it's likely that the
+        // instance is being rebooted, and we will always link to the
+        // releases as it's more practical
+        let always_link = evals.iter().all(|eval| !eval.finished.unwrap_or_default());
+
+        // this captures `test_builds` mutably but it does _not_ need
+        // to be marked as `mut` because it is moved into .filter_map()
+        // and re-borrowed as mut by them.
+        let filter_eval = |eval: EvalStatus| {
+            let short_rev = eval.short_rev.as_deref().unwrap_or_default();
+            for index in 0..test_builds.len() {
+                if test_builds[index]
+                    .name
+                    .as_deref()
+                    .unwrap_or_default()
+                    .contains(short_rev)
+                {
+                    let test = test_builds.remove(index)?.clone();
+                    return Some(Self::new(eval, test, channel, jobset, always_link));
+                }
+            }
+            None
+        };
+        evals.into_iter().filter_map(filter_eval).collect()
+    }
+
+    /// Pins the nixpkgs input at `url` to the tarball of the release,
+    /// if the release is finished and its tests succeeded. Whether the
+    /// release is published yet is up to the caller to check.
+    pub(crate) fn pinned_input(&self, url: &str) -> Option<PinnedInput> {
+        if !(self.test.success && self.eval.finished.unwrap_or_default()) {
+            return None;
         }
+        Some(PinnedInput {
+            url: url.trim_end_matches('/').into(),
+            revision: self.git_revision.clone()?,
+            release_url: Some(self.release_url.clone()?),
+        })
     }
 }
+
+#[test]
+fn pin_finished_releases() {
+    let release = |finished: bool, success: bool| ReleaseStatus {
+        eval: EvalStatus {
+            finished: Some(finished),
+            ..Default::default()
+        },
+        test: BuildStatus {
+            success,
+            ..Default::default()
+        },
+        release_url: Some(
+            "https://releases.nixos.org/nixos/unstable/nixos-25.11pre854150.5d8f4beac036".into(),
+        ),
+        git_revision: Some("5d8f4beac036".into()),
+        ..Default::default()
+    };
+    let url = "https://git.example.org/nixpkgs.git/";
+    let pinned = release(true, true).pinned_input(url).unwrap();
+    assert_eq!(pinned.url, "https://git.example.org/nixpkgs.git");
+    assert_eq!(pinned.revision, "5d8f4beac036");
+    assert!(release(false, true).pinned_input(url).is_none());
+    assert!(release(true, false).pinned_input(url).is_none());
+}